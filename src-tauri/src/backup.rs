@@ -0,0 +1,177 @@
+use chrono::{DateTime, FixedOffset};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::models::{
+    feeds::{self, FeedStatus, FeedToCreate, FeedToUpdate},
+    items::{self, ItemReadOption, ItemStatus, ItemToCreate},
+};
+
+/// Bumped whenever the document changes in a way older versions cannot read.
+const STATE_VERSION: u32 = 1;
+
+/// Everything needed to carry the subscriptions and what was read or saved to another machine.
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: u32,
+    feeds: Vec<FeedState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FeedState {
+    title: String,
+    user_title: Option<String>,
+    link: String,
+    status: FeedStatus,
+    fetch_interval_minutes: i32,
+    items: Vec<ItemState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemState {
+    guid: Option<String>,
+    author: Option<String>,
+    title: String,
+    description: String,
+    /// Missing from documents exported before items knew, when all content was HTML.
+    #[serde(default = "content_is_html_by_default")]
+    content_is_html: bool,
+    summary: Option<String>,
+    link: String,
+    status: ItemStatus,
+    is_saved: bool,
+    /// Missing from documents exported before filter rules could hide items.
+    #[serde(default)]
+    is_hidden: bool,
+    published_at: DateTime<FixedOffset>,
+}
+
+fn content_is_html_by_default() -> bool {
+    true
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub feeds_created: usize,
+    pub items_created: usize,
+    pub items_updated: usize,
+}
+
+/// Serializes every feed that is not archived, with its items and their read, saved and hidden
+/// flags, into a JSON document for `import_state`.
+pub fn export_state(db: &Connection) -> Result<String> {
+    let mut state = State {
+        version: STATE_VERSION,
+        feeds: vec![],
+    };
+
+    for feed in feeds::read_including_unsubscribed(db)? {
+        let items = items::read_all(
+            db,
+            &ItemReadOption {
+                feed: Some(feed.id),
+                include_hidden: true,
+                ..Default::default()
+            },
+        )?
+        .into_iter()
+        .map(|x| ItemState {
+            guid: x.guid,
+            author: x.author,
+            title: x.title,
+            description: x.description,
+            content_is_html: x.content_is_html,
+            summary: x.summary,
+            link: x.link,
+            status: x.status,
+            is_saved: x.is_saved,
+            is_hidden: x.is_hidden,
+            published_at: x.published_at,
+        })
+        .collect();
+
+        state.feeds.push(FeedState {
+            title: feed.title,
+            user_title: feed.user_title,
+            link: feed.link,
+            status: feed.status,
+            fetch_interval_minutes: feed.fetch_interval_minutes,
+            items,
+        });
+    }
+
+    serde_json::to_string_pretty(&state).map_err(|_| Error::Unknown)
+}
+
+/// Merges a document made by `export_state` into the database, all at once or not at all. Feeds are
+/// matched by link and items by guid, or by link for items without one, so importing the same
+/// document twice changes nothing the second time.
+pub fn import_state(db: &Connection, json: &str) -> Result<ImportSummary> {
+    let state: State = serde_json::from_str(json).map_err(|_| Error::BackupParsingFailure)?;
+    if state.version > STATE_VERSION {
+        return Err(Error::BackupParsingFailure);
+    }
+
+    let tx = db.unchecked_transaction()?;
+    let mut summary = ImportSummary::default();
+
+    for feed in state.feeds {
+        let arg = FeedToCreate {
+            title: feed.title,
+            link: feed.link,
+        };
+        let existed = feeds::read_by_link(&tx, &arg.link)?.is_some();
+        let id = feeds::upsert_by_link(&tx, &arg)?.id;
+        if !existed {
+            summary.feeds_created += 1;
+        }
+
+        let mut update = FeedToUpdate::for_id(id)
+            .status(feed.status)
+            .fetch_interval_minutes(feed.fetch_interval_minutes);
+        if let Some(user_title) = feed.user_title {
+            update = update.title(user_title);
+        }
+        feeds::update_with(&tx, &update.build())?;
+
+        for item in feed.items {
+            match items::find_id(&tx, id, item.guid.as_deref(), &item.link)? {
+                Some(existing) => {
+                    summary.items_updated += items::update_state(
+                        &tx,
+                        existing,
+                        &item.status,
+                        item.is_saved,
+                        item.is_hidden,
+                    )?;
+                }
+                None => {
+                    items::create(
+                        &tx,
+                        &ItemToCreate {
+                            guid: item.guid,
+                            author: item.author,
+                            title: item.title,
+                            description: item.description,
+                            content_is_html: item.content_is_html,
+                            is_hidden: item.is_hidden,
+                            summary: item.summary,
+                            link: item.link,
+                            status: item.status,
+                            published_at: item.published_at,
+                            feed: id,
+                        },
+                    )?;
+                    if item.is_saved {
+                        items::toggle_star(&tx, tx.last_insert_rowid() as i32)?;
+                    }
+                    summary.items_created += 1;
+                }
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(summary)
+}
@@ -0,0 +1,85 @@
+use regex::Regex;
+use serde::Serialize;
+use std::{sync::Mutex, thread};
+
+use crate::error::{Error, Result};
+use crate::models::database::DbPool;
+use crate::producer::subscribe_by_url;
+use crate::util::normalize_url;
+
+/// What importing bookmarks came to, in the order the bookmarks were listed.
+#[derive(Serialize, Debug, Default)]
+pub struct BookmarkImport {
+    /// The bookmarks that led to a feed, each with the id of the feed, which may have been
+    /// subscribed to already.
+    pub subscribed: Vec<(String, i32)>,
+    /// The bookmarks that did not, each with why.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Subscribes to the feed behind each bookmark of a browser's bookmarks file, where the bookmark
+/// is either a feed or a page advertising one. At most `concurrency` bookmarks are fetched at a
+/// time, and one that leads nowhere does not stop the others.
+pub fn import_bookmarks(
+    pool: &DbPool,
+    html: &str,
+    concurrency: usize,
+    proxy: Option<&str>,
+) -> Result<BookmarkImport> {
+    let queue = Mutex::new(parse_bookmarks(html).into_iter().enumerate());
+    let results = Mutex::new(vec![]);
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1) {
+            scope.spawn(|| loop {
+                let Some((i, link)) = queue.lock().unwrap().next() else {
+                    break;
+                };
+
+                let result = pool
+                    .get()
+                    .map_err(Error::from)
+                    .and_then(|db| subscribe_by_url(&db, &link, proxy))
+                    .map(|x| x.id);
+                results.lock().unwrap().push((i, link, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|x| x.0);
+
+    let mut import = BookmarkImport::default();
+    for (_, link, result) in results {
+        match result {
+            Ok(id) | Err(Error::AlreadyExists(id)) => import.subscribed.push((link, id)),
+            Err(e) => import.skipped.push((link, e.to_string())),
+        }
+    }
+
+    Ok(import)
+}
+
+/// Collects the web links bookmarked in a Netscape bookmarks file, as browsers export them, once
+/// each and in the order they are listed.
+pub fn parse_bookmarks(html: &str) -> Vec<String> {
+    let href =
+        Regex::new(r#"(?is)<a\s[^>]*?\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap();
+
+    let mut links: Vec<String> = vec![];
+    for x in href.captures_iter(html) {
+        let Some(link) = x.get(1).or(x.get(2)).or(x.get(3)) else {
+            continue;
+        };
+        let link = normalize_url(&link.as_str().trim().replace("&amp;", "&"));
+        let is_web = ["http://", "https://"]
+            .iter()
+            .any(|scheme| link.to_ascii_lowercase().starts_with(scheme));
+
+        if is_web && !links.contains(&link) {
+            links.push(link);
+        }
+    }
+
+    links
+}
@@ -1,70 +1,370 @@
-use tauri::State;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
 
 use crate::models::settings;
 use crate::models::settings::SettingKey;
 use crate::{
-    models::feeds::{self, Feed, FeedToCreate, FeedToUpdate},
-    producer::create_new_items,
-    syndication::fetch_feed_title,
+    bookmarks::{self, BookmarkImport},
+    error::Result,
+    markdown,
+    models::feeds::{
+        self, Favicon, Feed, FeedPayload, FeedStatus, FeedSummary, FeedToCreate, FeedToUpdate,
+    },
+    opml,
+    producer::{self, create_new_items, subscribe_by_url, RefreshReport},
+    syndication::{self, FeedProbe},
     DbState,
 };
 
+const REFRESH_CONCURRENCY: usize = 8;
+/// How many bookmarks are looked into at a time when importing them.
+const IMPORT_CONCURRENCY: usize = 4;
+
 #[tauri::command]
-pub fn create_feed(db_state: State<DbState>, arg: FeedToCreate) -> Result<String, String> {
-    let db = db_state.db.lock().unwrap();
+pub fn create_feed(db_state: State<DbState>, arg: FeedToCreate) -> Result<FeedPayload<Feed>> {
+    let db = db_state.db.get()?;
     let proxy = settings::read(&db, &SettingKey::Proxy)
         .map(|x| x.value)
         .ok();
-    let title = match fetch_feed_title(&arg.link, proxy.as_deref()) {
-        Ok(title) => title,
-        Err(err) => return Err(err.to_string()),
-    };
-    let arg = FeedToCreate {
-        title,
-        link: arg.link,
-    };
-
-    match feeds::create(&db, &arg) {
-        Ok(_) => {
-            let _ = create_new_items(&db, proxy.as_deref());
-            Ok("New feed added".to_string())
-        }
-        Err(err) => Err(err.to_string()),
-    }
+
+    let feed = subscribe_by_url(&db, &arg.link, proxy.as_deref())?;
+    let _ = create_new_items(&db, proxy.as_deref());
+
+    Ok(FeedPayload::new(feed))
 }
 
 #[tauri::command]
-pub fn read_all_feeds(db_state: State<DbState>) -> Result<Vec<Feed>, String> {
-    let db = db_state.db.lock().unwrap();
-    match feeds::read_all(&db) {
-        Ok(feeds) => Ok(feeds),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn probe_feed(db_state: State<DbState>, link: String) -> Result<FeedProbe> {
+    let db = db_state.db.get()?;
+    let proxy = settings::read(&db, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    syndication::probe_feed(&link, proxy.as_deref())
 }
 
 #[tauri::command]
-pub fn read_feed(db_state: State<DbState>, id: i32) -> Result<Option<Feed>, String> {
-    let db = db_state.db.lock().unwrap();
-    match feeds::read(&db, id) {
-        Ok(feed) => Ok(feed),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn refresh_feed(db_state: State<DbState>, id: i32) -> Result<usize> {
+    let db = db_state.db.get()?;
+    let proxy = settings::read(&db, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    producer::refresh_feed(&db, id, proxy.as_deref())
+}
+
+/// Asks the feed's WebSub hub to push its updates to `callback`.
+#[tauri::command]
+pub fn subscribe_websub(
+    db_state: State<DbState>,
+    feed_id: i32,
+    callback: String,
+) -> Result<String> {
+    let db = db_state.db.get()?;
+    let proxy = settings::read(&db, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    producer::subscribe_websub(&db, feed_id, &callback, proxy.as_deref())?;
+
+    Ok("WebSub subscription requested".to_string())
+}
+
+#[tauri::command]
+pub fn refresh_all_feeds(app: AppHandle, db_state: State<DbState>) -> Result<RefreshReport> {
+    let proxy = settings::read(&*db_state.db.get()?, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    producer::refresh_all(
+        &db_state.db,
+        REFRESH_CONCURRENCY,
+        proxy.as_deref(),
+        |progress| {
+            let _ = app.emit_all("feed-refresh-progress", progress);
+        },
+    )
+}
+
+#[tauri::command]
+pub fn fetch_feed_favicon(db_state: State<DbState>, id: i32) -> Result<Vec<u8>> {
+    let db = db_state.db.get()?;
+    let proxy = settings::read(&db, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    producer::fetch_favicon(&db, id, proxy.as_deref())
+}
+
+#[tauri::command]
+pub fn read_feed_favicon(db_state: State<DbState>, id: i32) -> Result<Option<Favicon>> {
+    let db = db_state.db.get()?;
+    feeds::read_favicon(&db, id)
 }
 
 #[tauri::command]
-pub fn update_feed(db_state: State<DbState>, arg: FeedToUpdate) -> Result<String, String> {
-    let db = db_state.db.lock().unwrap();
-    match feeds::update(&db, &arg) {
-        Ok(_) => Ok("Feed updated".to_string()),
-        Err(err) => Err(err.to_string()),
+pub fn read_all_feeds(db_state: State<DbState>) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read_all(&db).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn read_feeds_including_unsubscribed(
+    db_state: State<DbState>,
+) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read_including_unsubscribed(&db).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn read_sidebar(db_state: State<DbState>) -> Result<FeedPayload<Vec<FeedSummary>>> {
+    let db = db_state.db.get()?;
+    feeds::read_sidebar(&db).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn read_feeds_by_status(
+    db_state: State<DbState>,
+    status: FeedStatus,
+) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read_by_status(&db, &status).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn count_feeds_by_status(db_state: State<DbState>) -> Result<HashMap<FeedStatus, i64>> {
+    let db = db_state.db.get()?;
+    feeds::count_by_status(&db)
+}
+
+#[tauri::command]
+pub fn set_feed_headers(
+    db_state: State<DbState>,
+    id: i32,
+    headers: HashMap<String, String>,
+) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::set_headers(&db, id, &headers)?;
+
+    Ok("Feed headers updated".to_string())
+}
+
+#[tauri::command]
+pub fn find_duplicate_feeds(db_state: State<DbState>) -> Result<FeedPayload<Vec<Vec<Feed>>>> {
+    let db = db_state.db.get()?;
+    feeds::find_duplicates(&db).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn read_feed(db_state: State<DbState>, id: i32) -> Result<FeedPayload<Option<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read(&db, id).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn update_feed(db_state: State<DbState>, arg: FeedToUpdate) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::update(&db, &arg)?;
+
+    Ok("Feed updated".to_string())
+}
+
+/// Saves a feed the frontend edited in place, writing only the fields that differ from the stored
+/// feed.
+#[tauri::command]
+pub fn edit_feed(db_state: State<DbState>, feed: Feed) -> Result<String> {
+    let db = db_state.db.get()?;
+    let stored = feeds::read_required(&db, feed.id)?;
+    if let Some(update) = feed.edits_from(&stored) {
+        feeds::update(&db, &update)?;
     }
+
+    Ok("Feed updated".to_string())
+}
+
+#[tauri::command]
+pub fn subscribe_feed(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::subscribe(&db, id)?;
+
+    Ok("Feed subscribed".to_string())
+}
+
+#[tauri::command]
+pub fn unsubscribe_feed(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::unsubscribe(&db, id)?;
+
+    Ok("Feed unsubscribed".to_string())
 }
 
 #[tauri::command]
-pub fn delete_feed(db_state: State<DbState>, id: i32) -> Result<String, String> {
-    let db = db_state.db.lock().unwrap();
-    match feeds::delete(&db, id) {
-        Ok(_) => Ok("Feed deleted".to_string()),
-        Err(err) => Err(err.to_string()),
+pub fn bulk_unsubscribe_feeds(db_state: State<DbState>, ids: Vec<i32>) -> Result<usize> {
+    let db = db_state.db.get()?;
+    feeds::bulk_unsubscribe(&db, &ids)
+}
+
+#[tauri::command]
+pub fn set_feed_muted(db_state: State<DbState>, id: i32, is_muted: bool) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::set_muted(&db, id, is_muted)?;
+
+    Ok(if is_muted {
+        "Feed muted"
+    } else {
+        "Feed unmuted"
     }
+    .to_string())
+}
+
+#[tauri::command]
+pub fn set_feed_notify_on_new(
+    db_state: State<DbState>,
+    id: i32,
+    notify_on_new: bool,
+) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::set_notify_on_new(&db, id, notify_on_new)?;
+
+    Ok("Feed notifications updated".to_string())
+}
+
+#[tauri::command]
+pub fn set_read_position(db_state: State<DbState>, feed_id: i32, item_id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::set_read_position(&db, feed_id, item_id)?;
+
+    Ok("Read position updated".to_string())
+}
+
+#[tauri::command]
+pub fn set_feed_max_items_per_fetch(
+    db_state: State<DbState>,
+    id: i32,
+    limit: Option<i32>,
+) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::set_max_items_per_fetch(&db, id, limit)?;
+
+    Ok("Feed item limit updated".to_string())
+}
+
+#[tauri::command]
+pub fn is_feed_subscribed(db_state: State<DbState>, id: i32) -> Result<bool> {
+    let db = db_state.db.get()?;
+    feeds::is_subscribed(&db, id)
+}
+
+#[tauri::command]
+pub fn reorder_feed(db_state: State<DbState>, id: i32, position: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::reorder(&db, id, position)?;
+
+    Ok("Feed reordered".to_string())
+}
+
+#[tauri::command]
+pub fn delete_feed(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::delete(&db, id)?;
+
+    Ok("Feed deleted".to_string())
+}
+
+#[tauri::command]
+pub fn bulk_delete_feeds(db_state: State<DbState>, ids: Vec<i32>) -> Result<usize> {
+    let db = db_state.db.get()?;
+    feeds::bulk_delete(&db, &ids)
+}
+
+#[tauri::command]
+pub fn read_archived_feeds(db_state: State<DbState>) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read_archived(&db).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn read_unhealthy_feeds(
+    db_state: State<DbState>,
+    min_failures: i32,
+) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read_unhealthy(&db, min_failures).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn read_recent_feeds(db_state: State<DbState>, limit: u64) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::read_recent(&db, limit).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn reset_feed(db_state: State<DbState>, id: i32, keep_saved: bool) -> Result<usize> {
+    let db = db_state.db.get()?;
+    feeds::reset(&db, id, keep_saved)
+}
+
+#[tauri::command]
+pub fn restore_feed(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::restore(&db, id)?;
+
+    Ok("Feed restored".to_string())
+}
+
+#[tauri::command]
+pub fn purge_feed(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    feeds::purge(&db, id)?;
+
+    Ok("Feed purged".to_string())
+}
+
+#[tauri::command]
+pub fn merge_feeds(db_state: State<DbState>, keep: i32, remove: i32) -> Result<usize> {
+    let db = db_state.db.get()?;
+    feeds::merge(&db, keep, remove)
+}
+
+#[tauri::command]
+pub fn search_feeds(db_state: State<DbState>, query: String) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::search(&db, &query).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn search_feeds_by_title(
+    db_state: State<DbState>,
+    prefix: String,
+    limit: u64,
+) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    feeds::find_by_title_prefix(&db, &prefix, limit).map(FeedPayload::new)
+}
+
+#[tauri::command]
+pub fn import_opml(db_state: State<DbState>, xml: String) -> Result<Vec<i32>> {
+    let db = db_state.db.get()?;
+    opml::import_opml(&db, &xml)
+}
+
+#[tauri::command]
+pub fn import_bookmarks(db_state: State<DbState>, html: String) -> Result<BookmarkImport> {
+    let proxy = settings::read(&*db_state.db.get()?, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    bookmarks::import_bookmarks(&db_state.db, &html, IMPORT_CONCURRENCY, proxy.as_deref())
+}
+
+#[tauri::command]
+pub fn export_opml(db_state: State<DbState>) -> Result<String> {
+    let db = db_state.db.get()?;
+    opml::export_opml(&db)
+}
+
+#[tauri::command]
+pub fn export_feed_markdown(db_state: State<DbState>, feed_id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    markdown::export_feed_markdown(&db, feed_id)
 }
@@ -0,0 +1,35 @@
+use tauri::State;
+
+use crate::{
+    error::Result,
+    models::filters::{self, FilterRule, FilterRuleToCreate, FilterRuleToUpdate},
+    DbState,
+};
+
+#[tauri::command]
+pub fn create_filter_rule(db_state: State<DbState>, arg: FilterRuleToCreate) -> Result<i32> {
+    let db = db_state.db.get()?;
+    filters::create(&db, &arg)
+}
+
+#[tauri::command]
+pub fn read_filter_rules(db_state: State<DbState>) -> Result<Vec<FilterRule>> {
+    let db = db_state.db.get()?;
+    filters::read_all(&db)
+}
+
+#[tauri::command]
+pub fn update_filter_rule(db_state: State<DbState>, arg: FilterRuleToUpdate) -> Result<String> {
+    let db = db_state.db.get()?;
+    filters::update(&db, &arg)?;
+
+    Ok("Filter rule updated".to_string())
+}
+
+#[tauri::command]
+pub fn delete_filter_rule(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    filters::delete(&db, id)?;
+
+    Ok("Filter rule deleted".to_string())
+}
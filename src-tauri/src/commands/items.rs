@@ -1,42 +1,129 @@
+use chrono::{DateTime, FixedOffset};
 use tauri::State;
 
+use crate::models::settings;
+use crate::models::settings::SettingKey;
 use crate::{
-    models::items::{self, Item, ItemReadOption, ItemToUpdate, ItemToUpdateAll},
-    DbState,
+    error::Result,
+    models::items::{
+        self, Item, ItemReadOption, ItemToUpdate, ItemToUpdateAll, TimelineCursor, TimelineItem,
+    },
+    producer, DbState,
 };
 
 #[tauri::command]
-pub fn read_all_items(db_state: State<DbState>, opt: ItemReadOption) -> Result<Vec<Item>, String> {
-    let db = db_state.db.lock().unwrap();
-    match items::read_all(&db, &opt) {
-        Ok(items) => Ok(items),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn read_all_items(db_state: State<DbState>, opt: ItemReadOption) -> Result<Vec<Item>> {
+    let db = db_state.db.get()?;
+    items::read_all(&db, &opt)
 }
 
 #[tauri::command]
-pub fn count_all_items(db_state: State<DbState>, opt: ItemReadOption) -> Result<i64, String> {
-    let db = db_state.db.lock().unwrap();
-    match items::count_all(&db, &opt) {
-        Ok(count) => Ok(count),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn read_items_by_ids(db_state: State<DbState>, ids: Vec<i32>) -> Result<Vec<Item>> {
+    let db = db_state.db.get()?;
+    items::read_by_ids(&db, &ids)
 }
 
 #[tauri::command]
-pub fn update_item(db_state: State<DbState>, arg: ItemToUpdate) -> Result<String, String> {
-    let db = db_state.db.lock().unwrap();
-    match items::update(&db, &arg) {
-        Ok(_) => Ok("Item updated".to_string()),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn read_items_between(
+    db_state: State<DbState>,
+    feed: Option<i32>,
+    since: DateTime<FixedOffset>,
+    until: DateTime<FixedOffset>,
+) -> Result<Vec<Item>> {
+    let db = db_state.db.get()?;
+    items::read_items_between(&db, feed, since, until)
 }
 
 #[tauri::command]
-pub fn update_items(db_state: State<DbState>, arg: ItemToUpdateAll) -> Result<String, String> {
-    let db = db_state.db.lock().unwrap();
-    match items::update_all(&db, &arg) {
-        Ok(_) => Ok("Items updated".to_string()),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn read_timeline(
+    db_state: State<DbState>,
+    offset: u64,
+    limit: u64,
+    unread_only: bool,
+) -> Result<Vec<TimelineItem>> {
+    let db = db_state.db.get()?;
+    items::read_timeline(&db, offset, limit, unread_only)
+}
+
+#[tauri::command]
+pub fn read_timeline_after(
+    db_state: State<DbState>,
+    cursor: Option<TimelineCursor>,
+    limit: u64,
+    unread_only: bool,
+) -> Result<(Vec<TimelineItem>, Option<TimelineCursor>)> {
+    let db = db_state.db.get()?;
+    items::read_timeline_after(&db, cursor.as_ref(), limit, unread_only)
+}
+
+#[tauri::command]
+pub fn search_items(db_state: State<DbState>, query: String) -> Result<Vec<Item>> {
+    let db = db_state.db.get()?;
+    items::search(&db, &query)
+}
+
+#[tauri::command]
+pub fn count_all_items(db_state: State<DbState>, opt: ItemReadOption) -> Result<i64> {
+    let db = db_state.db.get()?;
+    items::count_all(&db, &opt)
+}
+
+#[tauri::command]
+pub fn count_unread_items(db_state: State<DbState>, feed: Option<i32>) -> Result<i64> {
+    let db = db_state.db.get()?;
+    items::unread_count(&db, feed)
+}
+
+#[tauri::command]
+pub fn update_item(db_state: State<DbState>, arg: ItemToUpdate) -> Result<String> {
+    let db = db_state.db.get()?;
+    items::update(&db, &arg)?;
+
+    Ok("Item updated".to_string())
+}
+
+#[tauri::command]
+pub fn mark_all_items_read(db_state: State<DbState>) -> Result<usize> {
+    let db = db_state.db.get()?;
+    items::mark_all_read(&db)
+}
+
+#[tauri::command]
+pub fn toggle_item_star(db_state: State<DbState>, id: i32) -> Result<bool> {
+    let db = db_state.db.get()?;
+    items::toggle_star(&db, id)
+}
+
+#[tauri::command]
+pub fn read_starred_items(db_state: State<DbState>) -> Result<Vec<Item>> {
+    let db = db_state.db.get()?;
+    items::read_starred(&db)
+}
+
+#[tauri::command]
+pub fn update_items(db_state: State<DbState>, arg: ItemToUpdateAll) -> Result<String> {
+    let db = db_state.db.get()?;
+    items::update_all(&db, &arg)?;
+
+    Ok("Items updated".to_string())
+}
+
+#[tauri::command]
+pub fn fetch_item_full_content(db_state: State<DbState>, id: i32) -> Result<String> {
+    let db = db_state.db.get()?;
+    let proxy = settings::read(&db, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    producer::fetch_full_content(&db, id, proxy.as_deref())
+}
+
+#[tauri::command]
+pub fn refresh_item(db_state: State<DbState>, item_id: i32) -> Result<bool> {
+    let db = db_state.db.get()?;
+    let proxy = settings::read(&db, &SettingKey::Proxy)
+        .map(|x| x.value)
+        .ok();
+
+    producer::refresh_item(&db, item_id, proxy.as_deref())
 }
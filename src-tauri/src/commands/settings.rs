@@ -1,33 +1,76 @@
 use tauri::State;
 
 use crate::{
-    models::settings::{self, Setting, SettingKey, SettingToUpdate},
-    DbState,
+    backup::{self, ImportSummary},
+    error::Result,
+    models::{
+        database::{self, DbStats},
+        settings::{self, Setting, SettingKey, SettingToUpdate},
+    },
+    rate_limit, DbState,
 };
 
 #[tauri::command]
-pub fn read_all_settings(db_state: State<DbState>) -> Result<Vec<Setting>, String> {
-    let db = db_state.db.lock().unwrap();
-    match settings::read_all(&db) {
-        Ok(settings) => Ok(settings),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn read_all_settings(db_state: State<DbState>) -> Result<Vec<Setting>> {
+    let db = db_state.db.get()?;
+    settings::read_all(&db)
 }
 
 #[tauri::command]
-pub fn read_setting(db_state: State<DbState>, key: SettingKey) -> Result<Setting, String> {
-    let db = db_state.db.lock().unwrap();
-    match settings::read(&db, &key) {
-        Ok(setting) => Ok(setting),
-        Err(err) => Err(err.to_string()),
-    }
+pub fn read_setting(db_state: State<DbState>, key: SettingKey) -> Result<Setting> {
+    let db = db_state.db.get()?;
+    settings::read(&db, &key)
 }
 
 #[tauri::command]
-pub fn update_setting(db_state: State<DbState>, arg: SettingToUpdate) -> Result<String, String> {
-    let db = db_state.db.lock().unwrap();
-    match settings::update(&db, &arg) {
-        Ok(_) => Ok("Setting updated".to_string()),
-        Err(err) => Err(err.to_string()),
+pub fn update_setting(db_state: State<DbState>, arg: SettingToUpdate) -> Result<String> {
+    let db = db_state.db.get()?;
+    settings::update(&db, &arg)?;
+    if arg.key == SettingKey::RequestsPerHostPerMinute {
+        rate_limit::set_requests_per_host_per_minute(settings::requests_per_host_per_minute(&db)?);
     }
+
+    Ok("Setting updated".to_string())
+}
+
+#[tauri::command]
+pub fn read_database_stats(db_state: State<DbState>) -> Result<DbStats> {
+    let db = db_state.db.get()?;
+    database::stats(&db)
+}
+
+#[tauri::command]
+pub fn compact_database(db_state: State<DbState>) -> Result<String> {
+    let db = db_state.db.get()?;
+    database::vacuum(&db)?;
+
+    Ok("Database compacted".to_string())
+}
+
+#[tauri::command]
+pub fn pause_refreshing(db_state: State<DbState>) -> Result<String> {
+    let db = db_state.db.get()?;
+    settings::pause_all(&db)?;
+
+    Ok("Refreshing paused".to_string())
+}
+
+#[tauri::command]
+pub fn resume_refreshing(db_state: State<DbState>) -> Result<String> {
+    let db = db_state.db.get()?;
+    settings::resume_all(&db)?;
+
+    Ok("Refreshing resumed".to_string())
+}
+
+#[tauri::command]
+pub fn export_state(db_state: State<DbState>) -> Result<String> {
+    let db = db_state.db.get()?;
+    backup::export_state(&db)
+}
+
+#[tauri::command]
+pub fn import_state(db_state: State<DbState>, json: String) -> Result<ImportSummary> {
+    let db = db_state.db.get()?;
+    backup::import_state(&db, &json)
 }
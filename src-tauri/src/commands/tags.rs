@@ -0,0 +1,38 @@
+use tauri::State;
+
+use crate::{
+    error::Result,
+    models::{
+        feeds::{Feed, FeedPayload},
+        tags,
+    },
+    DbState,
+};
+
+#[tauri::command]
+pub fn add_feed_tag(db_state: State<DbState>, feed: i32, name: String) -> Result<String> {
+    let db = db_state.db.get()?;
+    tags::add_tag(&db, feed, &name)?;
+
+    Ok("Tag added".to_string())
+}
+
+#[tauri::command]
+pub fn remove_feed_tag(db_state: State<DbState>, feed: i32, name: String) -> Result<String> {
+    let db = db_state.db.get()?;
+    tags::remove_tag(&db, feed, &name)?;
+
+    Ok("Tag removed".to_string())
+}
+
+#[tauri::command]
+pub fn read_feed_tags(db_state: State<DbState>, feed: i32) -> Result<Vec<String>> {
+    let db = db_state.db.get()?;
+    tags::read_tags(&db, feed)
+}
+
+#[tauri::command]
+pub fn read_feeds_by_tag(db_state: State<DbState>, name: String) -> Result<FeedPayload<Vec<Feed>>> {
+    let db = db_state.db.get()?;
+    tags::read_feeds_by_tag(&db, &name).map(FeedPayload::new)
+}
@@ -1,5 +1,8 @@
 use std::io;
 
+use chrono::{DateTime, FixedOffset};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("invalid value `{0}`")]
@@ -11,9 +14,45 @@ pub enum Error {
     #[error("forbidden")]
     Forbidden,
 
+    #[error("invalid feed: {0}")]
+    InvalidFeed(String),
+
+    #[error("feed already exists with id `{0}`")]
+    AlreadyExists(i32),
+
+    #[error("no feed with id `{0}`")]
+    NotFound(i32),
+
+    #[error("no icon found for feed with id `{0}`")]
+    FaviconNotFound(i32),
+
     #[error("failed to parse syndication feed")]
     SyndicationParsingFailure,
 
+    #[error("no syndication feed found at `{0}`")]
+    SyndicationNotFound(String),
+
+    #[error("could not reach `{0}`")]
+    Unreachable(String),
+
+    #[error("the feed at `{0}` has no items")]
+    EmptyFeed(String),
+
+    #[error("`{0}` asked not to be fetched again before {1}")]
+    RateLimited(String, DateTime<FixedOffset>),
+
+    #[error("feed with id `{0}` advertises no WebSub hub")]
+    NoWebSubHub(i32),
+
+    #[error("failed to parse OPML document")]
+    OpmlParsingFailure,
+
+    #[error("failed to parse backup document")]
+    BackupParsingFailure,
+
+    #[error("this build of collie cannot encrypt the database")]
+    EncryptionUnavailable,
+
     #[error("unknown")]
     Unknown,
 
@@ -23,6 +62,12 @@ pub enum Error {
         source: rusqlite::Error,
     },
 
+    #[error(transparent)]
+    R2d2Error {
+        #[from]
+        source: r2d2::Error,
+    },
+
     #[error(transparent)]
     SeaQueryError {
         #[from]
@@ -42,4 +87,66 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// A stable, machine-readable name for the kind of error, independent of the message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidValue(_) => "InvalidValue",
+            Self::InvalidEnumKey(_, _) => "InvalidEnumKey",
+            Self::Forbidden => "Forbidden",
+            Self::InvalidFeed(_) => "InvalidFeed",
+            Self::AlreadyExists(_) => "AlreadyExists",
+            Self::NotFound(_) => "NotFound",
+            Self::FaviconNotFound(_) => "FaviconNotFound",
+            Self::SyndicationParsingFailure
+            | Self::OpmlParsingFailure
+            | Self::BackupParsingFailure => "Parse",
+            Self::SyndicationNotFound(_) => "FeedNotFound",
+            Self::Unreachable(_) => "Unreachable",
+            Self::EmptyFeed(_) => "EmptyFeed",
+            Self::RateLimited(_, _) => "RateLimited",
+            Self::NoWebSubHub(_) => "NoWebSubHub",
+            Self::EncryptionUnavailable => "EncryptionUnavailable",
+            Self::Unknown => "Unknown",
+            Self::RusqliteError { .. } | Self::R2d2Error { .. } | Self::SeaQueryError { .. } => {
+                "Database"
+            }
+            Self::ReqwestError { .. } => "Network",
+            Self::IoError { .. } => "Io",
+        }
+    }
+
+    /// Whether trying again shortly might succeed: the server timed out, could not be reached, or
+    /// failed with a 5xx. A missing feed or one that does not parse will not get better.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::ReqwestError { source } => {
+                source.is_timeout()
+                    || source.is_connect()
+                    || source.status().is_some_and(|x| x.is_server_error())
+            }
+            Self::IoError { source } => matches!(
+                source.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::Interrupted
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Errors cross the Tauri command boundary as `{ kind, message }`, so the frontend can branch on
+/// `kind` rather than on storage or network specific messages.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
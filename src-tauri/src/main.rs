@@ -1,52 +1,131 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use rusqlite::Connection;
-use std::{fs, path::PathBuf, sync::Mutex};
+use std::path::PathBuf;
 use tauri::Manager;
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
 
 pub mod models {
     pub mod database;
     pub mod feeds;
+    pub mod filters;
     pub mod items;
+    pub mod migrations;
     pub mod settings;
+    pub mod tags;
 }
 
 pub mod commands {
     pub mod feeds;
+    pub mod filters;
     pub mod items;
     pub mod settings;
+    pub mod tags;
 }
 
+pub mod backup;
+pub mod bookmarks;
 pub mod error;
+pub mod markdown;
+pub mod opml;
 pub mod producer;
+pub mod rate_limit;
 pub mod syndication;
+pub mod util;
 pub mod worker;
 
 #[cfg(test)]
-mod tests {
-    mod syndication;
-}
+mod tests;
 
 pub struct DbState {
-    db: Mutex<Connection>,
+    db: models::database::DbPool,
 }
 
 fn main() {
+    // `RUST_LOG` picks what is logged, e.g. `collie=debug`. Closed spans are logged with how long
+    // they took.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("collie=info")),
+        )
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
     let _ = tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             commands::feeds::create_feed,
+            commands::feeds::probe_feed,
+            commands::feeds::refresh_feed,
+            commands::feeds::refresh_all_feeds,
+            commands::feeds::subscribe_websub,
+            commands::feeds::fetch_feed_favicon,
+            commands::feeds::read_feed_favicon,
             commands::feeds::read_all_feeds,
+            commands::feeds::read_feeds_including_unsubscribed,
+            commands::feeds::read_sidebar,
+            commands::feeds::read_feeds_by_status,
+            commands::feeds::count_feeds_by_status,
+            commands::feeds::find_duplicate_feeds,
+            commands::feeds::set_feed_headers,
             commands::feeds::read_feed,
             commands::feeds::update_feed,
+            commands::feeds::edit_feed,
+            commands::feeds::subscribe_feed,
+            commands::feeds::unsubscribe_feed,
+            commands::feeds::bulk_unsubscribe_feeds,
+            commands::feeds::is_feed_subscribed,
+            commands::feeds::set_feed_muted,
+            commands::feeds::set_feed_notify_on_new,
+            commands::feeds::set_feed_max_items_per_fetch,
+            commands::feeds::set_read_position,
+            commands::feeds::reorder_feed,
             commands::feeds::delete_feed,
+            commands::feeds::bulk_delete_feeds,
+            commands::feeds::read_archived_feeds,
+            commands::feeds::read_unhealthy_feeds,
+            commands::feeds::read_recent_feeds,
+            commands::feeds::reset_feed,
+            commands::feeds::restore_feed,
+            commands::feeds::purge_feed,
+            commands::feeds::merge_feeds,
+            commands::feeds::search_feeds,
+            commands::feeds::search_feeds_by_title,
+            commands::feeds::import_opml,
+            commands::feeds::import_bookmarks,
+            commands::feeds::export_opml,
+            commands::feeds::export_feed_markdown,
             commands::items::read_all_items,
+            commands::items::read_items_by_ids,
+            commands::items::read_items_between,
+            commands::items::read_timeline,
+            commands::items::read_timeline_after,
+            commands::items::search_items,
             commands::items::count_all_items,
+            commands::items::count_unread_items,
             commands::items::update_item,
             commands::items::update_items,
+            commands::items::mark_all_items_read,
+            commands::items::toggle_item_star,
+            commands::items::read_starred_items,
+            commands::items::fetch_item_full_content,
+            commands::items::refresh_item,
+            commands::tags::add_feed_tag,
+            commands::tags::remove_feed_tag,
+            commands::tags::read_feed_tags,
+            commands::tags::read_feeds_by_tag,
+            commands::filters::create_filter_rule,
+            commands::filters::read_filter_rules,
+            commands::filters::update_filter_rule,
+            commands::filters::delete_filter_rule,
             commands::settings::read_all_settings,
             commands::settings::read_setting,
             commands::settings::update_setting,
+            commands::settings::read_database_stats,
+            commands::settings::compact_database,
+            commands::settings::pause_refreshing,
+            commands::settings::resume_refreshing,
+            commands::settings::export_state,
+            commands::settings::import_state,
         ])
         .setup(|app| {
             let app_data_dir = if cfg!(dev) {
@@ -55,11 +134,17 @@ fn main() {
                 app.handle().path_resolver().app_data_dir().unwrap()
             };
 
-            fs::create_dir_all(&app_data_dir).unwrap();
-            let db = models::database::open_connection(&app_data_dir).unwrap();
-            let _ = models::database::migrate(&db);
+            let config = models::database::DbConfig::new(&app_data_dir);
+            let db = models::database::init(&config).unwrap();
+            if let Ok(per_minute) = db
+                .get()
+                .map_err(error::Error::from)
+                .and_then(|x| models::settings::requests_per_host_per_minute(&x))
+            {
+                rate_limit::set_requests_per_host_per_minute(per_minute);
+            }
 
-            app.manage(DbState { db: Mutex::new(db) });
+            app.manage(DbState { db });
             worker::start(app);
 
             Ok(())
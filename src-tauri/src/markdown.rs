@@ -0,0 +1,156 @@
+use regex::{Captures, Regex};
+use rusqlite::Connection;
+
+use crate::error::Result;
+use crate::models::{
+    feeds,
+    items::{self, ItemOrder, ItemReadOption},
+};
+
+/// Renders the feed and its items, oldest first, into a single Markdown document: a heading, date
+/// and link for each item, followed by its content converted from HTML. Hidden items are left out.
+pub fn export_feed_markdown(db: &Connection, feed_id: i32) -> Result<String> {
+    let feed = feeds::read_required(db, feed_id)?;
+    let mut items = items::read_all(
+        db,
+        &ItemReadOption {
+            feed: Some(feed.id),
+            order_by: Some(ItemOrder::PublishedDateDesc),
+            ..Default::default()
+        },
+    )?;
+    items.reverse();
+
+    let mut document = format!(
+        "# {}\n\n<{}>\n",
+        feed.display_title(),
+        feed.html_url.as_deref().unwrap_or(&feed.link)
+    );
+    for item in items {
+        let content = match item.content_is_html {
+            true => html_to_markdown(&item.description),
+            false => item.description.trim().to_string(),
+        };
+
+        document.push_str(&format!(
+            "\n## [{}]({})\n\n*{}*\n",
+            item.title.replace('[', "\\[").replace(']', "\\]"),
+            item.link,
+            item.published_at.format("%Y-%m-%d"),
+        ));
+        if !content.is_empty() {
+            document.push_str(&format!("\n{content}\n"));
+        }
+    }
+
+    Ok(document)
+}
+
+/// Converts the common elements of feed HTML to their Markdown equivalent: headings, paragraphs,
+/// emphasis, links, images, lists, quotes and code. Other tags are dropped, keeping their text.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut md = Regex::new(r"(?is)<!--.*?-->|<(script|style)\b.*?</(script|style)\s*>")
+        .unwrap()
+        .replace_all(html, "")
+        .into_owned();
+
+    md = Regex::new(r"(?is)<blockquote\b[^>]*>(.*?)</blockquote\s*>")
+        .unwrap()
+        .replace_all(&md, |x: &Captures| {
+            let quoted = html_to_markdown(&x[1])
+                .lines()
+                .map(|line| format!("> {line}").trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("\n\n{quoted}\n\n")
+        })
+        .into_owned();
+    md = Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre\s*>")
+        .unwrap()
+        .replace_all(&md, |x: &Captures| {
+            let code = strip_tags(&x[1]);
+            format!("\n\n```\n{}\n```\n\n", code.trim_matches('\n'))
+        })
+        .into_owned();
+
+    for (level, tag) in ["h1", "h2", "h3", "h4", "h5", "h6"].iter().enumerate() {
+        md = Regex::new(&format!(r"(?is)<{tag}\b[^>]*>(.*?)</{tag}\s*>"))
+            .unwrap()
+            .replace_all(&md, |x: &Captures| {
+                format!(
+                    "\n\n{} {}\n\n",
+                    "#".repeat(level + 1),
+                    strip_tags(&x[1]).trim()
+                )
+            })
+            .into_owned();
+    }
+
+    md = Regex::new(r"(?is)<img\b([^>]*)>")
+        .unwrap()
+        .replace_all(&md, |x: &Captures| match attribute(&x[1], "src") {
+            Some(src) => format!("![{}]({src})", attribute(&x[1], "alt").unwrap_or_default()),
+            None => String::new(),
+        })
+        .into_owned();
+    md = Regex::new(r"(?is)<a\b([^>]*)>(.*?)</a\s*>")
+        .unwrap()
+        .replace_all(&md, |x: &Captures| match attribute(&x[1], "href") {
+            Some(href) => format!("[{}]({href})", x[2].trim()),
+            None => x[2].to_string(),
+        })
+        .into_owned();
+
+    for (pattern, replacement) in [
+        (r"(?is)</?(strong|b)\b[^>]*>", "**"),
+        (r"(?is)</?(em|i)\b[^>]*>", "*"),
+        (r"(?is)</?code\b[^>]*>", "`"),
+        (r"(?is)<li\b[^>]*>", "\n- "),
+        (r"(?is)<br\s*/?>", "\n"),
+        (r"(?is)<hr\s*/?>", "\n\n---\n\n"),
+        (
+            r"(?is)</?(p|div|ul|ol|table|tr|section|figure)\b[^>]*>",
+            "\n\n",
+        ),
+    ] {
+        md = Regex::new(pattern)
+            .unwrap()
+            .replace_all(&md, replacement)
+            .into_owned();
+    }
+
+    let md = decode_entities(&strip_tags(&md));
+    let lines = md.lines().map(str::trim_end).collect::<Vec<_>>().join("\n");
+
+    Regex::new(r"\n{3,}")
+        .unwrap()
+        .replace_all(lines.trim(), "\n\n")
+        .into_owned()
+}
+
+fn strip_tags(html: &str) -> String {
+    Regex::new(r"(?s)<[^>]*>")
+        .unwrap()
+        .replace_all(html, "")
+        .into_owned()
+}
+
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    Regex::new(&format!(
+        r#"(?is)\b{name}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#
+    ))
+    .unwrap()
+    .captures(attrs)
+    .and_then(|x| x.get(1).or(x.get(2)).or(x.get(3)))
+    .map(|x| decode_entities(x.as_str().trim()))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -1,12 +1,20 @@
-use std::path::Path;
-
-use rusqlite::Connection;
-use sea_query::{
-    ColumnDef, Expr, ForeignKey, ForeignKeyAction, Iden, Index, Query, SqliteQueryBuilder, Table,
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
 };
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{types::Type, Connection, Row};
+use sea_query::{Asterisk, Expr, Func, Iden, Query, SqliteQueryBuilder};
 use sea_query_rusqlite::RusqliteBinder;
+use serde::Serialize;
+
+use crate::error::{Error, Result};
 
-use crate::error::Result;
+use super::migrations;
 
 #[derive(Iden)]
 pub enum Feeds {
@@ -16,6 +24,31 @@ pub enum Feeds {
     Link,
     Status,
     CheckedAt,
+    FetchIntervalMinutes,
+    DeletedAt,
+    Etag,
+    LastModified,
+    HtmlUrl,
+    Favicon,
+    FaviconContentType,
+    LastError,
+    ConsecutiveFailures,
+    Position,
+    ContentType,
+    UserTitle,
+    IsMuted,
+    TitleSortKey,
+    Headers,
+    CreatedAt,
+    UpdatedAt,
+    NotifyOnNew,
+    LastNotifiedAt,
+    MaxItemsPerFetch,
+    HubUrl,
+    TopicUrl,
+    RetryAfter,
+    LastReadItemId,
+    LastNotifiedItemId,
 }
 
 #[derive(Iden)]
@@ -31,6 +64,50 @@ pub enum Items {
     IsSaved,
     PublishedAt,
     Feed,
+    Guid,
+    Summary,
+    ContentHash,
+    ContentIsHtml,
+    IsHidden,
+    CreatedAt,
+    OriginalPublishedAt,
+}
+
+#[derive(Iden)]
+pub enum Tags {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(Iden)]
+pub enum FeedTags {
+    Table,
+    Feed,
+    Tag,
+}
+
+#[derive(Iden)]
+pub enum FilterRules {
+    Table,
+    Id,
+    Pattern,
+    Action,
+    Feed,
+}
+
+#[derive(Iden)]
+pub enum FeedsFts {
+    Table,
+    Rowid,
+    Rank,
+}
+
+#[derive(Iden)]
+pub enum ItemsFts {
+    Table,
+    Rowid,
+    Rank,
 }
 
 #[derive(Iden)]
@@ -40,106 +117,129 @@ pub enum Settings {
     Value,
 }
 
-pub fn open_connection(path: &Path) -> Result<Connection> {
-    Ok(Connection::open(path.join("collie.db"))?)
+pub type DbPool = Pool<SqliteConnectionManager>;
+
+/// How much the database stores. Feeds and items are counted as stored, archived ones included.
+#[derive(Serialize, Debug)]
+pub struct DbStats {
+    pub page_count: i64,
+    pub page_size: i64,
+    /// Pages left unused by deletions, which `vacuum` gives back.
+    pub freelist_count: i64,
+    pub size_bytes: i64,
+    pub feed_count: i64,
+    pub item_count: i64,
 }
 
+pub const DEFAULT_POOL_SIZE: u32 = 8;
+
+pub const DB_FILE_NAME: &str = "collie.db";
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many prepared statements each connection keeps for reuse. Queries bind their values as
+/// parameters, so each function prepares the same few statements over and over.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// Overrides where the database file lives, e.g. to run collie portably from a removable drive.
+pub const DB_PATH_VAR: &str = "COLLIE_DB_PATH";
+
+/// Logs every statement a connection runs, at debug level under the `collie::sql` target, when set
+/// to a non-empty value.
+pub const LOG_SQL_VAR: &str = "COLLIE_LOG_SQL";
+
+/// The passphrase the database is encrypted with, in builds with the `sqlcipher` feature.
+pub const DB_KEY_VAR: &str = "COLLIE_DB_KEY";
+
+/// Where the database lives and how many connections to it are pooled.
+pub struct DbConfig {
+    pub path: PathBuf,
+    pub pool_size: u32,
+    /// Encrypts the database with SQLCipher. Only builds with the `sqlcipher` feature can, and the
+    /// others refuse to open a database with a passphrase rather than store it unencrypted.
+    pub passphrase: Option<String>,
+}
+
+impl DbConfig {
+    /// The database in `data_dir`, unless the `COLLIE_DB_PATH` environment variable names another
+    /// file, encrypted with the passphrase in `COLLIE_DB_KEY` if there is one.
+    pub fn new(data_dir: &Path) -> Self {
+        let path = env::var_os(DB_PATH_VAR)
+            .filter(|x| !x.is_empty())
+            .map_or_else(|| data_dir.join(DB_FILE_NAME), PathBuf::from);
+
+        Self {
+            path,
+            pool_size: DEFAULT_POOL_SIZE,
+            passphrase: env::var(DB_KEY_VAR).ok().filter(|x| !x.is_empty()),
+        }
+    }
+}
+
+/// Opens the pool the app runs on, once at startup, creating the database and the directories
+/// leading to it if needed.
+pub fn init(config: &DbConfig) -> Result<DbPool> {
+    check_encryption(config.passphrase.as_deref())?;
+    if let Some(parent) = config.path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let passphrase = config.passphrase.clone();
+    let manager = SqliteConnectionManager::file(&config.path)
+        .with_init(move |x| configure(x, passphrase.as_deref()));
+    let pool = Pool::builder().max_size(config.pool_size).build(manager)?;
+    migrate(&*pool.get()?)?;
+    Ok(pool)
+}
+
+fn check_encryption(passphrase: Option<&str>) -> Result<()> {
+    match passphrase {
+        Some(_) if !cfg!(feature = "sqlcipher") => Err(Error::EncryptionUnavailable),
+        _ => Ok(()),
+    }
+}
+
+/// Unlocks an encrypted database with `passphrase`, lets readers proceed while a refresh writes,
+/// and makes a connection wait for a busy database instead of failing with `database is locked`.
+#[cfg_attr(not(feature = "sqlcipher"), allow(unused_variables))]
+fn configure(db: &mut Connection, passphrase: Option<&str>) -> rusqlite::Result<()> {
+    // The key has to be set before anything reads the database.
+    #[cfg(feature = "sqlcipher")]
+    if let Some(passphrase) = passphrase {
+        db.pragma_update(None, "key", passphrase)?;
+    }
+
+    db.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    db.pragma_update(None, "synchronous", "NORMAL")?;
+    db.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    if env::var_os(LOG_SQL_VAR).is_some_and(|x| !x.is_empty()) {
+        db.trace(Some(log_sql));
+    }
+    db.busy_timeout(BUSY_TIMEOUT)
+}
+
+fn log_sql(sql: &str) {
+    tracing::debug!(target: "collie::sql", "{sql}");
+}
+
+/// Opens a private, migrated database that lives in memory and disappears with the connection.
+pub fn open_in_memory() -> Result<Connection> {
+    let db = Connection::open_in_memory()?;
+    db.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    migrate(&db)?;
+    Ok(db)
+}
+
+pub fn open_pool(path: &Path, size: u32) -> Result<DbPool> {
+    init(&DbConfig {
+        path: path.join(DB_FILE_NAME),
+        pool_size: size,
+        passphrase: None,
+    })
+}
+
+/// Brings the schema up to date and fills in the default settings. Safe to run on every start.
 pub fn migrate(db: &Connection) -> Result<()> {
-    let create_table_feeds = Table::create()
-        .table(Feeds::Table)
-        .if_not_exists()
-        .col(
-            ColumnDef::new(Feeds::Id)
-                .integer()
-                .not_null()
-                .auto_increment()
-                .primary_key(),
-        )
-        .col(ColumnDef::new(Feeds::Title).text().not_null())
-        .col(ColumnDef::new(Feeds::Link).text().not_null())
-        .col(
-            ColumnDef::new(Feeds::Status)
-                .text()
-                .check(Expr::col(Feeds::Status).is_in(["subscribed", "unsubscribed"]))
-                .not_null()
-                .default("subscribed"),
-        )
-        .col(ColumnDef::new(Feeds::CheckedAt).date_time().not_null())
-        .index(
-            Index::create()
-                .unique()
-                .name("uk_feeds_title_link")
-                .col(Feeds::Title)
-                .col(Feeds::Link),
-        )
-        .build(SqliteQueryBuilder);
-
-    let create_table_items = Table::create()
-        .table(Items::Table)
-        .if_not_exists()
-        .col(
-            ColumnDef::new(Items::Id)
-                .integer()
-                .not_null()
-                .auto_increment()
-                .primary_key(),
-        )
-        .col(
-            ColumnDef::new(Items::Fingerprint)
-                .text()
-                .not_null()
-                .unique_key(),
-        )
-        .col(ColumnDef::new(Items::Author).text())
-        .col(ColumnDef::new(Items::Title).text().not_null())
-        .col(ColumnDef::new(Items::Description).text().not_null())
-        .col(ColumnDef::new(Items::Link).text().not_null())
-        .col(
-            ColumnDef::new(Items::Status)
-                .text()
-                .check(Expr::col(Items::Status).is_in(["unread", "read"]))
-                .not_null()
-                .default("unread"),
-        )
-        .col(
-            ColumnDef::new(Items::IsSaved)
-                .integer()
-                .check(Expr::col(Items::IsSaved).is_in([0, 1]))
-                .not_null()
-                .default(0),
-        )
-        .col(ColumnDef::new(Items::PublishedAt).date_time().not_null())
-        .col(ColumnDef::new(Items::Feed).integer().not_null())
-        .foreign_key(
-            ForeignKey::create()
-                .name("fk_items_feeds")
-                .from(Items::Table, Items::Feed)
-                .to(Feeds::Table, Feeds::Id)
-                .on_delete(ForeignKeyAction::Cascade)
-                .on_update(ForeignKeyAction::Cascade),
-        )
-        .build(SqliteQueryBuilder);
-
-    let create_table_settings = Table::create()
-        .table(Settings::Table)
-        .if_not_exists()
-        .col(
-            ColumnDef::new(Settings::Key)
-                .text()
-                .not_null()
-                .primary_key(),
-        )
-        .col(ColumnDef::new(Settings::Value).text().not_null())
-        .build(SqliteQueryBuilder);
-
-    db.execute_batch(
-        &[
-            create_table_feeds,
-            create_table_items,
-            create_table_settings,
-        ]
-        .join(";"),
-    )?;
+    migrations::run(db)?;
 
     let _ = insert_settings(db, "db_scheme_version", "1");
     let _ = insert_settings(db, "polling_frequency", "120");
@@ -147,10 +247,111 @@ pub fn migrate(db: &Connection) -> Result<()> {
     let _ = insert_settings(db, "theme", "system");
     let _ = insert_settings(db, "items_order", "ReceivedDateDesc");
     let _ = insert_settings(db, "proxy", "");
+    let _ = insert_settings(db, "refresh_paused", "false");
+    let _ = insert_settings(db, "max_items_per_fetch", "0");
+    let _ = insert_settings(db, "requests_per_host_per_minute", "60");
 
     Ok(())
 }
 
+/// Rebuilds the database file to reclaim the space left by deleted rows. SQLite cannot vacuum
+/// inside a transaction, so this fails with `Forbidden` when one is open on `db`.
+pub fn vacuum(db: &Connection) -> Result<()> {
+    if !db.is_autocommit() {
+        return Err(Error::Forbidden);
+    }
+
+    Ok(db.execute_batch("VACUUM")?)
+}
+
+pub fn stats(db: &Connection) -> Result<DbStats> {
+    let pragma = |name| db.pragma_query_value(None, name, |x| x.get::<_, i64>(0));
+    let page_count = pragma("page_count")?;
+    let page_size = pragma("page_size")?;
+
+    Ok(DbStats {
+        page_count,
+        page_size,
+        freelist_count: pragma("freelist_count")?,
+        size_bytes: page_count * page_size,
+        feed_count: count_rows(db, Feeds::Table)?,
+        item_count: count_rows(db, Items::Table)?,
+    })
+}
+
+/// Formats a timestamp the way it is stored: RFC 3339 in UTC with millisecond precision, e.g.
+/// `2023-08-01T12:30:00.000Z`. Every stored timestamp has this exact shape, so they sort and compare
+/// correctly as text.
+pub fn format_timestamp<Tz: TimeZone>(at: &DateTime<Tz>) -> String {
+    at.with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parses a stored timestamp. Besides RFC 3339, this accepts the `YYYY-MM-DD HH:MM:SS[.f][+HH:MM]`
+/// shape older versions stored, taking timestamps without an offset as UTC.
+pub fn parse_timestamp(x: &str) -> Option<DateTime<FixedOffset>> {
+    let x = x.trim();
+    DateTime::parse_from_rfc3339(x)
+        .or_else(|_| DateTime::parse_from_str(x, "%Y-%m-%d %H:%M:%S%.f%:z"))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(x, "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .map(|x| Utc.from_utc_datetime(&x).fixed_offset())
+        })
+}
+
+/// Reads the timestamp in `column`, failing with a conversion error naming the column and value if
+/// it cannot be parsed.
+pub fn get_timestamp(row: &Row, column: &str) -> rusqlite::Result<DateTime<FixedOffset>> {
+    get_optional_timestamp(row, column)?.ok_or(rusqlite::Error::InvalidColumnType(
+        row.as_ref().column_index(column)?,
+        column.to_string(),
+        Type::Null,
+    ))
+}
+
+pub fn get_optional_timestamp(
+    row: &Row,
+    column: &str,
+) -> rusqlite::Result<Option<DateTime<FixedOffset>>> {
+    let Some(value) = row.get::<_, Option<String>>(column)? else {
+        return Ok(None);
+    };
+
+    parse_timestamp(&value).map(Some).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            row.as_ref().column_index(column).unwrap_or_default(),
+            Type::Text,
+            format!("invalid timestamp `{value}` in `{column}`").into(),
+        )
+    })
+}
+
+/// Turns free-form user input into an FTS5 query by quoting every term, so that characters such as
+/// `+`, `-`, `*` or `"` are matched literally instead of being parsed as query syntax.
+pub fn fts_query(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .map(|x| format!("\"{}\"", x.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+fn count_rows<T: Iden + 'static>(db: &Connection, table: T) -> Result<i64> {
+    let (sql, values) = Query::select()
+        .expr(Func::count(Expr::col(Asterisk)))
+        .from(table)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.query_row(sql.as_str(), &*values.as_params(), |x| x.get(0))?)
+}
+
 fn insert_settings(db: &Connection, key: &str, value: &str) -> Result<usize> {
     let (insert_settings_sql, insert_settings_values) = Query::insert()
         .into_table(Settings::Table)
@@ -1,22 +1,56 @@
 use core::fmt;
 use std::{
+    collections::HashMap,
     fmt::{Display, Formatter},
     str::FromStr,
 };
 
-use chrono::{DateTime, FixedOffset, Utc};
-use rusqlite::{Connection, Row};
-use sea_query::{Expr, Query, SqliteQueryBuilder};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Url,
+};
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ValueRef},
+    Connection, Row, Transaction,
+};
+use sea_query::{
+    Alias, Expr, Func, InsertStatement, OnConflict, Order, Query, SelectStatement, SimpleExpr,
+    SqliteQueryBuilder, UpdateStatement,
+};
 use sea_query_rusqlite::RusqliteBinder;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
+use crate::util::{normalize_url, sort_key};
+
+use super::database::{
+    format_timestamp, fts_query, get_optional_timestamp, get_timestamp, FeedTags, Feeds, FeedsFts,
+    FilterRules, Items,
+};
+use super::items::{self, ItemStatus};
+
+/// The format SQLite's date and time functions produce, in UTC.
+const SQLITE_DATETIME: &str = "%Y-%m-%d %H:%M:%S";
 
-use super::database::Feeds;
+/// The shortest wait after a failed fetch, for feeds whose own interval is shorter.
+const BACKOFF_BASE_MINUTES: i32 = 5;
+/// The longest wait between retries of a failing feed.
+const BACKOFF_MAX_MINUTES: i32 = 24 * 60;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// The version of the shape `Feed` is serialized in for the frontend. Bump it whenever a field is
+/// added, removed, renamed or changes type, so that a frontend built against another shape can
+/// tell.
+pub const FEED_SCHEMA_VERSION: u32 = 3;
+
+/// Serialized as it is stored and displayed, in lowercase. The capitalized names it used to be
+/// serialized as, which older backups contain, are still accepted.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
 pub enum FeedStatus {
+    #[serde(alias = "Subscribed")]
     Subscribed,
+    #[serde(alias = "Unsubscribed")]
     Unsubscribed,
 }
 
@@ -44,98 +78,792 @@ impl FromStr for FeedStatus {
     }
 }
 
-#[derive(Serialize, Debug)]
+impl FromSql for FeedStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()
+            .and_then(|x| Self::from_str(x).map_err(|e| FromSqlError::Other(Box::new(e))))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Feed {
     pub id: i32,
+    /// The title the feed gives itself, as of the last fetch.
     pub title: String,
+    /// The title the user renamed the feed to, which refreshes leave alone.
+    pub user_title: Option<String>,
     pub link: String,
     pub status: FeedStatus,
     pub checked_at: DateTime<FixedOffset>,
+    pub fetch_interval_minutes: i32,
+    pub deleted_at: Option<DateTime<FixedOffset>>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub html_url: Option<String>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: i32,
+    pub position: i32,
+    /// The media type of the feed as of its last successful fetch.
+    pub content_type: Option<String>,
+    /// A muted feed is still refreshed, but its unread items are left out of the total count.
+    pub is_muted: bool,
+    pub headers: FeedHeaders,
+    /// When the feed was subscribed to.
+    pub created_at: DateTime<FixedOffset>,
+    /// When the feed was last changed through `update`.
+    pub updated_at: DateTime<FixedOffset>,
+    /// Whether new items of the feed are announced with a desktop notification.
+    pub notify_on_new: bool,
+    /// The checkpoint of notifications: items stored after it have not been announced yet.
+    pub last_notified_at: Option<DateTime<FixedOffset>>,
+    /// The last item announced, which tells apart the items stored at the same time as it.
+    pub last_notified_item_id: Option<i32>,
+    /// How many of the newest items of a fetch are stored, in place of the global setting. 0
+    /// stores them all.
+    pub max_items_per_fetch: Option<i32>,
+    /// The WebSub hub the feed last advertised, which pushes its updates to subscribers.
+    pub hub_url: Option<String>,
+    /// The link the feed is subscribed to at the hub as, its own `self` link.
+    pub topic_url: Option<String>,
+    /// Until when the server asked, with a 429, not to be fetched again.
+    pub retry_after: Option<DateTime<FixedOffset>>,
+    /// The item the user last read in the feed, to come back to when reopening it.
+    pub last_read_item_id: Option<i32>,
+}
+
+impl TryFrom<&Row<'_>> for Feed {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            title: row.get("title")?,
+            user_title: row.get("user_title")?,
+            link: row.get("link")?,
+            status: row.get("status")?,
+            checked_at: get_timestamp(row, "checked_at")?,
+            fetch_interval_minutes: row.get("fetch_interval_minutes")?,
+            deleted_at: get_optional_timestamp(row, "deleted_at")?,
+            etag: row.get("etag")?,
+            last_modified: row.get("last_modified")?,
+            html_url: row.get("html_url")?,
+            last_error: row.get("last_error")?,
+            consecutive_failures: row.get("consecutive_failures")?,
+            position: row.get("position")?,
+            content_type: row.get("content_type")?,
+            is_muted: row.get("is_muted")?,
+            headers: row.get("headers")?,
+            created_at: get_timestamp(row, "created_at")?,
+            updated_at: get_timestamp(row, "updated_at")?,
+            notify_on_new: row.get("notify_on_new")?,
+            last_notified_at: get_optional_timestamp(row, "last_notified_at")?,
+            last_notified_item_id: row.get("last_notified_item_id")?,
+            max_items_per_fetch: row.get("max_items_per_fetch")?,
+            hub_url: row.get("hub_url")?,
+            topic_url: row.get("topic_url")?,
+            retry_after: get_optional_timestamp(row, "retry_after")?,
+            last_read_item_id: row.get("last_read_item_id")?,
+        })
+    }
 }
 
-impl From<&Row<'_>> for Feed {
-    fn from(row: &Row) -> Self {
+/// Feeds as sent to the frontend, tagged with the `FEED_SCHEMA_VERSION` they are serialized in.
+#[derive(Serialize, Debug)]
+pub struct FeedPayload<T> {
+    pub schema_version: u32,
+    pub data: T,
+}
+
+impl<T> FeedPayload<T> {
+    pub fn new(data: T) -> Self {
         Self {
-            id: row.get_unwrap("id"),
-            title: row.get_unwrap("title"),
-            link: row.get_unwrap("link"),
-            status: FeedStatus::from_str(&row.get_unwrap::<&str, String>("status")).unwrap(),
-            checked_at: row.get_unwrap("checked_at"),
+            schema_version: FEED_SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+impl Feed {
+    /// The title to show the feed with: the user's own if they renamed it.
+    pub fn display_title(&self) -> &str {
+        self.user_title.as_deref().unwrap_or(&self.title)
+    }
+
+    /// Compares what a fetch found out about the feed with what is stored, and returns an update of
+    /// only the fields that differ, or `None` when nothing does. A blank title, or a missing website
+    /// link or media type, leaves the stored one alone.
+    pub fn diff(&self, fetched: &FeedMetadata) -> Option<FeedToUpdate> {
+        let mut update = FeedToUpdate::for_id(self.id).build();
+
+        let title = fetched.title.trim();
+        if !title.is_empty() && title != self.title {
+            update.remote_title = Some(title.to_string());
+        }
+        if fetched.html_url.is_some() && fetched.html_url != self.html_url.as_deref() {
+            update.html_url = fetched.html_url.map(str::to_string);
+        }
+        if fetched.content_type.is_some() && fetched.content_type != self.content_type.as_deref() {
+            update.content_type = fetched.content_type.map(str::to_string);
+        }
+
+        (!update.is_empty()).then_some(update)
+    }
+
+    /// Compares the feed as edited with the feed as stored, and returns an update of only the fields
+    /// the user changed, or `None` when they changed nothing. Unlike converting the whole feed, this
+    /// leaves alone what a refresh changed in the meantime.
+    pub fn edits_from(&self, stored: &Feed) -> Option<FeedToUpdate> {
+        let mut edited = FeedToUpdate::from_feed(self);
+
+        if self.user_title == stored.user_title {
+            edited.title = None;
+        }
+        if self.link == stored.link {
+            edited.link = None;
+        }
+        if self.status == stored.status {
+            edited.status = None;
+        }
+        if self.fetch_interval_minutes == stored.fetch_interval_minutes {
+            edited.fetch_interval_minutes = None;
+        }
+        if self.html_url == stored.html_url {
+            edited.html_url = None;
+        }
+
+        (!edited.is_empty()).then_some(edited)
+    }
+}
+
+/// What fetching a feed says about the feed itself.
+pub struct FeedMetadata<'a> {
+    pub title: &'a str,
+    pub html_url: Option<&'a str>,
+    pub content_type: Option<&'a str>,
+}
+
+/// Extra HTTP headers sent whenever the feed is fetched, such as an `Authorization` token for a
+/// private feed. `Debug` shows only their names, so the values stay out of logs.
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+pub struct FeedHeaders(pub HashMap<String, String>);
+
+impl fmt::Debug for FeedHeaders {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.keys().map(|x| (x, "<redacted>")))
+            .finish()
+    }
+}
+
+impl FromSql for FeedHeaders {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(Self::default()),
+            value => serde_json::from_str(value.as_str()?)
+                .map(Self)
+                .map_err(|e| FromSqlError::Other(Box::new(e))),
         }
     }
 }
 
+/// A subscribed feed as listed in the sidebar, with what the list shows next to it.
+#[derive(Serialize, Debug)]
+pub struct FeedSummary {
+    #[serde(flatten)]
+    pub feed: Feed,
+    /// Hidden items are not counted.
+    pub unread_count: i64,
+    pub has_favicon: bool,
+}
+
+impl TryFrom<&Row<'_>> for FeedSummary {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            feed: Feed::try_from(row)?,
+            unread_count: row.get("unread_count")?,
+            has_favicon: row.get("has_favicon")?,
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Favicon {
+    pub data: Vec<u8>,
+    pub content_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub enum FeedSort {
+    TitleAsc,
+    TitleDesc,
+    CheckedAtAsc,
+    CheckedAtDesc,
+    /// The most recently added first.
+    CreatedAtDesc,
+}
+
 #[derive(Deserialize)]
 pub struct FeedToCreate {
     pub title: String,
     pub link: String,
 }
 
+impl FeedToCreate {
+    /// Checks that the title is not blank and that the link is an absolute `http(s)` URL.
+    pub fn validate(&self) -> Result<()> {
+        if self.title.trim().is_empty() {
+            return Err(Error::InvalidFeed("title is empty".to_string()));
+        }
+
+        validate_link(&self.link)
+    }
+}
+
+fn validate_link(link: &str) -> Result<()> {
+    match Url::parse(link.trim()) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(()),
+        _ => Err(Error::InvalidFeed(format!(
+            "`{link}` is not an http(s) URL"
+        ))),
+    }
+}
+
 #[derive(Deserialize)]
 pub struct FeedToUpdate {
     pub id: i32,
+    /// Renames the feed. An empty title reverts to the feed's own.
     pub title: Option<String>,
     pub link: Option<String>,
     pub status: Option<FeedStatus>,
     pub checked_at: Option<DateTime<FixedOffset>>,
+    pub fetch_interval_minutes: Option<i32>,
+    pub html_url: Option<String>,
+    /// The title the feed gives itself, which only refreshes set.
+    #[serde(skip)]
+    pub remote_title: Option<String>,
+    #[serde(skip)]
+    pub content_type: Option<String>,
 }
 
-pub fn create(db: &Connection, arg: &FeedToCreate) -> Result<usize> {
-    let (sql, values) = Query::insert()
-        .into_table(Feeds::Table)
-        .columns([Feeds::Title, Feeds::Link, Feeds::CheckedAt])
-        .values_panic([(*arg.title).into(), (*arg.link).into(), Utc::now().into()])
+impl FeedToUpdate {
+    /// Starts an update of the feed with the id that changes nothing until fields are set.
+    pub fn for_id(id: i32) -> FeedToUpdateBuilder {
+        FeedToUpdateBuilder(Self {
+            id,
+            title: None,
+            link: None,
+            status: None,
+            checked_at: None,
+            fetch_interval_minutes: None,
+            html_url: None,
+            remote_title: None,
+            content_type: None,
+        })
+    }
+
+    /// An update setting every field the user edits to its value in `feed`. A feed without its own
+    /// title gets an empty one, which reverts to the title the feed gives itself. What only refreshes
+    /// set, such as `checked_at`, is left alone, and so are `created_at` and `updated_at`.
+    fn from_feed(feed: &Feed) -> Self {
+        let mut update = Self::for_id(feed.id)
+            .title(feed.user_title.clone().unwrap_or_default())
+            .link(feed.link.clone())
+            .status(feed.status)
+            .fetch_interval_minutes(feed.fetch_interval_minutes)
+            .build();
+        update.html_url = feed.html_url.clone();
+
+        update
+    }
+
+    /// Whether the update sets no field at all.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.link.is_none()
+            && self.status.is_none()
+            && self.checked_at.is_none()
+            && self.fetch_interval_minutes.is_none()
+            && self.html_url.is_none()
+            && self.remote_title.is_none()
+            && self.content_type.is_none()
+    }
+}
+
+impl From<Feed> for FeedToUpdate {
+    fn from(feed: Feed) -> Self {
+        Self::from_feed(&feed)
+    }
+}
+
+/// Builds a `FeedToUpdate` one field at a time:
+/// `FeedToUpdate::for_id(id).title("x").status(FeedStatus::Unsubscribed).build()`.
+pub struct FeedToUpdateBuilder(FeedToUpdate);
+
+impl FeedToUpdateBuilder {
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.0.title = Some(title.into());
+        self
+    }
+
+    pub fn link(mut self, link: impl Into<String>) -> Self {
+        self.0.link = Some(link.into());
+        self
+    }
+
+    pub fn status(mut self, status: FeedStatus) -> Self {
+        self.0.status = Some(status);
+        self
+    }
+
+    pub fn checked_at(mut self, checked_at: DateTime<FixedOffset>) -> Self {
+        self.0.checked_at = Some(checked_at);
+        self
+    }
+
+    pub fn fetch_interval_minutes(mut self, fetch_interval_minutes: i32) -> Self {
+        self.0.fetch_interval_minutes = Some(fetch_interval_minutes);
+        self
+    }
+
+    pub fn html_url(mut self, html_url: impl Into<String>) -> Self {
+        self.0.html_url = Some(html_url.into());
+        self
+    }
+
+    pub fn remote_title(mut self, remote_title: impl Into<String>) -> Self {
+        self.0.remote_title = Some(remote_title.into());
+        self
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.0.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn build(self) -> FeedToUpdate {
+        self.0
+    }
+}
+
+/// Creates the feed and returns it as stored, with its id and normalized link. This used to return
+/// the number of rows affected, which was always 1.
+#[tracing::instrument(skip_all, fields(link = %arg.link), err)]
+pub fn create(db: &Connection, arg: &FeedToCreate) -> Result<Feed> {
+    let id = insert_or_restore(db, arg)?;
+    read_required(db, id)
+}
+
+/// Returns the feed with the normalized link if there is one, restoring it if it was archived, or
+/// else creates it. This is a single statement, so concurrent imports of the same link cannot both
+/// create it.
+pub fn upsert_by_link(db: &Connection, arg: &FeedToCreate) -> Result<Feed> {
+    arg.validate()?;
+
+    let (sql, values) = insert_feed(&arg.title, normalize_url(&arg.link))
+        .on_conflict(
+            OnConflict::column(Feeds::Link)
+                .value(Feeds::DeletedAt, Expr::val(Option::<String>::None))
+                .to_owned(),
+        )
+        .returning_col(Feeds::Id)
         .build_rusqlite(SqliteQueryBuilder);
+    let id = db.query_row(sql.as_str(), &*values.as_params(), |x| x.get(0))?;
+    update_title_sort_key(db, id)?;
 
-    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+    read_required(db, id)
 }
 
+/// Creates all `args` in a single transaction and returns their ids in order. If any of them
+/// fails, none are created.
+pub fn create_many(db: &Connection, args: &[FeedToCreate]) -> Result<Vec<i32>> {
+    let tx = db.unchecked_transaction()?;
+
+    let mut ids = Vec::with_capacity(args.len());
+    for arg in args {
+        ids.push(insert_or_restore(&tx, arg)?);
+    }
+
+    tx.commit()?;
+    Ok(ids)
+}
+
+/// Reads the subscribed feeds in their custom order.
 pub fn read_all(db: &Connection) -> Result<Vec<Feed>> {
+    read_by_status(db, &FeedStatus::Subscribed)
+}
+
+/// Calls `f` with each feed `read_all` would return, one row at a time instead of all of them at
+/// once, and stops at the first error `f` returns.
+pub fn for_each(db: &Connection, mut f: impl FnMut(Feed) -> Result<()>) -> Result<()> {
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col((Feeds::Table, Feeds::Status)).eq(FeedStatus::Subscribed.to_string()))
+        .order_by(Feeds::Position, Order::Asc)
+        .order_by(Feeds::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    for row in stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))? {
+        f(row?)?;
+    }
+
+    Ok(())
+}
+
+/// Reads every feed, subscribed or not, in their custom order.
+pub fn read_including_unsubscribed(db: &Connection) -> Result<Vec<Feed>> {
+    read_paged(db, 0, i64::MAX as u64)
+}
+
+/// Reads the subscribed feeds in their custom order, each with its unread count, in one query.
+pub fn read_sidebar(db: &Connection) -> Result<Vec<FeedSummary>> {
+    let (sql, values) = select_feeds()
+        .expr_as(
+            Func::count(Expr::col((Items::Table, Items::Id))),
+            Alias::new("unread_count"),
+        )
+        .expr_as(
+            Expr::col((Feeds::Table, Feeds::Favicon)).is_not_null(),
+            Alias::new("has_favicon"),
+        )
+        .left_join(
+            Items::Table,
+            Expr::col((Items::Table, Items::Feed))
+                .equals((Feeds::Table, Feeds::Id))
+                .and(Expr::col((Items::Table, Items::Status)).eq(ItemStatus::Unread.to_string()))
+                .and(Expr::col((Items::Table, Items::IsHidden)).eq(false)),
+        )
+        .and_where(Expr::col((Feeds::Table, Feeds::Status)).eq(FeedStatus::Subscribed.to_string()))
+        .group_by_col((Feeds::Table, Feeds::Id))
+        .order_by((Feeds::Table, Feeds::Position), Order::Asc)
+        .order_by((Feeds::Table, Feeds::Id), Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| FeedSummary::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<FeedSummary>, _>>()?)
+}
+
+pub fn read_by_status(db: &Connection, status: &FeedStatus) -> Result<Vec<Feed>> {
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col((Feeds::Table, Feeds::Status)).eq(status.to_string()))
+        .order_by(Feeds::Position, Order::Asc)
+        .order_by(Feeds::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+/// Reads a page of feeds in their custom order.
+pub fn read_paged(db: &Connection, offset: u64, limit: u64) -> Result<Vec<Feed>> {
+    let (sql, values) = select_feeds()
+        .order_by(Feeds::Position, Order::Asc)
+        .order_by(Feeds::Id, Order::Asc)
+        .limit(limit)
+        .offset(offset)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+pub fn read_sorted(db: &Connection, sort: &FeedSort) -> Result<Vec<Feed>> {
+    let (column, order) = match sort {
+        FeedSort::TitleAsc => (Expr::col(Feeds::TitleSortKey).into(), Order::Asc),
+        FeedSort::TitleDesc => (Expr::col(Feeds::TitleSortKey).into(), Order::Desc),
+        FeedSort::CheckedAtAsc => (Expr::col(Feeds::CheckedAt).into(), Order::Asc),
+        FeedSort::CheckedAtDesc => (Expr::col(Feeds::CheckedAt).into(), Order::Desc),
+        FeedSort::CreatedAtDesc => (Expr::col(Feeds::CreatedAt).into(), Order::Desc),
+    };
+    // Feeds added at the same time, such as by one import, keep the order they were added in.
+    let tie = match sort {
+        FeedSort::CreatedAtDesc => Order::Desc,
+        _ => Order::Asc,
+    };
+
+    let (sql, values) = select_feeds()
+        .order_by_expr(column, order)
+        .order_by(Feeds::Id, tie)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+/// Reads the subscribed feeds whose own fetch interval has elapsed since they were last checked,
+/// as of `now`. The feeds that became due the longest ago come first.
+///
+/// A failing feed backs off: its interval doubles with each consecutive failure, starting from at
+/// least `BACKOFF_BASE_MINUTES` and capped at `BACKOFF_MAX_MINUTES`. A feed whose server asked to
+/// be left alone is not due before its `retry_after`, whatever its interval.
+pub fn read_stale(db: &Connection, now: DateTime<FixedOffset>) -> Result<Vec<Feed>> {
+    let delay = format!(
+        "CASE WHEN consecutive_failures = 0 THEN fetch_interval_minutes \
+         ELSE min(max(fetch_interval_minutes, {BACKOFF_BASE_MINUTES}) << min(consecutive_failures, 16), {BACKOFF_MAX_MINUTES}) END"
+    );
+    let due_at = format!("datetime(checked_at, '+' || ({delay}) || ' minutes')");
+
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col(Feeds::Status).eq(FeedStatus::Subscribed.to_string()))
+        // Implied by the due time check below since intervals are never negative, but lets SQLite
+        // narrow the candidates down with the `checked_at` index first.
+        .and_where(Expr::col((Feeds::Table, Feeds::CheckedAt)).lte(format_timestamp(&now)))
+        .and_where(Expr::cust_with_values(
+            format!("{due_at} <= ?"),
+            [now.with_timezone(&Utc).format(SQLITE_DATETIME).to_string()],
+        ))
+        .and_where(
+            Expr::col((Feeds::Table, Feeds::RetryAfter))
+                .is_null()
+                .or(Expr::col((Feeds::Table, Feeds::RetryAfter)).lte(format_timestamp(&now))),
+        )
+        .order_by_expr(Expr::cust(&due_at), Order::Asc)
+        .order_by(Feeds::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+/// Reads up to `limit` subscribed feeds that were checked most recently, the latest first. Feeds
+/// never checked are left out. The `checked_at` index gives this order, so only `limit` rows are read.
+pub fn read_recent(db: &Connection, limit: u64) -> Result<Vec<Feed>> {
+    let never = format_timestamp(&Utc.timestamp_opt(0, 0).unwrap());
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col((Feeds::Table, Feeds::Status)).eq(FeedStatus::Subscribed.to_string()))
+        .and_where(Expr::col((Feeds::Table, Feeds::CheckedAt)).gt(never))
+        .order_by((Feeds::Table, Feeds::CheckedAt), Order::Desc)
+        .order_by((Feeds::Table, Feeds::Id), Order::Desc)
+        .limit(limit)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+/// Reads the subscribed feeds whose new items are announced, in their custom order.
+pub fn read_notifying(db: &Connection) -> Result<Vec<Feed>> {
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col((Feeds::Table, Feeds::Status)).eq(FeedStatus::Subscribed.to_string()))
+        .and_where(Expr::col((Feeds::Table, Feeds::NotifyOnNew)).eq(true))
+        .order_by(Feeds::Position, Order::Asc)
+        .order_by(Feeds::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+/// Reads the feeds whose last `min_failures` or more fetches in a row failed, the most failing first.
+pub fn read_unhealthy(db: &Connection, min_failures: i32) -> Result<Vec<Feed>> {
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col((Feeds::Table, Feeds::ConsecutiveFailures)).gte(min_failures.max(1)))
+        .order_by(Feeds::ConsecutiveFailures, Order::Desc)
+        .order_by(Feeds::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+pub fn count(db: &Connection) -> Result<i64> {
     let (sql, values) = Query::select()
-        .columns([
-            Feeds::Id,
-            Feeds::Title,
-            Feeds::Link,
-            Feeds::Status,
-            Feeds::CheckedAt,
-        ])
+        .expr(Func::count(Expr::col(Feeds::Id)))
         .from(Feeds::Table)
+        .and_where(Expr::col(Feeds::DeletedAt).is_null())
         .build_rusqlite(SqliteQueryBuilder);
 
-    let mut stmt = db.prepare(sql.as_str())?;
-    let rows = stmt.query_map(&*values.as_params(), |x| Ok(Feed::from(x)))?;
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let mut rows = stmt.query(&*values.as_params())?;
 
-    Ok(rows.map(std::result::Result::unwrap).collect::<Vec<Feed>>())
+    Ok(if let Some(row) = rows.next()? {
+        row.get(0)?
+    } else {
+        0
+    })
 }
 
 pub fn read(db: &Connection, id: i32) -> Result<Option<Feed>> {
+    let (sql, values) = select_feeds()
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .limit(1)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let mut rows = stmt.query(&*values.as_params())?;
+
+    Ok(rows.next()?.map(Feed::try_from).transpose()?)
+}
+
+pub fn read_required(db: &Connection, id: i32) -> Result<Feed> {
+    read(db, id)?.ok_or(Error::NotFound(id))
+}
+
+/// Counts the feeds that are not archived by status, in a single query. Statuses with no feeds are
+/// left out, and so are rows with a status this version does not know, which are reported instead.
+pub fn count_by_status(db: &Connection) -> Result<HashMap<FeedStatus, i64>> {
     let (sql, values) = Query::select()
-        .columns([
-            Feeds::Id,
-            Feeds::Title,
-            Feeds::Link,
-            Feeds::Status,
-            Feeds::CheckedAt,
-        ])
+        .column(Feeds::Status)
+        .expr(Func::count(Expr::col(Feeds::Id)))
         .from(Feeds::Table)
-        .and_where(Expr::col(Feeds::Id).eq(id))
+        .and_where(Expr::col(Feeds::DeletedAt).is_null())
+        .group_by_col(Feeds::Status)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| {
+        Ok((x.get::<_, String>(0)?, x.get::<_, i64>(1)?))
+    })?;
+
+    let mut counts = HashMap::new();
+    for row in rows {
+        let (status, count) = row?;
+        match FeedStatus::from_str(&status) {
+            Ok(status) => {
+                counts.insert(status, count);
+            }
+            Err(e) => tracing::warn!("skipping {count} feed(s) when counting by status: {e}"),
+        }
+    }
+
+    Ok(counts)
+}
+
+/// Unlike the other reads, this also finds archived feeds, so that a link is never stored twice.
+pub fn read_by_link(db: &Connection, link: &str) -> Result<Option<Feed>> {
+    let (sql, values) = select_feeds_including_archived()
+        .and_where(Expr::col(Feeds::Link).eq(normalize_url(link)))
         .limit(1)
         .build_rusqlite(SqliteQueryBuilder);
 
-    let mut stmt = db.prepare(sql.as_str())?;
+    let mut stmt = db.prepare_cached(sql.as_str())?;
     let mut rows = stmt.query(&*values.as_params())?;
 
-    Ok(rows.next()?.map(Feed::from))
+    Ok(rows.next()?.map(Feed::try_from).transpose()?)
+}
+
+pub fn search(db: &Connection, query: &str) -> Result<Vec<Feed>> {
+    let Some(query) = fts_query(query) else {
+        return Ok(vec![]);
+    };
+
+    let (sql, values) = select_feeds()
+        .inner_join(
+            FeedsFts::Table,
+            Expr::col((FeedsFts::Table, FeedsFts::Rowid)).equals((Feeds::Table, Feeds::Id)),
+        )
+        .and_where(Expr::cust_with_values("feeds_fts MATCH ?", [query]))
+        .order_by((FeedsFts::Table, FeedsFts::Rank), Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
 }
 
+/// Reads up to `limit` feeds whose title starts with `prefix`, ignoring ASCII case, ordered by title.
+/// `%`, `_` and `\` in `prefix` match literally.
+pub fn find_by_title_prefix(db: &Connection, prefix: &str, limit: u64) -> Result<Vec<Feed>> {
+    let pattern = format!(
+        "{}%",
+        prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+
+    let (sql, values) = select_feeds()
+        .and_where(Expr::cust_with_values(
+            "COALESCE(feeds.user_title, feeds.title) LIKE ? ESCAPE '\\'",
+            [pattern],
+        ))
+        .order_by((Feeds::Table, Feeds::TitleSortKey), Order::Asc)
+        .order_by((Feeds::Table, Feeds::Id), Order::Asc)
+        .limit(limit)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+/// Groups the feeds, subscribed or not, whose links are the same once normalized, ignoring whether
+/// they use http or https. Only groups of more than one feed are returned, each in the feeds' custom
+/// order, and the groups in the order of their first feed.
+pub fn find_duplicates(db: &Connection) -> Result<Vec<Vec<Feed>>> {
+    let mut groups: Vec<Vec<Feed>> = vec![];
+    let mut by_link = HashMap::<String, usize>::new();
+    for feed in read_including_unsubscribed(db)? {
+        let link = normalize_url(&feed.link);
+        let key = link
+            .strip_prefix("https://")
+            .or_else(|| link.strip_prefix("http://"))
+            .unwrap_or(&link)
+            .to_string();
+
+        match by_link.get(&key) {
+            Some(&index) => groups[index].push(feed),
+            None => {
+                by_link.insert(key, groups.len());
+                groups.push(vec![feed]);
+            }
+        }
+    }
+
+    Ok(groups.into_iter().filter(|x| x.len() > 1).collect())
+}
+
+/// Updates the fields of the feed that are set in `arg`, along with when it was last updated, and
+/// returns the number of rows changed, which is 0 when no field is set. Fails with `NotFound` if
+/// there is no feed with the id. A link is normalized as `create` does, and fails with
+/// `InvalidFeed` if it is not an http(s) URL or `AlreadyExists` if another feed has it.
+///
+/// All the fields set are written by a single `UPDATE`, so setting the status and `checked_at`
+/// together needs nothing more. Use `update_with` to combine several updates in one transaction.
+#[tracing::instrument(skip_all, fields(id = arg.id), err)]
 pub fn update(db: &Connection, arg: &FeedToUpdate) -> Result<usize> {
+    let tx = db.unchecked_transaction()?;
+    let updated = update_with(&tx, arg)?;
+    tx.commit()?;
+
+    Ok(updated)
+}
+
+/// Does what `update` does within the caller's transaction.
+pub fn update_with(db: &Transaction, arg: &FeedToUpdate) -> Result<usize> {
     let mut vals = vec![];
 
     if let Some(title) = &arg.title {
-        vals.push((Feeds::Title, title.into()));
+        let title = Some(title.trim()).filter(|x| !x.is_empty());
+        vals.push((Feeds::UserTitle, title.into()));
     }
 
     if let Some(link) = &arg.link {
+        validate_link(link)?;
+        let link = normalize_url(link);
+        if let Some(feed) = read_by_link(db, &link)?.filter(|x| x.id != arg.id) {
+            return Err(Error::AlreadyExists(feed.id));
+        }
         vals.push((Feeds::Link, link.into()));
     }
 
@@ -144,23 +872,708 @@ pub fn update(db: &Connection, arg: &FeedToUpdate) -> Result<usize> {
     }
 
     if let Some(checked_at) = arg.checked_at {
-        vals.push((Feeds::CheckedAt, checked_at.into()));
+        let checked_at = clamp_checked_at(checked_at, Utc::now());
+        vals.push((Feeds::CheckedAt, format_timestamp(&checked_at).into()));
+    }
+
+    if let Some(fetch_interval_minutes) = arg.fetch_interval_minutes {
+        if fetch_interval_minutes < 0 {
+            return Err(Error::InvalidValue(fetch_interval_minutes.to_string()));
+        }
+        vals.push((Feeds::FetchIntervalMinutes, fetch_interval_minutes.into()));
     }
 
+    if let Some(html_url) = &arg.html_url {
+        vals.push((Feeds::HtmlUrl, html_url.into()));
+    }
+
+    if let Some(remote_title) = &arg.remote_title {
+        vals.push((Feeds::Title, remote_title.into()));
+    }
+
+    if let Some(content_type) = &arg.content_type {
+        vals.push((Feeds::ContentType, content_type.into()));
+    }
+
+    if vals.is_empty() {
+        return if exists(db, arg.id)? {
+            Ok(0)
+        } else {
+            Err(Error::NotFound(arg.id))
+        };
+    }
+    vals.push((Feeds::UpdatedAt, format_timestamp(&Utc::now()).into()));
+
     let (sql, values) = Query::update()
         .table(Feeds::Table)
         .values(vals)
         .and_where(Expr::col(Feeds::Id).eq(arg.id))
         .build_rusqlite(SqliteQueryBuilder);
 
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(arg.id)),
+        updated => {
+            if arg.title.is_some() || arg.remote_title.is_some() {
+                update_title_sort_key(db, arg.id)?;
+            }
+            Ok(updated)
+        }
+    }
+}
+
+pub fn subscribe(db: &Connection, id: i32) -> Result<usize> {
+    update(
+        db,
+        &FeedToUpdate::for_id(id)
+            .status(FeedStatus::Subscribed)
+            .build(),
+    )
+}
+
+pub fn unsubscribe(db: &Connection, id: i32) -> Result<usize> {
+    update(
+        db,
+        &FeedToUpdate::for_id(id)
+            .status(FeedStatus::Unsubscribed)
+            .build(),
+    )
+}
+
+/// Unsubscribes from every feed in `ids` at once and returns how many were subscribed. Ids of
+/// missing feeds are skipped.
+pub fn bulk_unsubscribe(db: &Connection, ids: &[i32]) -> Result<usize> {
+    bulk_update(
+        db,
+        ids,
+        Query::update()
+            .table(Feeds::Table)
+            .values([
+                (Feeds::Status, FeedStatus::Unsubscribed.to_string().into()),
+                (Feeds::UpdatedAt, format_timestamp(&Utc::now()).into()),
+            ])
+            .and_where(Expr::col(Feeds::Status).ne(FeedStatus::Unsubscribed.to_string())),
+    )
+}
+
+pub fn is_subscribed(db: &Connection, id: i32) -> Result<bool> {
+    Ok(read_required(db, id)?.status == FeedStatus::Subscribed)
+}
+
+pub fn set_muted(db: &Connection, id: i32, is_muted: bool) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::IsMuted, is_muted)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(id)),
+        x => Ok(x),
+    }
+}
+
+pub fn set_notify_on_new(db: &Connection, id: i32, notify_on_new: bool) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::NotifyOnNew, notify_on_new)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(id)),
+        x => Ok(x),
+    }
+}
+
+/// Sets how many of the newest items of a fetch are stored for the feed; `None` goes back to the
+/// global setting. Fails with `InvalidValue` if the limit is negative.
+pub fn set_max_items_per_fetch(db: &Connection, id: i32, limit: Option<i32>) -> Result<usize> {
+    if let Some(limit) = limit.filter(|x| *x < 0) {
+        return Err(Error::InvalidValue(limit.to_string()));
+    }
+
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::MaxItemsPerFetch, limit)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(id)),
+        x => Ok(x),
+    }
+}
+
+/// Remembers `item` as where the user left off reading the feed. Fails with `NotFound` if there is no
+/// such feed, and with `InvalidValue` if the item is not one of its items. The position is forgotten
+/// when the item is deleted.
+pub fn set_read_position(db: &Connection, id: i32, item: i32) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::LastReadItemId, item)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .and_where(
+            Expr::val(item).in_subquery(
+                Query::select()
+                    .column(Items::Id)
+                    .from(Items::Table)
+                    .and_where(Expr::col(Items::Feed).eq(id))
+                    .to_owned(),
+            ),
+        )
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 if exists(db, id)? => Err(Error::InvalidValue(item.to_string())),
+        0 => Err(Error::NotFound(id)),
+        x => Ok(x),
+    }
+}
+
+/// Moves the feed's notification checkpoint to `item`, stored `at`. Returns 0 when there is no feed
+/// with the id.
+pub fn update_last_notified_at(
+    db: &Connection,
+    id: i32,
+    at: DateTime<FixedOffset>,
+    item: i32,
+) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::LastNotifiedAt, format_timestamp(&at))
+        .value(Feeds::LastNotifiedItemId, item)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Replaces the headers sent when fetching the feed; an empty map removes them all. Fails with
+/// `InvalidValue` if a name or value could not be sent over HTTP, naming only the header.
+pub fn set_headers(db: &Connection, id: i32, headers: &HashMap<String, String>) -> Result<usize> {
+    for (name, value) in headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(Error::InvalidValue(name.to_string()));
+        }
+        if HeaderValue::from_str(value).is_err() {
+            return Err(Error::InvalidValue(format!("value of header `{name}`")));
+        }
+    }
+
+    let json = match headers.is_empty() {
+        true => None,
+        false => Some(serde_json::to_string(headers).map_err(|_| Error::Unknown)?),
+    };
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::Headers, json)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(id)),
+        x => Ok(x),
+    }
+}
+
+/// Takes a check more than the tolerance in the future, from a wrong clock, as made `now`, the way
+/// `items::clamp_published_at` does for publication dates. Otherwise `read_stale` would not pick
+/// the feed up again until that date.
+pub fn clamp_checked_at(when: DateTime<FixedOffset>, now: DateTime<Utc>) -> DateTime<FixedOffset> {
+    items::clamp_published_at(when, now).0
+}
+
+/// Sets only when the feed was last checked, without reading it first, clamped to now. Returns 0
+/// when there is no feed with the id.
+pub fn touch_checked_at(db: &Connection, id: i32, when: DateTime<FixedOffset>) -> Result<usize> {
+    let when = clamp_checked_at(when, Utc::now());
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::CheckedAt, format_timestamp(&when))
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
     Ok(db.execute(sql.as_str(), &*values.as_params())?)
 }
 
+/// Stores the cache validators the server last sent for the feed, clearing those it no longer sends.
+pub fn update_validators(
+    db: &Connection,
+    id: i32,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([
+            (Feeds::Etag, etag.into()),
+            (Feeds::LastModified, last_modified.into()),
+        ])
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Stores the WebSub hub and topic the feed last advertised, clearing them when it no longer does.
+pub fn update_websub(
+    db: &Connection,
+    id: i32,
+    hub_url: Option<&str>,
+    topic_url: Option<&str>,
+) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([
+            (Feeds::HubUrl, hub_url.into()),
+            (Feeds::TopicUrl, topic_url.into()),
+        ])
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Records the outcome of fetching the feed: a failure is stored as the last error and extends the
+/// run of consecutive failures, while a success clears both.
+pub fn update_health(db: &Connection, id: i32, error: Option<&str>) -> Result<usize> {
+    let failures = match error {
+        Some(_) => Expr::col(Feeds::ConsecutiveFailures).add(1),
+        None => Expr::val(0).into(),
+    };
+
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([
+            (Feeds::LastError, error.into()),
+            (Feeds::ConsecutiveFailures, failures),
+        ])
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Defers fetching the feed until `retry_after`, as its server asked, or lifts that with `None`.
+pub fn update_retry_after(
+    db: &Connection,
+    id: i32,
+    retry_after: Option<DateTime<FixedOffset>>,
+) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::RetryAfter, retry_after.map(|x| format_timestamp(&x)))
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+pub fn update_favicon(
+    db: &Connection,
+    id: i32,
+    data: &[u8],
+    content_type: Option<&str>,
+) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([
+            (Feeds::Favicon, data.to_vec().into()),
+            (Feeds::FaviconContentType, content_type.into()),
+        ])
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Reads the stored icon of the feed, if one was fetched.
+pub fn read_favicon(db: &Connection, id: i32) -> Result<Option<Favicon>> {
+    let (sql, values) = Query::select()
+        .columns([Feeds::Favicon, Feeds::FaviconContentType])
+        .from(Feeds::Table)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .and_where(Expr::col(Feeds::Favicon).is_not_null())
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let mut rows = stmt.query(&*values.as_params())?;
+
+    Ok(match rows.next()? {
+        Some(row) => Some(Favicon {
+            data: row.get(0)?,
+            content_type: row.get(1)?,
+        }),
+        None => None,
+    })
+}
+
+/// Archives the feed: it and its items disappear from every read, but nothing is removed until the
+/// feed is purged.
+#[tracing::instrument(skip(db), err)]
 pub fn delete(db: &Connection, id: i32) -> Result<usize> {
-    let (sql, values) = Query::delete()
-        .from_table(Feeds::Table)
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([(Feeds::DeletedAt, format_timestamp(&Utc::now()).into())])
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .and_where(Expr::col(Feeds::DeletedAt).is_null())
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Archives every feed in `ids` at once, as `delete` does, and returns how many were archived.
+pub fn bulk_delete(db: &Connection, ids: &[i32]) -> Result<usize> {
+    bulk_update(
+        db,
+        ids,
+        Query::update()
+            .table(Feeds::Table)
+            .values([(Feeds::DeletedAt, format_timestamp(&Utc::now()).into())])
+            .and_where(Expr::col(Feeds::DeletedAt).is_null()),
+    )
+}
+
+/// How many ids a bulk update binds per statement, well below SQLite's limit on the number of
+/// parameters a statement may have.
+const BULK_CHUNK_SIZE: usize = 500;
+
+/// Runs `update` on the feeds in `ids`, a chunk of them at a time, in a single transaction so that
+/// either every feed is changed or none is.
+fn bulk_update(db: &Connection, ids: &[i32], update: &UpdateStatement) -> Result<usize> {
+    let tx = db.unchecked_transaction()?;
+    let mut updated = 0;
+    for chunk in ids.chunks(BULK_CHUNK_SIZE) {
+        let (sql, values) = update
+            .clone()
+            .and_where(Expr::col(Feeds::Id).is_in(chunk.iter().copied()))
+            .build_rusqlite(SqliteQueryBuilder);
+        updated += tx.execute(sql.as_str(), &*values.as_params())?;
+    }
+    tx.commit()?;
+
+    Ok(updated)
+}
+
+pub fn read_archived(db: &Connection) -> Result<Vec<Feed>> {
+    let (sql, values) = select_feeds_including_archived()
+        .and_where(Expr::col((Feeds::Table, Feeds::DeletedAt)).is_not_null())
+        .order_by(Feeds::DeletedAt, Order::Desc)
+        .order_by(Feeds::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
+
+pub fn restore(db: &Connection, id: i32) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([(Feeds::DeletedAt, Option::<String>::None.into())])
         .and_where(Expr::col(Feeds::Id).eq(id))
         .build_rusqlite(SqliteQueryBuilder);
 
     Ok(db.execute(sql.as_str(), &*values.as_params())?)
 }
+
+/// Permanently removes the feed, whether archived or not, along with its items, tags and filter
+/// rules, and returns the total number of rows removed. The feeds after it move up to close the gap
+/// in positions.
+pub fn purge(db: &Connection, id: i32) -> Result<usize> {
+    let tx = db.unchecked_transaction()?;
+    let purged = purge_in(&tx, id)?;
+    tx.commit()?;
+
+    Ok(purged)
+}
+
+/// Deletes the feed's items, keeping those saved if `keep_saved`, and forgets its cache validators
+/// and when it was checked, so that the next refresh downloads and stores everything again. Returns
+/// the number of items deleted. Fails with `NotFound` if there is no feed with the id.
+pub fn reset(db: &Connection, id: i32, keep_saved: bool) -> Result<usize> {
+    let tx = db.unchecked_transaction()?;
+
+    let mut query = Query::delete()
+        .from_table(Items::Table)
+        .and_where(Expr::col(Items::Feed).eq(id))
+        .to_owned();
+    if keep_saved {
+        query.and_where(Expr::col(Items::IsSaved).eq(false));
+    }
+    let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
+    let deleted = tx.execute(sql.as_str(), &*values.as_params())?;
+
+    // The items stored again are not new, so they are not announced either.
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .values([
+            (Feeds::Etag, Option::<String>::None.into()),
+            (Feeds::LastModified, Option::<String>::None.into()),
+            (
+                Feeds::CheckedAt,
+                format_timestamp(&Utc.timestamp_opt(0, 0).unwrap()).into(),
+            ),
+            (Feeds::LastNotifiedAt, format_timestamp(&Utc::now()).into()),
+            (Feeds::LastNotifiedItemId, Option::<i32>::None.into()),
+        ])
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+    if tx.execute(sql.as_str(), &*values.as_params())? == 0 {
+        return Err(Error::NotFound(id));
+    }
+
+    tx.commit()?;
+    Ok(deleted)
+}
+
+/// Moves the items of feed `remove` to feed `keep` and then purges `remove`, all or nothing, and
+/// returns the number of items moved. An item that `keep` already has, by guid or else by link, is
+/// dropped instead, after passing on whether it was read or saved to the copy in `keep`.
+pub fn merge(db: &Connection, keep: i32, remove: i32) -> Result<usize> {
+    if keep == remove {
+        return Err(Error::InvalidValue(format!(
+            "cannot merge feed {keep} into itself"
+        )));
+    }
+
+    let tx = db.unchecked_transaction()?;
+    for id in [keep, remove] {
+        read_position(&tx, id)?.ok_or(Error::NotFound(id))?;
+    }
+    let moved = items::move_all(&tx, remove, keep)?;
+    purge_in(&tx, remove)?;
+    tx.commit()?;
+
+    Ok(moved)
+}
+
+/// Moves the feed to `new_position`, clamped to the positions in use, shifting the feeds in between
+/// by one so that positions stay unique. Archived feeds keep their positions too, so they come back
+/// where they were when restored.
+pub fn reorder(db: &Connection, id: i32, new_position: i32) -> Result<()> {
+    let tx = db.unchecked_transaction()?;
+    let old_position = read_position(&tx, id)?.ok_or(Error::NotFound(id))?;
+
+    let (sql, values) = Query::select()
+        .expr(Func::max(Expr::col(Feeds::Position)))
+        .from(Feeds::Table)
+        .build_rusqlite(SqliteQueryBuilder);
+    let last: i32 = tx.query_row(sql.as_str(), &*values.as_params(), |x| x.get(0))?;
+    let new_position = new_position.clamp(0, last);
+
+    let (range, shift) = if new_position < old_position {
+        (
+            Expr::col(Feeds::Position)
+                .gte(new_position)
+                .and(Expr::col(Feeds::Position).lt(old_position)),
+            Expr::col(Feeds::Position).add(1),
+        )
+    } else {
+        (
+            Expr::col(Feeds::Position)
+                .gt(old_position)
+                .and(Expr::col(Feeds::Position).lte(new_position)),
+            Expr::col(Feeds::Position).sub(1),
+        )
+    };
+
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::Position, shift)
+        .and_where(range)
+        .build_rusqlite(SqliteQueryBuilder);
+    tx.execute(sql.as_str(), &*values.as_params())?;
+
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::Position, new_position)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+    tx.execute(sql.as_str(), &*values.as_params())?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// `purge` within a transaction the caller holds.
+fn purge_in(tx: &Transaction, id: i32) -> Result<usize> {
+    let Some(position) = read_position(tx, id)? else {
+        return Ok(0);
+    };
+
+    // Foreign keys are not enforced, so the rows referring to the feed are removed by hand.
+    let mut purged = 0;
+    for (sql, values) in [
+        Query::delete()
+            .from_table(Items::Table)
+            .and_where(Expr::col(Items::Feed).eq(id))
+            .build_rusqlite(SqliteQueryBuilder),
+        Query::delete()
+            .from_table(FeedTags::Table)
+            .and_where(Expr::col(FeedTags::Feed).eq(id))
+            .build_rusqlite(SqliteQueryBuilder),
+        Query::delete()
+            .from_table(FilterRules::Table)
+            .and_where(Expr::col(FilterRules::Feed).eq(id))
+            .build_rusqlite(SqliteQueryBuilder),
+        Query::delete()
+            .from_table(Feeds::Table)
+            .and_where(Expr::col(Feeds::Id).eq(id))
+            .build_rusqlite(SqliteQueryBuilder),
+    ] {
+        purged += tx.execute(sql.as_str(), &*values.as_params())?;
+    }
+
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::Position, Expr::col(Feeds::Position).sub(1))
+        .and_where(Expr::col(Feeds::Position).gt(position))
+        .build_rusqlite(SqliteQueryBuilder);
+    tx.execute(sql.as_str(), &*values.as_params())?;
+
+    Ok(purged)
+}
+
+/// Inserts a feed and returns its id. Adding back a feed that was archived restores it along with
+/// its items instead.
+fn insert_or_restore(db: &Connection, arg: &FeedToCreate) -> Result<i32> {
+    arg.validate()?;
+
+    let link = normalize_url(&arg.link);
+    if let Some(feed) = read_by_link(db, &link)? {
+        if feed.deleted_at.is_none() {
+            return Err(Error::AlreadyExists(feed.id));
+        }
+        restore(db, feed.id)?;
+        return Ok(feed.id);
+    }
+
+    let (sql, values) = insert_feed(&arg.title, link).build_rusqlite(SqliteQueryBuilder);
+
+    db.execute(sql.as_str(), &*values.as_params())?;
+    let id = db.last_insert_rowid() as i32;
+    update_title_sort_key(db, id)?;
+
+    Ok(id)
+}
+
+/// New feeds go last, and count as never checked so that the next check fetches them right away.
+fn insert_feed(title: &str, link: String) -> InsertStatement {
+    let now = format_timestamp(&Utc::now());
+    Query::insert()
+        .into_table(Feeds::Table)
+        .columns([
+            Feeds::Title,
+            Feeds::Link,
+            Feeds::CheckedAt,
+            Feeds::Position,
+            Feeds::CreatedAt,
+            Feeds::UpdatedAt,
+        ])
+        .values_panic([
+            title.into(),
+            link.into(),
+            format_timestamp(&Utc.timestamp_opt(0, 0).unwrap()).into(),
+            Expr::cust("(SELECT COALESCE(MAX(position) + 1, 0) FROM feeds)"),
+            now.clone().into(),
+            now.into(),
+        ])
+        .to_owned()
+}
+
+/// Recomputes the key the feed is sorted by from the title it is shown with. SQLite cannot fold
+/// accents itself, so this is done here after every change to either title.
+fn update_title_sort_key(db: &Connection, id: i32) -> Result<()> {
+    let (sql, values) = Query::select()
+        .expr(display_title())
+        .from(Feeds::Table)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let mut rows = stmt.query(&*values.as_params())?;
+    let Some(title) = rows.next()?.map(|x| x.get::<_, String>(0)).transpose()? else {
+        return Ok(());
+    };
+
+    let (sql, values) = Query::update()
+        .table(Feeds::Table)
+        .value(Feeds::TitleSortKey, sort_key(&title))
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+    db.execute(sql.as_str(), &*values.as_params())?;
+
+    Ok(())
+}
+
+fn read_position(db: &Connection, id: i32) -> Result<Option<i32>> {
+    let (sql, values) = Query::select()
+        .column(Feeds::Position)
+        .from(Feeds::Table)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let mut rows = stmt.query(&*values.as_params())?;
+
+    Ok(rows.next()?.map(|x| x.get(0)).transpose()?)
+}
+
+/// Checks whether a feed with the id is stored, archived or not.
+fn exists(db: &Connection, id: i32) -> Result<bool> {
+    let (sql, values) = Query::select()
+        .expr(Expr::val(1))
+        .from(Feeds::Table)
+        .and_where(Expr::col(Feeds::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    Ok(stmt.exists(&*values.as_params())?)
+}
+
+pub(super) fn select_feeds() -> SelectStatement {
+    select_feeds_including_archived()
+        .and_where(Expr::col((Feeds::Table, Feeds::DeletedAt)).is_null())
+        .to_owned()
+}
+
+/// The title a feed is shown with, for joining feeds to items.
+pub(super) fn display_title() -> SimpleExpr {
+    Expr::cust("COALESCE(feeds.user_title, feeds.title)")
+}
+
+fn select_feeds_including_archived() -> SelectStatement {
+    Query::select()
+        .columns([
+            (Feeds::Table, Feeds::Id),
+            (Feeds::Table, Feeds::Title),
+            (Feeds::Table, Feeds::UserTitle),
+            (Feeds::Table, Feeds::Link),
+            (Feeds::Table, Feeds::Status),
+            (Feeds::Table, Feeds::CheckedAt),
+            (Feeds::Table, Feeds::FetchIntervalMinutes),
+            (Feeds::Table, Feeds::DeletedAt),
+            (Feeds::Table, Feeds::Etag),
+            (Feeds::Table, Feeds::LastModified),
+            (Feeds::Table, Feeds::HtmlUrl),
+            (Feeds::Table, Feeds::LastError),
+            (Feeds::Table, Feeds::ConsecutiveFailures),
+            (Feeds::Table, Feeds::Position),
+            (Feeds::Table, Feeds::ContentType),
+            (Feeds::Table, Feeds::IsMuted),
+            (Feeds::Table, Feeds::Headers),
+            (Feeds::Table, Feeds::CreatedAt),
+            (Feeds::Table, Feeds::UpdatedAt),
+            (Feeds::Table, Feeds::NotifyOnNew),
+            (Feeds::Table, Feeds::LastNotifiedAt),
+            (Feeds::Table, Feeds::LastNotifiedItemId),
+            (Feeds::Table, Feeds::MaxItemsPerFetch),
+            (Feeds::Table, Feeds::HubUrl),
+            (Feeds::Table, Feeds::TopicUrl),
+            (Feeds::Table, Feeds::RetryAfter),
+            (Feeds::Table, Feeds::LastReadItemId),
+        ])
+        .from(Feeds::Table)
+        .to_owned()
+}
@@ -0,0 +1,204 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ValueRef},
+    Connection, Row,
+};
+use sea_query::{Expr, Order, Query, SimpleExpr, SqliteQueryBuilder};
+use sea_query_rusqlite::RusqliteBinder;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+use super::database::FilterRules;
+use super::items::{ItemStatus, ItemToCreate};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum FilterAction {
+    MarkRead,
+    Hide,
+}
+
+impl Display for FilterAction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::MarkRead => write!(f, "mark_read"),
+            Self::Hide => write!(f, "hide"),
+        }
+    }
+}
+
+impl FromStr for FilterAction {
+    type Err = Error;
+
+    fn from_str(x: &str) -> std::result::Result<Self, Self::Err> {
+        match x {
+            "mark_read" => Ok(Self::MarkRead),
+            "hide" => Ok(Self::Hide),
+            _ => Err(Error::InvalidEnumKey(
+                x.to_string(),
+                "FilterAction".to_string(),
+            )),
+        }
+    }
+}
+
+impl FromSql for FilterAction {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()
+            .and_then(|x| Self::from_str(x).map_err(|e| FromSqlError::Other(Box::new(e))))
+    }
+}
+
+/// A rule applied to items as they are first stored: those whose title or content contains
+/// `pattern`, ignoring case, are marked read or hidden.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FilterRule {
+    pub id: i32,
+    pub pattern: String,
+    pub action: FilterAction,
+    /// The feed the rule is limited to, or `None` for every feed.
+    pub feed: Option<i32>,
+}
+
+impl TryFrom<&Row<'_>> for FilterRule {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            pattern: row.get("pattern")?,
+            action: row.get("action")?,
+            feed: row.get("feed")?,
+        })
+    }
+}
+
+impl FilterRule {
+    pub fn matches(&self, item: &ItemToCreate) -> bool {
+        if self.feed.is_some_and(|x| x != item.feed) {
+            return false;
+        }
+
+        let pattern = self.pattern.to_lowercase();
+        item.title.to_lowercase().contains(&pattern)
+            || item.description.to_lowercase().contains(&pattern)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FilterRuleToCreate {
+    pub pattern: String,
+    pub action: FilterAction,
+    pub feed: Option<i32>,
+}
+
+/// Replaces every field of the rule with the given id.
+#[derive(Deserialize)]
+pub struct FilterRuleToUpdate {
+    pub id: i32,
+    pub pattern: String,
+    pub action: FilterAction,
+    pub feed: Option<i32>,
+}
+
+pub fn create(db: &Connection, arg: &FilterRuleToCreate) -> Result<i32> {
+    let (sql, values) = Query::insert()
+        .into_table(FilterRules::Table)
+        .columns([FilterRules::Pattern, FilterRules::Action, FilterRules::Feed])
+        .values_panic([
+            validate_pattern(&arg.pattern)?.into(),
+            arg.action.to_string().into(),
+            arg.feed.into(),
+        ])
+        .build_rusqlite(SqliteQueryBuilder);
+    db.execute(sql.as_str(), &*values.as_params())?;
+
+    Ok(db.last_insert_rowid() as i32)
+}
+
+pub fn read_all(db: &Connection) -> Result<Vec<FilterRule>> {
+    read_where(db, Expr::val(true).into())
+}
+
+/// Reads the rules that apply to `feed`: its own and those for every feed.
+pub fn read_for_feed(db: &Connection, feed: i32) -> Result<Vec<FilterRule>> {
+    read_where(
+        db,
+        Expr::col(FilterRules::Feed)
+            .is_null()
+            .or(Expr::col(FilterRules::Feed).eq(feed)),
+    )
+}
+
+pub fn update(db: &Connection, arg: &FilterRuleToUpdate) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(FilterRules::Table)
+        .values([
+            (FilterRules::Pattern, validate_pattern(&arg.pattern)?.into()),
+            (FilterRules::Action, arg.action.to_string().into()),
+            (FilterRules::Feed, arg.feed.into()),
+        ])
+        .and_where(Expr::col(FilterRules::Id).eq(arg.id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(arg.id)),
+        x => Ok(x),
+    }
+}
+
+pub fn delete(db: &Connection, id: i32) -> Result<usize> {
+    let (sql, values) = Query::delete()
+        .from_table(FilterRules::Table)
+        .and_where(Expr::col(FilterRules::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Marks `item` read, hidden or both, as the rules it matches say.
+pub fn apply(rules: &[FilterRule], item: &mut ItemToCreate) {
+    let actions = rules
+        .iter()
+        .filter(|x| x.matches(item))
+        .map(|x| x.action)
+        .collect::<Vec<_>>();
+    for action in actions {
+        match action {
+            FilterAction::MarkRead => item.status = ItemStatus::Read,
+            FilterAction::Hide => item.is_hidden = true,
+        }
+    }
+}
+
+fn validate_pattern(pattern: &str) -> Result<String> {
+    match pattern.trim() {
+        "" => Err(Error::InvalidValue(pattern.to_string())),
+        x => Ok(x.to_string()),
+    }
+}
+
+fn read_where(db: &Connection, filter: SimpleExpr) -> Result<Vec<FilterRule>> {
+    let (sql, values) = Query::select()
+        .columns([
+            FilterRules::Id,
+            FilterRules::Pattern,
+            FilterRules::Action,
+            FilterRules::Feed,
+        ])
+        .from(FilterRules::Table)
+        .and_where(filter)
+        .order_by(FilterRules::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| FilterRule::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<FilterRule>, _>>()?)
+}
@@ -1,16 +1,23 @@
 use core::fmt::{self, Display, Formatter};
-use std::str::FromStr;
-
-use chrono::{DateTime, FixedOffset};
-use rusqlite::{Connection, Row};
-use sea_query::{Alias, Expr, Func, Order, Query, SqliteQueryBuilder, Values};
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ValueRef},
+    Connection, Row,
+};
+use sea_query::{
+    Alias, Expr, Func, Order, Query, SelectStatement, SimpleExpr, SqliteQueryBuilder, Value, Values,
+};
 use sea_query_rusqlite::RusqliteBinder;
 use serde::{Deserialize, Serialize};
 use sha1_smol::Sha1;
+use sha2::{Digest, Sha256};
 
 use crate::error::{Error, Result};
 
-use super::database::{Feeds, Items};
+use super::database::{format_timestamp, fts_query, get_timestamp, Feeds, Items, ItemsFts};
+use super::feeds::{self, Favicon};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ItemStatus {
@@ -44,53 +51,118 @@ impl FromStr for ItemStatus {
 
 #[derive(Serialize, Debug)]
 pub struct ItemFeed {
-    id: i32,
-    title: String,
-    link: String,
+    pub id: i32,
+    pub title: String,
+    pub link: String,
 }
 
 #[derive(Serialize, Debug)]
 pub struct Item {
-    id: i32,
-    fingerprint: String,
-    author: Option<String>,
-    title: String,
-    description: String,
-    link: String,
-    status: ItemStatus,
-    is_saved: bool,
-    published_at: DateTime<FixedOffset>,
-    feed: ItemFeed,
-}
-
-impl From<&Row<'_>> for Item {
-    fn from(row: &Row) -> Self {
-        Self {
-            id: row.get_unwrap("id"),
-            fingerprint: row.get_unwrap("fingerprint"),
-            author: row.get_unwrap("author"),
-            title: row.get_unwrap("title"),
-            description: row.get_unwrap("description"),
-            link: row.get_unwrap("link"),
-            status: ItemStatus::from_str(&row.get_unwrap::<&str, String>("status")).unwrap(),
-            is_saved: row.get_unwrap("is_saved"),
-            published_at: row.get_unwrap("published_at"),
+    pub id: i32,
+    pub fingerprint: String,
+    pub guid: Option<String>,
+    pub author: Option<String>,
+    pub title: String,
+    /// The full content when it is known, and the summary otherwise.
+    pub description: String,
+    /// Whether `description` is HTML to render, rather than plain text to escape.
+    pub content_is_html: bool,
+    pub summary: Option<String>,
+    pub link: String,
+    pub status: ItemStatus,
+    pub is_saved: bool,
+    /// Hidden by a filter rule, and so left out of what is read unless asked for.
+    pub is_hidden: bool,
+    pub published_at: DateTime<FixedOffset>,
+    /// When the item was stored.
+    pub created_at: DateTime<FixedOffset>,
+    pub feed: ItemFeed,
+}
+
+impl Item {
+    /// Whether the feed only provided a summary of the item, which `producer::fetch_full_content`
+    /// can replace with the article itself.
+    pub fn is_summary_only(&self) -> bool {
+        self.description.trim().is_empty() || self.summary.as_ref() == Some(&self.description)
+    }
+}
+
+impl FromSql for ItemStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()
+            .and_then(|x| Self::from_str(x).map_err(|e| FromSqlError::Other(Box::new(e))))
+    }
+}
+
+impl TryFrom<&Row<'_>> for Item {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            fingerprint: row.get("fingerprint")?,
+            guid: row.get("guid")?,
+            author: row.get("author")?,
+            title: row.get("title")?,
+            description: row.get("description")?,
+            content_is_html: row.get("content_is_html")?,
+            summary: row.get("summary")?,
+            link: row.get("link")?,
+            status: row.get("status")?,
+            is_saved: row.get("is_saved")?,
+            is_hidden: row.get("is_hidden")?,
+            published_at: get_timestamp(row, "published_at")?,
+            created_at: get_timestamp(row, "created_at")?,
             feed: ItemFeed {
-                id: row.get_unwrap("feed_id"),
-                title: row.get_unwrap("feed_title"),
-                link: row.get_unwrap("feed_link"),
+                id: row.get("feed_id")?,
+                title: row.get("feed_title")?,
+                link: row.get("feed_link")?,
             },
-        }
+        })
+    }
+}
+
+/// An item as shown in the timeline, with the icon of its feed.
+#[derive(Serialize, Debug)]
+pub struct TimelineItem {
+    #[serde(flatten)]
+    pub item: Item,
+    pub feed_favicon: Option<Favicon>,
+}
+
+impl TryFrom<&Row<'_>> for TimelineItem {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        let data: Option<Vec<u8>> = row.get("feed_favicon")?;
+        let content_type = row.get("feed_favicon_content_type")?;
+
+        Ok(Self {
+            item: Item::try_from(row)?,
+            feed_favicon: data.map(|data| Favicon { data, content_type }),
+        })
     }
 }
 
+/// Where a page of the timeline ends: the last item on it, by the timeline's order.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TimelineCursor {
+    pub published_at: DateTime<FixedOffset>,
+    pub id: i32,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ItemToCreate {
+    pub guid: Option<String>,
     pub author: Option<String>,
     pub title: String,
     pub description: String,
+    pub content_is_html: bool,
+    pub summary: Option<String>,
     pub link: String,
     pub status: ItemStatus,
+    pub is_hidden: bool,
     pub published_at: DateTime<FixedOffset>,
     pub feed: i32,
 }
@@ -99,6 +171,21 @@ impl ItemToCreate {
     pub fn fingerprint(&self) -> String {
         Sha1::from(format!("{}:{}", &self.title, &self.link)).hexdigest()
     }
+
+    /// Hashes the title, link and content with whitespace collapsed, so that the same post
+    /// republished under a new guid hashes the same.
+    pub fn content_hash(&self) -> String {
+        let normalize = |x: &str| x.split_whitespace().collect::<Vec<_>>().join(" ");
+        let hash = Sha256::new()
+            .chain_update(normalize(&self.title))
+            .chain_update("\n")
+            .chain_update(self.link.trim())
+            .chain_update("\n")
+            .chain_update(normalize(&self.description))
+            .finalize();
+
+        format!("{hash:x}")
+    }
 }
 
 #[derive(Deserialize)]
@@ -122,7 +209,7 @@ pub enum ItemOrder {
     UnreadFirst,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct ItemReadOption {
     pub ids: Option<Vec<i32>>,
     pub feed: Option<i32>,
@@ -131,9 +218,33 @@ pub struct ItemReadOption {
     pub order_by: Option<ItemOrder>,
     pub limit: Option<u64>,
     pub offset: Option<u64>,
+    /// Includes the items hidden by filter rules, which are otherwise left out.
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+/// How far ahead of now a publication date may be, allowing for a server clock slightly out of
+/// sync, before it is taken as wrong.
+pub fn future_tolerance() -> Duration {
+    Duration::minutes(10)
+}
+
+/// Takes a publication date more than the tolerance in the future as wrong, so that it does not
+/// stay on top of the timeline, and returns `now` instead together with the date as given.
+pub fn clamp_published_at(
+    published_at: DateTime<FixedOffset>,
+    now: DateTime<Utc>,
+) -> (DateTime<FixedOffset>, Option<DateTime<FixedOffset>>) {
+    match published_at > now + future_tolerance() {
+        true => (now.fixed_offset(), Some(published_at)),
+        false => (published_at, None),
+    }
 }
 
+/// Stores the item, with a publication date in the future clamped to now and the original kept.
 pub fn create(db: &Connection, arg: &ItemToCreate) -> Result<usize> {
+    let now = Utc::now();
+    let (published_at, original_published_at) = clamp_published_at(arg.published_at, now);
     let (sql, values) = Query::insert()
         .into_table(Items::Table)
         .columns([
@@ -145,6 +256,13 @@ pub fn create(db: &Connection, arg: &ItemToCreate) -> Result<usize> {
             Items::Status,
             Items::PublishedAt,
             Items::Feed,
+            Items::Guid,
+            Items::Summary,
+            Items::ContentHash,
+            Items::ContentIsHtml,
+            Items::IsHidden,
+            Items::CreatedAt,
+            Items::OriginalPublishedAt,
         ])
         .values_panic([
             arg.fingerprint().into(),
@@ -153,45 +271,96 @@ pub fn create(db: &Connection, arg: &ItemToCreate) -> Result<usize> {
             arg.description.clone().into(),
             arg.link.clone().into(),
             arg.status.to_string().into(),
-            arg.published_at.into(),
+            format_timestamp(&published_at).into(),
             arg.feed.into(),
+            arg.guid.clone().into(),
+            arg.summary.clone().into(),
+            arg.content_hash().into(),
+            arg.content_is_html.into(),
+            arg.is_hidden.into(),
+            format_timestamp(&now).into(),
+            original_published_at.map(|x| format_timestamp(&x)).into(),
         ])
         .build_rusqlite(SqliteQueryBuilder);
 
     Ok(db.execute(sql.as_str(), &*values.as_params())?)
 }
 
-pub fn read_all(db: &Connection, opt: &ItemReadOption) -> Result<Vec<Item>> {
-    let mut query = Query::select()
-        .columns([
-            (Items::Table, Items::Id),
-            (Items::Table, Items::Fingerprint),
-            (Items::Table, Items::Author),
-            (Items::Table, Items::Title),
-            (Items::Table, Items::Description),
-            (Items::Table, Items::Link),
-            (Items::Table, Items::Status),
-            (Items::Table, Items::IsSaved),
-            (Items::Table, Items::PublishedAt),
-        ])
-        .expr_as(Expr::col((Feeds::Table, Feeds::Id)), Alias::new("feed_id"))
-        .expr_as(
-            Expr::col((Feeds::Table, Feeds::Title)),
-            Alias::new("feed_title"),
-        )
-        .expr_as(
-            Expr::col((Feeds::Table, Feeds::Link)),
-            Alias::new("feed_link"),
-        )
+/// Checks whether `feed` already has an item with the given `guid`, or with the given `link` when
+/// the item has no guid.
+pub fn exists(db: &Connection, feed: i32, guid: Option<&str>, link: &str) -> Result<bool> {
+    Ok(find_id(db, feed, guid, link)?.is_some())
+}
+
+/// Finds the item of `feed` that `exists` would find.
+pub fn find_id(db: &Connection, feed: i32, guid: Option<&str>, link: &str) -> Result<Option<i32>> {
+    let identity = match guid {
+        Some(guid) => Expr::col(Items::Guid).eq(guid),
+        None => Expr::col(Items::Link).eq(link),
+    };
+
+    let (sql, values) = Query::select()
+        .column(Items::Id)
         .from(Items::Table)
-        .inner_join(
-            Feeds::Table,
-            Expr::col((Items::Table, Items::Feed)).equals((Feeds::Table, Feeds::Id)),
-        )
-        .clone();
+        .and_where(Expr::col(Items::Feed).eq(feed))
+        .and_where(identity)
+        .order_by(Items::Id, Order::Asc)
+        .limit(1)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let mut rows = stmt.query(&*values.as_params())?;
+
+    Ok(rows.next()?.map(|x| x.get(0)).transpose()?)
+}
+
+/// Checks whether `feed` already has an item with the given content hash, whatever its guid.
+pub fn content_exists(db: &Connection, feed: i32, content_hash: &str) -> Result<bool> {
+    let (sql, values) = Query::select()
+        .expr(Expr::val(1))
+        .from(Items::Table)
+        .and_where(Expr::col(Items::Feed).eq(feed))
+        .and_where(Expr::col(Items::ContentHash).eq(content_hash))
+        .limit(1)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    Ok(stmt.exists(&*values.as_params())?)
+}
+
+pub fn read_all(db: &Connection, opt: &ItemReadOption) -> Result<Vec<Item>> {
+    let (sql, values) = select_by_option(opt).build_rusqlite(SqliteQueryBuilder);
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Item::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Item>, _>>()?)
+}
+
+/// Calls `f` with each item `read_all` would return, one row at a time instead of all of them at
+/// once, and stops at the first error `f` returns.
+pub fn for_each(
+    db: &Connection,
+    opt: &ItemReadOption,
+    mut f: impl FnMut(Item) -> Result<()>,
+) -> Result<()> {
+    let (sql, values) = select_by_option(opt).build_rusqlite(SqliteQueryBuilder);
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    for row in stmt.query_map(&*values.as_params(), |x| Item::try_from(x))? {
+        f(row?)?;
+    }
+
+    Ok(())
+}
+
+fn select_by_option(opt: &ItemReadOption) -> SelectStatement {
+    let mut query = select_items();
+
+    if !opt.include_hidden {
+        query.and_where(is_visible());
+    }
 
     if let Some(ids) = &opt.ids {
-        query.and_where(Expr::col(Items::Id).is_in(ids.clone()));
+        query.and_where(Expr::col((Items::Table, Items::Id)).is_in(ids.clone()));
     }
 
     if let Some(feed) = &opt.feed {
@@ -239,19 +408,212 @@ pub fn read_all(db: &Connection, opt: &ItemReadOption) -> Result<Vec<Item>> {
         });
     }
 
+    query
+}
+
+pub fn read(db: &Connection, id: i32) -> Result<Option<Item>> {
+    let opt = ItemReadOption {
+        ids: Some(vec![id]),
+        limit: Some(1),
+        ..Default::default()
+    };
+
+    Ok(read_all(db, &opt)?.pop())
+}
+
+/// Reads the items with the given ids in one query, in the order the ids are given, leaving out
+/// those that do not exist and repeated ids.
+pub fn read_by_ids(db: &Connection, ids: &[i32]) -> Result<Vec<Item>> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let opt = ItemReadOption {
+        ids: Some(ids.to_vec()),
+        ..Default::default()
+    };
+    let mut found = read_all(db, &opt)?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect::<HashMap<_, _>>();
+
+    Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+}
+
+/// Reads the items of `feed`, or of every feed when it is `None`, published from `since` inclusive
+/// until `until` exclusive, newest first. Consecutive ranges therefore never share an item.
+pub fn read_items_between(
+    db: &Connection,
+    feed: Option<i32>,
+    since: DateTime<FixedOffset>,
+    until: DateTime<FixedOffset>,
+) -> Result<Vec<Item>> {
+    let mut query = select_items();
+    query
+        .and_where(is_visible())
+        .and_where(Expr::col((Items::Table, Items::PublishedAt)).is_not_null())
+        .and_where(Expr::col((Items::Table, Items::PublishedAt)).gte(format_timestamp(&since)))
+        .and_where(Expr::col((Items::Table, Items::PublishedAt)).lt(format_timestamp(&until)))
+        .order_by((Items::Table, Items::PublishedAt), Order::Desc)
+        .order_by((Items::Table, Items::Id), Order::Desc);
+
+    if let Some(feed) = feed {
+        query.and_where(Expr::col((Items::Table, Items::Feed)).eq(feed));
+    }
+
     let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
-    let mut stmt = db.prepare(sql.as_str())?;
-    let rows = stmt.query_map(&*values.as_params(), |x| Ok(Item::from(x)))?;
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Item::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Item>, _>>()?)
+}
+
+/// Reads the visible items of `feed` stored after the item `since_id`, stored at `since`, in the
+/// order they were stored. Items stored at the same time are told apart by their ids, so that one
+/// stored by another connection in the same millisecond is not skipped.
+pub fn new_items_since(
+    db: &Connection,
+    feed: i32,
+    since: DateTime<FixedOffset>,
+    since_id: i32,
+) -> Result<Vec<Item>> {
+    let (sql, values) = select_items()
+        .and_where(is_visible())
+        .and_where(Expr::col((Items::Table, Items::Feed)).eq(feed))
+        .and_where(Expr::cust_with_values(
+            "(items.created_at, items.id) > (?, ?)",
+            [Value::from(format_timestamp(&since)), since_id.into()],
+        ))
+        .order_by((Items::Table, Items::CreatedAt), Order::Asc)
+        .order_by((Items::Table, Items::Id), Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Item::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Item>, _>>()?)
+}
+
+/// Reads a page of the items of every feed, newest first, optionally only those unread.
+pub fn read_timeline(
+    db: &Connection,
+    offset: u64,
+    limit: u64,
+    unread_only: bool,
+) -> Result<Vec<TimelineItem>> {
+    let (sql, values) = select_timeline(unread_only)
+        .limit(limit)
+        .offset(offset)
+        .build_rusqlite(SqliteQueryBuilder);
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| TimelineItem::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<TimelineItem>, _>>()?)
+}
+
+/// Reads the page of the timeline that follows the item `cursor` points at, or the first page
+/// without one, and returns it with the cursor of the page after it, if there may be one. Unlike
+/// `read_timeline`, pages stay put when items are stored while scrolling: an item newer than the
+/// cursor never shifts the pages after it.
+pub fn read_timeline_after(
+    db: &Connection,
+    cursor: Option<&TimelineCursor>,
+    limit: u64,
+    unread_only: bool,
+) -> Result<(Vec<TimelineItem>, Option<TimelineCursor>)> {
+    let mut query = select_timeline(unread_only);
+    if let Some(cursor) = cursor {
+        query.and_where(Expr::cust_with_values(
+            "(items.published_at, items.id) < (?, ?)",
+            [
+                Value::from(format_timestamp(&cursor.published_at)),
+                cursor.id.into(),
+            ],
+        ));
+    }
+
+    let (sql, values) = query.limit(limit).build_rusqlite(SqliteQueryBuilder);
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| TimelineItem::try_from(x))?;
+    let page = rows.collect::<std::result::Result<Vec<TimelineItem>, _>>()?;
+
+    let next = match page.len() as u64 == limit {
+        true => page.last().map(|x| TimelineCursor {
+            published_at: x.item.published_at,
+            id: x.item.id,
+        }),
+        false => None,
+    };
+    Ok((page, next))
+}
+
+fn select_timeline(unread_only: bool) -> SelectStatement {
+    let mut query = select_items();
+    query
+        .and_where(is_visible())
+        .expr_as(
+            Expr::col((Feeds::Table, Feeds::Favicon)),
+            Alias::new("feed_favicon"),
+        )
+        .expr_as(
+            Expr::col((Feeds::Table, Feeds::FaviconContentType)),
+            Alias::new("feed_favicon_content_type"),
+        )
+        .order_by((Items::Table, Items::PublishedAt), Order::Desc)
+        .order_by((Items::Table, Items::Id), Order::Desc);
+
+    if unread_only {
+        query
+            .and_where(Expr::col((Items::Table, Items::Status)).eq(ItemStatus::Unread.to_string()));
+    }
+
+    query
+}
+
+pub fn search(db: &Connection, query: &str) -> Result<Vec<Item>> {
+    let Some(query) = fts_query(query) else {
+        return Ok(vec![]);
+    };
+
+    let (sql, values) = select_items()
+        .inner_join(
+            ItemsFts::Table,
+            Expr::col((ItemsFts::Table, ItemsFts::Rowid)).equals((Items::Table, Items::Id)),
+        )
+        .and_where(Expr::cust_with_values("items_fts MATCH ?", [query]))
+        .and_where(is_visible())
+        .order_by((ItemsFts::Table, ItemsFts::Rank), Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
 
-    Ok(rows.map(std::result::Result::unwrap).collect::<Vec<Item>>())
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Item::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Item>, _>>()?)
 }
 
 pub fn count_all(db: &Connection, opt: &ItemReadOption) -> Result<i64> {
+    count(db, count_query(opt))
+}
+
+fn count_query(opt: &ItemReadOption) -> SelectStatement {
     let mut query = Query::select()
         .from(Items::Table)
         .expr(Func::count(Expr::col(Items::Id)))
+        .and_where(
+            Expr::col(Items::Feed).in_subquery(
+                Query::select()
+                    .column(Feeds::Id)
+                    .from(Feeds::Table)
+                    .and_where(Expr::col(Feeds::DeletedAt).is_null())
+                    .to_owned(),
+            ),
+        )
         .clone();
 
+    if !opt.include_hidden {
+        query.and_where(is_visible());
+    }
+
     if let Some(feed) = &opt.feed {
         query.and_where(Expr::col(Items::Feed).eq(*feed));
     }
@@ -264,8 +626,12 @@ pub fn count_all(db: &Connection, opt: &ItemReadOption) -> Result<i64> {
         query.and_where(Expr::col(Items::IsSaved).eq(*is_saved));
     }
 
+    query
+}
+
+fn count(db: &Connection, query: SelectStatement) -> Result<i64> {
     let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
-    let mut stmt = db.prepare(sql.as_str())?;
+    let mut stmt = db.prepare_cached(sql.as_str())?;
     let mut rows = stmt.query(&*values.as_params())?;
 
     Ok(if let Some(row) = rows.next()? {
@@ -295,6 +661,68 @@ pub fn update(db: &Connection, arg: &ItemToUpdate) -> Result<usize> {
     Ok(db.execute(sql.as_str(), &*values.as_params())?)
 }
 
+/// Sets whether the item is read, saved and hidden, and returns 0 when it already was as given.
+pub fn update_state(
+    db: &Connection,
+    id: i32,
+    status: &ItemStatus,
+    is_saved: bool,
+    is_hidden: bool,
+) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Items::Table)
+        .values([
+            (Items::Status, status.to_string().into()),
+            (Items::IsSaved, is_saved.into()),
+            (Items::IsHidden, is_hidden.into()),
+        ])
+        .and_where(Expr::col(Items::Id).eq(id))
+        .and_where(
+            Expr::col(Items::Status)
+                .ne(status.to_string())
+                .or(Expr::col(Items::IsSaved).ne(is_saved))
+                .or(Expr::col(Items::IsHidden).ne(is_hidden)),
+        )
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Replaces the item's content with `content`, which is HTML.
+pub fn update_content(db: &Connection, id: i32, content: &str) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Items::Table)
+        .values([
+            (Items::Description, content.into()),
+            (Items::ContentIsHtml, true.into()),
+        ])
+        .and_where(Expr::col(Items::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Replaces the item's title and content with those of `arg`, as fetched again, leaving whether it
+/// was read or saved alone. Fails with `NotFound` if there is no item with the id.
+pub fn update_fetched(db: &Connection, id: i32, arg: &ItemToCreate) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Items::Table)
+        .values([
+            (Items::Title, arg.title.clone().into()),
+            (Items::Description, arg.description.clone().into()),
+            (Items::Summary, arg.summary.clone().into()),
+            (Items::ContentIsHtml, arg.content_is_html.into()),
+            (Items::ContentHash, arg.content_hash().into()),
+        ])
+        .and_where(Expr::col(Items::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    match db.execute(sql.as_str(), &*values.as_params())? {
+        0 => Err(Error::NotFound(id)),
+        x => Ok(x),
+    }
+}
+
 pub fn update_all(db: &Connection, arg: &ItemToUpdateAll) -> Result<usize> {
     let mut vals = vec![];
 
@@ -329,3 +757,238 @@ pub fn update_all(db: &Connection, arg: &ItemToUpdateAll) -> Result<usize> {
     let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
     Ok(db.execute(sql.as_str(), &*values.as_params())?)
 }
+
+pub fn mark_read(db: &Connection, id: i32) -> Result<usize> {
+    set_status(db, Expr::col(Items::Id).eq(id), &ItemStatus::Read)
+}
+
+pub fn mark_unread(db: &Connection, id: i32) -> Result<usize> {
+    set_status(db, Expr::col(Items::Id).eq(id), &ItemStatus::Unread)
+}
+
+pub fn mark_feed_read(db: &Connection, feed: i32) -> Result<usize> {
+    set_status(db, Expr::col(Items::Feed).eq(feed), &ItemStatus::Read)
+}
+
+pub fn mark_read_bulk(db: &Connection, ids: &[i32]) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    set_status(
+        db,
+        Expr::col(Items::Id).is_in(ids.iter().copied()),
+        &ItemStatus::Read,
+    )
+}
+
+/// Marks every unread item as read and returns how many there were.
+pub fn mark_all_read(db: &Connection) -> Result<usize> {
+    set_status(
+        db,
+        Expr::col(Items::Status).eq(ItemStatus::Unread.to_string()),
+        &ItemStatus::Read,
+    )
+}
+
+/// Counts the unread items of `feed`, or of every feed that is not muted when it is `None`.
+pub fn unread_count(db: &Connection, feed: Option<i32>) -> Result<i64> {
+    let mut query = count_query(&ItemReadOption {
+        feed,
+        status: Some(ItemStatus::Unread),
+        ..Default::default()
+    });
+
+    if feed.is_none() {
+        query.and_where(
+            Expr::col(Items::Feed).not_in_subquery(
+                Query::select()
+                    .column(Feeds::Id)
+                    .from(Feeds::Table)
+                    .and_where(Expr::col(Feeds::IsMuted).eq(true))
+                    .to_owned(),
+            ),
+        );
+    }
+
+    count(db, query)
+}
+
+/// Flips whether the item is saved for later and returns the new state.
+pub fn toggle_star(db: &Connection, id: i32) -> Result<bool> {
+    let (sql, values) = Query::update()
+        .table(Items::Table)
+        .value(Items::IsSaved, Expr::cust("1 - is_saved"))
+        .and_where(Expr::col(Items::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    if db.execute(sql.as_str(), &*values.as_params())? == 0 {
+        return Err(Error::NotFound(id));
+    }
+
+    let (sql, values) = Query::select()
+        .column(Items::IsSaved)
+        .from(Items::Table)
+        .and_where(Expr::col(Items::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.query_row(sql.as_str(), &*values.as_params(), |x| x.get(0))?)
+}
+
+/// Reads the saved items of every feed, most recently published first.
+pub fn read_starred(db: &Connection) -> Result<Vec<Item>> {
+    read_all(
+        db,
+        &ItemReadOption {
+            is_saved: Some(true),
+            order_by: Some(ItemOrder::PublishedDateDesc),
+            ..Default::default()
+        },
+    )
+}
+
+/// Deletes the items of `feed` beyond its `keep_latest` most recently published ones, sparing those
+/// that are unread or saved, and returns how many were deleted. `keep_latest` should stay above the
+/// number of items the feed serves at once, or pruned items come back on the next fetch.
+pub fn prune_items(db: &Connection, feed: i32, keep_latest: usize) -> Result<usize> {
+    prune(db, Some(feed), keep_latest)
+}
+
+/// Prunes the items of every feed as `prune_items` does.
+pub fn prune_all(db: &Connection, keep_latest: usize) -> Result<usize> {
+    prune(db, None, keep_latest)
+}
+
+/// Moves the items of feed `from` to feed `to` and returns how many were moved. An item that `to`
+/// already has, by guid or else by link, is deleted instead, after marking the copy in `to` read or
+/// saved if it was.
+pub(super) fn move_all(db: &Connection, from: i32, to: i32) -> Result<usize> {
+    let (sql, values) = Query::select()
+        .columns([
+            Items::Id,
+            Items::Guid,
+            Items::Link,
+            Items::Status,
+            Items::IsSaved,
+        ])
+        .from(Items::Table)
+        .and_where(Expr::col(Items::Feed).eq(from))
+        .order_by(Items::Id, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt
+        .query_map(&*values.as_params(), |x| {
+            Ok((
+                x.get::<_, i32>(0)?,
+                x.get::<_, Option<String>>(1)?,
+                x.get::<_, String>(2)?,
+                x.get::<_, ItemStatus>(3)?,
+                x.get::<_, bool>(4)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut moved = 0;
+    for (id, guid, link, status, is_saved) in rows {
+        let Some(existing) = find_id(db, to, guid.as_deref(), &link)? else {
+            let (sql, values) = Query::update()
+                .table(Items::Table)
+                .values([(Items::Feed, to.into())])
+                .and_where(Expr::col(Items::Id).eq(id))
+                .build_rusqlite(SqliteQueryBuilder);
+            moved += db.execute(sql.as_str(), &*values.as_params())?;
+            continue;
+        };
+
+        let mut vals = vec![];
+        if matches!(status, ItemStatus::Read) {
+            vals.push((Items::Status, ItemStatus::Read.to_string().into()));
+        }
+        if is_saved {
+            vals.push((Items::IsSaved, true.into()));
+        }
+        if !vals.is_empty() {
+            let (sql, values) = Query::update()
+                .table(Items::Table)
+                .values(vals)
+                .and_where(Expr::col(Items::Id).eq(existing))
+                .build_rusqlite(SqliteQueryBuilder);
+            db.execute(sql.as_str(), &*values.as_params())?;
+        }
+        delete(db, id)?;
+    }
+
+    Ok(moved)
+}
+
+pub fn delete(db: &Connection, id: i32) -> Result<usize> {
+    let (sql, values) = Query::delete()
+        .from_table(Items::Table)
+        .and_where(Expr::col(Items::Id).eq(id))
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+fn prune(db: &Connection, feed: Option<i32>, keep_latest: usize) -> Result<usize> {
+    Ok(db.execute(
+        "DELETE FROM items WHERE id IN (
+            SELECT id FROM (
+                SELECT id, status, is_saved, ROW_NUMBER() OVER (
+                    PARTITION BY feed ORDER BY published_at DESC, id DESC
+                ) AS position
+                FROM items
+                WHERE ?1 IS NULL OR feed = ?1
+            )
+            WHERE position > ?2 AND status = 'read' AND is_saved = 0
+        )",
+        rusqlite::params![feed, keep_latest],
+    )?)
+}
+
+fn set_status(db: &Connection, filter: SimpleExpr, status: &ItemStatus) -> Result<usize> {
+    let (sql, values) = Query::update()
+        .table(Items::Table)
+        .values([(Items::Status, status.to_string().into())])
+        .and_where(filter)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+fn is_visible() -> SimpleExpr {
+    Expr::col((Items::Table, Items::IsHidden)).eq(false)
+}
+
+fn select_items() -> SelectStatement {
+    Query::select()
+        .columns([
+            (Items::Table, Items::Id),
+            (Items::Table, Items::Fingerprint),
+            (Items::Table, Items::Guid),
+            (Items::Table, Items::Author),
+            (Items::Table, Items::Title),
+            (Items::Table, Items::Description),
+            (Items::Table, Items::ContentIsHtml),
+            (Items::Table, Items::Summary),
+            (Items::Table, Items::Link),
+            (Items::Table, Items::Status),
+            (Items::Table, Items::IsSaved),
+            (Items::Table, Items::IsHidden),
+            (Items::Table, Items::PublishedAt),
+            (Items::Table, Items::CreatedAt),
+        ])
+        .expr_as(Expr::col((Feeds::Table, Feeds::Id)), Alias::new("feed_id"))
+        .expr_as(feeds::display_title(), Alias::new("feed_title"))
+        .expr_as(
+            Expr::col((Feeds::Table, Feeds::Link)),
+            Alias::new("feed_link"),
+        )
+        .from(Items::Table)
+        .inner_join(
+            Feeds::Table,
+            Expr::col((Items::Table, Items::Feed)).equals((Feeds::Table, Feeds::Id)),
+        )
+        .and_where(Expr::col((Feeds::Table, Feeds::DeletedAt)).is_null())
+        .to_owned()
+}
@@ -0,0 +1,711 @@
+//! Schema migrations, applied in order and recorded in SQLite's `user_version` pragma so that each
+//! runs at most once per database.
+//!
+//! Migrations are append-only: once released, a migration must never be edited or reordered. Every
+//! migration also tolerates running against a schema that already has its change, because databases
+//! created before this runner existed start at version 0.
+
+use chrono::Utc;
+use rusqlite::Connection;
+use sea_query::{
+    Alias, ColumnDef, Expr, ForeignKey, ForeignKeyAction, Index, SqliteQueryBuilder, Table,
+};
+
+use crate::error::Result;
+use crate::util::sort_key;
+
+use super::database::{format_timestamp, FeedTags, Feeds, FilterRules, Items, Settings, Tags};
+use super::items;
+
+pub type Migration = fn(&Connection) -> Result<()>;
+
+pub const MIGRATIONS: &[Migration] = &[
+    create_tables,
+    create_search_indexes,
+    add_feeds_fetch_interval_minutes,
+    add_items_guid,
+    add_feeds_deleted_at,
+    create_tags,
+    create_lookup_indexes,
+    add_feeds_validators,
+    normalize_timestamps,
+    add_feeds_html_url,
+    add_feeds_favicon,
+    add_feeds_health,
+    add_feeds_position,
+    add_items_summary,
+    create_timeline_index,
+    add_feeds_content_type,
+    add_items_content_hash,
+    add_feeds_user_title,
+    add_feeds_is_muted,
+    add_feeds_title_sort_key,
+    add_feeds_headers,
+    add_items_content_is_html,
+    create_filter_rules,
+    add_feeds_created_at,
+    add_items_created_at,
+    add_feeds_notify_on_new,
+    add_feeds_max_items_per_fetch,
+    add_items_original_published_at,
+    add_feeds_websub,
+    normalize_feed_statuses,
+    add_feeds_retry_after,
+    add_feeds_last_read_item_id,
+    clamp_feeds_checked_at,
+    add_feeds_last_notified_item_id,
+];
+
+/// Applies every migration newer than the database's `user_version`, each in its own transaction
+/// together with the version bump.
+pub fn run(db: &Connection) -> Result<()> {
+    let applied = version(db)?;
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = db.unchecked_transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+pub fn version(db: &Connection) -> Result<usize> {
+    Ok(db.pragma_query_value(None, "user_version", |x| x.get(0))?)
+}
+
+fn create_tables(db: &Connection) -> Result<()> {
+    let create_table_feeds = Table::create()
+        .table(Feeds::Table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(Feeds::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Feeds::Title).text().not_null())
+        .col(ColumnDef::new(Feeds::Link).text().not_null())
+        .col(
+            ColumnDef::new(Feeds::Status)
+                .text()
+                .check(Expr::col(Feeds::Status).is_in(["subscribed", "unsubscribed"]))
+                .not_null()
+                .default("subscribed"),
+        )
+        .col(ColumnDef::new(Feeds::CheckedAt).date_time().not_null())
+        .index(
+            Index::create()
+                .unique()
+                .name("uk_feeds_title_link")
+                .col(Feeds::Title)
+                .col(Feeds::Link),
+        )
+        .build(SqliteQueryBuilder);
+
+    let create_table_items = Table::create()
+        .table(Items::Table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(Items::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Items::Fingerprint)
+                .text()
+                .not_null()
+                .unique_key(),
+        )
+        .col(ColumnDef::new(Items::Author).text())
+        .col(ColumnDef::new(Items::Title).text().not_null())
+        .col(ColumnDef::new(Items::Description).text().not_null())
+        .col(ColumnDef::new(Items::Link).text().not_null())
+        .col(
+            ColumnDef::new(Items::Status)
+                .text()
+                .check(Expr::col(Items::Status).is_in(["unread", "read"]))
+                .not_null()
+                .default("unread"),
+        )
+        .col(
+            ColumnDef::new(Items::IsSaved)
+                .integer()
+                .check(Expr::col(Items::IsSaved).is_in([0, 1]))
+                .not_null()
+                .default(0),
+        )
+        .col(ColumnDef::new(Items::PublishedAt).date_time().not_null())
+        .col(ColumnDef::new(Items::Feed).integer().not_null())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_items_feeds")
+                .from(Items::Table, Items::Feed)
+                .to(Feeds::Table, Feeds::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        )
+        .build(SqliteQueryBuilder);
+
+    let create_table_settings = Table::create()
+        .table(Settings::Table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(Settings::Key)
+                .text()
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Settings::Value).text().not_null())
+        .build(SqliteQueryBuilder);
+
+    db.execute_batch(
+        &[
+            create_table_feeds,
+            create_table_items,
+            create_table_settings,
+        ]
+        .join(";"),
+    )?;
+
+    Ok(())
+}
+
+fn create_search_indexes(db: &Connection) -> Result<()> {
+    create_search_index(db, "feeds_fts", "feeds", &["title"])?;
+    create_search_index(db, "items_fts", "items", &["title", "description"])
+}
+
+fn add_feeds_fetch_interval_minutes(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::FetchIntervalMinutes)
+            .integer()
+            .not_null()
+            .default(0),
+    )
+}
+
+fn add_items_guid(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "items", ColumnDef::new(Items::Guid).text())
+}
+
+fn add_feeds_deleted_at(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::DeletedAt).date_time())
+}
+
+fn create_tags(db: &Connection) -> Result<()> {
+    let create_table_tags = Table::create()
+        .table(Tags::Table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(Tags::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Tags::Name)
+                .text()
+                .not_null()
+                .extra("COLLATE NOCASE".to_string())
+                .unique_key(),
+        )
+        .build(SqliteQueryBuilder);
+
+    let create_table_feed_tags = Table::create()
+        .table(FeedTags::Table)
+        .if_not_exists()
+        .col(ColumnDef::new(FeedTags::Feed).integer().not_null())
+        .col(ColumnDef::new(FeedTags::Tag).integer().not_null())
+        .primary_key(Index::create().col(FeedTags::Feed).col(FeedTags::Tag))
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_feed_tags_feeds")
+                .from(FeedTags::Table, FeedTags::Feed)
+                .to(Feeds::Table, Feeds::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_feed_tags_tags")
+                .from(FeedTags::Table, FeedTags::Tag)
+                .to(Tags::Table, Tags::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        )
+        .build(SqliteQueryBuilder);
+
+    db.execute_batch(&[create_table_tags, create_table_feed_tags].join(";"))?;
+
+    Ok(())
+}
+
+/// Indexes feeds by link and check time, and items by feed and publication time. Feeds sharing a
+/// link, which older versions allowed, are first merged into the oldest of them so that the link
+/// index can be unique.
+fn create_lookup_indexes(db: &Connection) -> Result<()> {
+    db.execute_batch(
+        "UPDATE items SET feed = (
+            SELECT MIN(b.id) FROM feeds a JOIN feeds b ON b.link = a.link WHERE a.id = items.feed
+        ) WHERE feed IN (SELECT id FROM feeds);
+        INSERT OR IGNORE INTO feed_tags (feed, tag)
+            SELECT (SELECT MIN(b.id) FROM feeds a JOIN feeds b ON b.link = a.link WHERE a.id = feed),
+                tag
+            FROM feed_tags;
+        DELETE FROM feed_tags WHERE feed NOT IN (SELECT MIN(id) FROM feeds GROUP BY link);
+        DELETE FROM feeds WHERE id NOT IN (SELECT MIN(id) FROM feeds GROUP BY link);",
+    )?;
+
+    let indexes = [
+        Index::create()
+            .if_not_exists()
+            .unique()
+            .name("uk_feeds_link")
+            .table(Feeds::Table)
+            .col(Feeds::Link)
+            .build(SqliteQueryBuilder),
+        Index::create()
+            .if_not_exists()
+            .name("idx_feeds_checked_at")
+            .table(Feeds::Table)
+            .col(Feeds::CheckedAt)
+            .build(SqliteQueryBuilder),
+        Index::create()
+            .if_not_exists()
+            .name("idx_items_feed_published_at")
+            .table(Items::Table)
+            .col(Items::Feed)
+            .col(Items::PublishedAt)
+            .build(SqliteQueryBuilder),
+    ];
+    db.execute_batch(&indexes.join(";"))?;
+
+    Ok(())
+}
+
+fn add_feeds_validators(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::Etag).text())?;
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::LastModified).text())
+}
+
+/// Rewrites every stored timestamp as RFC 3339 in UTC, the shape `format_timestamp` produces, so
+/// that timestamps stored with different offsets sort correctly.
+fn normalize_timestamps(db: &Connection) -> Result<()> {
+    for (table, column) in [
+        ("feeds", "checked_at"),
+        ("feeds", "deleted_at"),
+        ("items", "published_at"),
+    ] {
+        let normalized = format!("strftime('%Y-%m-%dT%H:%M:%fZ', {column})");
+        db.execute(
+            &format!("UPDATE {table} SET {column} = {normalized} WHERE {normalized} IS NOT NULL"),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn add_feeds_html_url(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::HtmlUrl).text())
+}
+
+fn add_feeds_favicon(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::Favicon).binary())?;
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::FaviconContentType).text(),
+    )
+}
+
+fn add_feeds_health(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::LastError).text())?;
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::ConsecutiveFailures)
+            .integer()
+            .not_null()
+            .default(0),
+    )
+}
+
+/// Feeds start out in the order they were added.
+fn add_feeds_position(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::Position)
+            .integer()
+            .not_null()
+            .default(0),
+    )?;
+    db.execute_batch(
+        "UPDATE feeds SET position = (SELECT COUNT(*) FROM feeds f WHERE f.id < feeds.id);",
+    )?;
+
+    Ok(())
+}
+
+fn add_items_summary(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "items", ColumnDef::new(Items::Summary).text())
+}
+
+/// Lets the timeline walk items newest first across every feed without sorting them.
+fn create_timeline_index(db: &Connection) -> Result<()> {
+    db.execute_batch(
+        &Index::create()
+            .if_not_exists()
+            .name("idx_items_published_at")
+            .table(Items::Table)
+            .col(Items::PublishedAt)
+            .build(SqliteQueryBuilder),
+    )?;
+
+    Ok(())
+}
+
+fn add_feeds_content_type(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::ContentType).text())
+}
+
+/// Lets an item that is republished under a new guid be recognized by its content.
+fn add_items_content_hash(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "items", ColumnDef::new(Items::ContentHash).text())?;
+    db.execute_batch(
+        &Index::create()
+            .if_not_exists()
+            .name("idx_items_feed_content_hash")
+            .table(Items::Table)
+            .col(Items::Feed)
+            .col(Items::ContentHash)
+            .build(SqliteQueryBuilder),
+    )?;
+
+    Ok(())
+}
+
+/// Feeds are searched by the title they are shown with, so the search index is rebuilt as a
+/// contentless table fed with the user's title when there is one.
+fn add_feeds_user_title(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::UserTitle).text())?;
+    db.execute_batch(
+        "DROP TRIGGER IF EXISTS feeds_fts_insert;
+        DROP TRIGGER IF EXISTS feeds_fts_delete;
+        DROP TRIGGER IF EXISTS feeds_fts_update;
+        DROP TABLE IF EXISTS feeds_fts;
+        CREATE VIRTUAL TABLE feeds_fts USING fts5(title, content='');
+        CREATE TRIGGER feeds_fts_insert AFTER INSERT ON feeds BEGIN
+            INSERT INTO feeds_fts(rowid, title) VALUES (new.id, COALESCE(new.user_title, new.title));
+        END;
+        CREATE TRIGGER feeds_fts_delete AFTER DELETE ON feeds BEGIN
+            INSERT INTO feeds_fts(feeds_fts, rowid, title)
+                VALUES ('delete', old.id, COALESCE(old.user_title, old.title));
+        END;
+        CREATE TRIGGER feeds_fts_update AFTER UPDATE OF title, user_title ON feeds BEGIN
+            INSERT INTO feeds_fts(feeds_fts, rowid, title)
+                VALUES ('delete', old.id, COALESCE(old.user_title, old.title));
+            INSERT INTO feeds_fts(rowid, title) VALUES (new.id, COALESCE(new.user_title, new.title));
+        END;
+        INSERT INTO feeds_fts(rowid, title) SELECT id, COALESCE(user_title, title) FROM feeds;",
+    )?;
+
+    Ok(())
+}
+
+fn add_feeds_is_muted(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::IsMuted)
+            .boolean()
+            .not_null()
+            .default(false),
+    )
+}
+
+fn add_feeds_title_sort_key(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::TitleSortKey)
+            .text()
+            .not_null()
+            .default(""),
+    )?;
+    db.execute_batch(
+        &Index::create()
+            .if_not_exists()
+            .name("idx_feeds_title_sort_key")
+            .table(Feeds::Table)
+            .col(Feeds::TitleSortKey)
+            .build(SqliteQueryBuilder),
+    )?;
+
+    let titles = db
+        .prepare("SELECT id, COALESCE(user_title, title) FROM feeds")?
+        .query_map([], |x| Ok((x.get::<_, i32>(0)?, x.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut stmt = db.prepare("UPDATE feeds SET title_sort_key = ? WHERE id = ?")?;
+    for (id, title) in titles {
+        stmt.execute(rusqlite::params![sort_key(&title), id])?;
+    }
+
+    Ok(())
+}
+
+fn add_feeds_headers(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::Headers).text())
+}
+
+/// Items stored before this were all rendered as HTML, so they default to it.
+fn add_items_content_is_html(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "items",
+        ColumnDef::new(Items::ContentIsHtml)
+            .boolean()
+            .not_null()
+            .default(true),
+    )
+}
+
+fn create_filter_rules(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "items",
+        ColumnDef::new(Items::IsHidden)
+            .boolean()
+            .not_null()
+            .default(false),
+    )?;
+
+    let create_table_filter_rules = Table::create()
+        .table(FilterRules::Table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(FilterRules::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(FilterRules::Pattern).text().not_null())
+        .col(ColumnDef::new(FilterRules::Action).text().not_null())
+        .col(ColumnDef::new(FilterRules::Feed).integer())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_filter_rules_feeds")
+                .from(FilterRules::Table, FilterRules::Feed)
+                .to(Feeds::Table, Feeds::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        )
+        .build(SqliteQueryBuilder);
+    db.execute_batch(&create_table_filter_rules)?;
+
+    Ok(())
+}
+
+/// When existing feeds were added is not known, so they count as added and last changed now.
+fn add_feeds_created_at(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::CreatedAt).date_time())?;
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::UpdatedAt).date_time())?;
+
+    let now = format_timestamp(&Utc::now());
+    db.execute(
+        "UPDATE feeds SET created_at = ?1 WHERE created_at IS NULL",
+        [&now],
+    )?;
+    db.execute(
+        "UPDATE feeds SET updated_at = created_at WHERE updated_at IS NULL",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// When existing items were stored is not known either, so they count as stored when published.
+fn add_items_created_at(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "items", ColumnDef::new(Items::CreatedAt).date_time())?;
+
+    db.execute(
+        "UPDATE items SET created_at = COALESCE(published_at, ?1) WHERE created_at IS NULL",
+        [format_timestamp(&Utc::now())],
+    )?;
+
+    Ok(())
+}
+
+/// Every feed notified of new items before this could be chosen, so they all keep doing so.
+fn add_feeds_notify_on_new(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::NotifyOnNew)
+            .boolean()
+            .not_null()
+            .default(true),
+    )?;
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::LastNotifiedAt).date_time(),
+    )
+}
+
+fn add_feeds_max_items_per_fetch(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::MaxItemsPerFetch).integer(),
+    )
+}
+
+/// Items already stored with a date in the future are clamped the way new ones are.
+fn add_items_original_published_at(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "items",
+        ColumnDef::new(Items::OriginalPublishedAt).date_time(),
+    )?;
+
+    let now = Utc::now();
+    db.execute(
+        "UPDATE items SET original_published_at = published_at, published_at = ?1
+        WHERE published_at > ?2",
+        [
+            format_timestamp(&now),
+            format_timestamp(&(now + items::future_tolerance())),
+        ],
+    )?;
+
+    Ok(())
+}
+
+fn add_feeds_websub(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::HubUrl).text())?;
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::TopicUrl).text())
+}
+
+/// `feeds.status` has been checked since the table was created, but a status written past the
+/// check, by a tool or with `ignore_check_constraints`, cannot be read as a `FeedStatus`. Such feeds
+/// are unsubscribed, so that they show up to be dealt with without being fetched.
+fn normalize_feed_statuses(db: &Connection) -> Result<()> {
+    db.execute(
+        "UPDATE feeds SET status = 'unsubscribed' WHERE status NOT IN ('subscribed', 'unsubscribed')",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn add_feeds_retry_after(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::RetryAfter).date_time())
+}
+
+/// Foreign keys are not enforced, so a trigger forgets the read position of a feed when its item is
+/// deleted, however it was: pruned, merged away or deleted by hand.
+fn add_feeds_last_read_item_id(db: &Connection) -> Result<()> {
+    add_column_if_missing(db, "feeds", ColumnDef::new(Feeds::LastReadItemId).integer())?;
+    db.execute_batch(
+        "DROP TRIGGER IF EXISTS feeds_last_read_item_delete;
+        CREATE TRIGGER feeds_last_read_item_delete AFTER DELETE ON items BEGIN
+            UPDATE feeds SET last_read_item_id = NULL WHERE last_read_item_id = old.id;
+        END;",
+    )?;
+
+    Ok(())
+}
+
+/// Feeds already checked in the future, by a wrong clock, are taken as checked now, the way new
+/// checks are clamped, so that they get refreshed again.
+fn clamp_feeds_checked_at(db: &Connection) -> Result<()> {
+    let now = Utc::now();
+    db.execute(
+        "UPDATE feeds SET checked_at = ?1 WHERE checked_at > ?2",
+        [
+            format_timestamp(&now),
+            format_timestamp(&(now + items::future_tolerance())),
+        ],
+    )?;
+
+    Ok(())
+}
+
+fn add_feeds_last_notified_item_id(db: &Connection) -> Result<()> {
+    add_column_if_missing(
+        db,
+        "feeds",
+        ColumnDef::new(Feeds::LastNotifiedItemId).integer(),
+    )
+}
+
+fn add_column_if_missing(db: &Connection, table: &str, column: &mut ColumnDef) -> Result<()> {
+    let name = column.get_column_name();
+    let exists = db
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?"
+        ))?
+        .exists([&name])?;
+    if exists {
+        return Ok(());
+    }
+
+    let sql = Table::alter()
+        .table(Alias::new(table))
+        .add_column(column)
+        .build(SqliteQueryBuilder);
+    db.execute_batch(&sql)?;
+
+    Ok(())
+}
+
+/// Creates an external-content FTS5 table over `columns` of `table`, kept in sync by triggers. The
+/// index is rebuilt from existing rows only when it is created for the first time.
+fn create_search_index(db: &Connection, fts: &str, table: &str, columns: &[&str]) -> Result<()> {
+    let exists = db
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")?
+        .exists([fts])?;
+    if exists {
+        return Ok(());
+    }
+
+    let cols = columns.join(", ");
+    let new_cols = columns
+        .iter()
+        .map(|x| format!("new.{x}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let old_cols = columns
+        .iter()
+        .map(|x| format!("old.{x}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    db.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE {fts} USING fts5({cols}, content='{table}', content_rowid='id');
+        CREATE TRIGGER {fts}_insert AFTER INSERT ON {table} BEGIN
+            INSERT INTO {fts}(rowid, {cols}) VALUES (new.id, {new_cols});
+        END;
+        CREATE TRIGGER {fts}_delete AFTER DELETE ON {table} BEGIN
+            INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.id, {old_cols});
+        END;
+        CREATE TRIGGER {fts}_update AFTER UPDATE OF {cols} ON {table} BEGIN
+            INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.id, {old_cols});
+            INSERT INTO {fts}(rowid, {cols}) VALUES (new.id, {new_cols});
+        END;
+        INSERT INTO {fts}({fts}) VALUES ('rebuild');"
+    ))?;
+
+    Ok(())
+}
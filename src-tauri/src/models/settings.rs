@@ -22,6 +22,9 @@ pub enum SettingKey {
     Theme,
     ItemsOrder,
     Proxy,
+    RefreshPaused,
+    MaxItemsPerFetch,
+    RequestsPerHostPerMinute,
 }
 
 impl Display for SettingKey {
@@ -33,6 +36,9 @@ impl Display for SettingKey {
             Self::Theme => write!(f, "theme"),
             Self::ItemsOrder => write!(f, "items_order"),
             Self::Proxy => write!(f, "proxy"),
+            Self::RefreshPaused => write!(f, "refresh_paused"),
+            Self::MaxItemsPerFetch => write!(f, "max_items_per_fetch"),
+            Self::RequestsPerHostPerMinute => write!(f, "requests_per_host_per_minute"),
         }
     }
 }
@@ -48,6 +54,9 @@ impl FromStr for SettingKey {
             "theme" => Ok(Self::Theme),
             "items_order" => Ok(Self::ItemsOrder),
             "proxy" => Ok(Self::Proxy),
+            "refresh_paused" => Ok(Self::RefreshPaused),
+            "max_items_per_fetch" => Ok(Self::MaxItemsPerFetch),
+            "requests_per_host_per_minute" => Ok(Self::RequestsPerHostPerMinute),
             _ => Err(Error::InvalidEnumKey(
                 x.to_string(),
                 "SettingKey".to_string(),
@@ -83,7 +92,7 @@ pub fn read_all(db: &Connection) -> Result<Vec<Setting>> {
         .from(Settings::Table)
         .build_rusqlite(SqliteQueryBuilder);
 
-    let mut stmt = db.prepare(sql.as_str())?;
+    let mut stmt = db.prepare_cached(sql.as_str())?;
     let rows = stmt.query_map(&*values.as_params(), |x| Ok(Setting::from(x)))?;
 
     Ok(rows
@@ -99,7 +108,7 @@ pub fn read(db: &Connection, key: &SettingKey) -> Result<Setting> {
         .limit(1)
         .build_rusqlite(SqliteQueryBuilder);
 
-    let mut stmt = db.prepare(sql.as_str())?;
+    let mut stmt = db.prepare_cached(sql.as_str())?;
     let mut rows = stmt.query(&*values.as_params())?;
     match rows.next()? {
         None => Err(Error::Unknown),
@@ -130,6 +139,16 @@ pub fn update(db: &Connection, arg: &SettingToUpdate) -> Result<usize> {
                 return Err(Error::Unknown);
             }
         }
+        SettingKey::RefreshPaused => {
+            if arg.value.parse::<bool>().is_err() {
+                return Err(Error::InvalidValue(arg.value.clone()));
+            }
+        }
+        SettingKey::MaxItemsPerFetch | SettingKey::RequestsPerHostPerMinute => {
+            if arg.value.parse::<u32>().is_err() {
+                return Err(Error::InvalidValue(arg.value.clone()));
+            }
+        }
         SettingKey::DbSchemeVersion => return Err(Error::Forbidden),
         _ => {}
     }
@@ -142,3 +161,47 @@ pub fn update(db: &Connection, arg: &SettingToUpdate) -> Result<usize> {
 
     Ok(db.execute(sql.as_str(), &*values.as_params())?)
 }
+
+/// Stops the background refresher from fetching any feed until `resume_all`, without touching the
+/// feeds' subscriptions. Feeds can still be refreshed one by one on request.
+pub fn pause_all(db: &Connection) -> Result<usize> {
+    set_paused(db, true)
+}
+
+pub fn resume_all(db: &Connection) -> Result<usize> {
+    set_paused(db, false)
+}
+
+pub fn is_paused(db: &Connection) -> Result<bool> {
+    Ok(read(db, &SettingKey::RefreshPaused)?
+        .value
+        .parse()
+        .unwrap_or(false))
+}
+
+/// How many of the newest items of a fetch are stored for feeds that do not set their own limit.
+/// 0 stores them all.
+pub fn max_items_per_fetch(db: &Connection) -> Result<u32> {
+    Ok(read(db, &SettingKey::MaxItemsPerFetch)?
+        .value
+        .parse()
+        .unwrap_or(0))
+}
+
+/// How many requests a minute each host may get from fetching. 0 does not limit them.
+pub fn requests_per_host_per_minute(db: &Connection) -> Result<u32> {
+    Ok(read(db, &SettingKey::RequestsPerHostPerMinute)?
+        .value
+        .parse()
+        .unwrap_or(0))
+}
+
+fn set_paused(db: &Connection, paused: bool) -> Result<usize> {
+    update(
+        db,
+        &SettingToUpdate {
+            key: SettingKey::RefreshPaused,
+            value: paused.to_string(),
+        },
+    )
+}
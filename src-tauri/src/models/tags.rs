@@ -0,0 +1,117 @@
+use rusqlite::Connection;
+use sea_query::{Expr, OnConflict, Order, Query, SqliteQueryBuilder};
+use sea_query_rusqlite::RusqliteBinder;
+
+use crate::error::{Error, Result};
+
+use super::database::{FeedTags, Feeds, Tags};
+use super::feeds::{select_feeds, Feed};
+
+/// Tags `feed` with `name`, creating the tag if needed. Tag names are compared case-insensitively,
+/// so tagging with "tech" reuses an existing "Tech".
+pub fn add_tag(db: &Connection, feed: i32, name: &str) -> Result<usize> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::InvalidValue(name.to_string()));
+    }
+
+    let (sql, values) = Query::insert()
+        .into_table(Tags::Table)
+        .columns([Tags::Name])
+        .values_panic([name.into()])
+        .on_conflict(OnConflict::column(Tags::Name).do_nothing().to_owned())
+        .build_rusqlite(SqliteQueryBuilder);
+    db.execute(sql.as_str(), &*values.as_params())?;
+
+    let (sql, values) = Query::insert()
+        .into_table(FeedTags::Table)
+        .columns([FeedTags::Feed, FeedTags::Tag])
+        .select_from(
+            Query::select()
+                .expr(Expr::val(feed))
+                .column(Tags::Id)
+                .from(Tags::Table)
+                .and_where(Expr::col(Tags::Name).eq(name))
+                .to_owned(),
+        )?
+        .on_conflict(
+            OnConflict::columns([FeedTags::Feed, FeedTags::Tag])
+                .do_nothing()
+                .to_owned(),
+        )
+        .build_rusqlite(SqliteQueryBuilder);
+
+    Ok(db.execute(sql.as_str(), &*values.as_params())?)
+}
+
+/// Removes the tag `name` from `feed`. A tag left without any feed is deleted.
+pub fn remove_tag(db: &Connection, feed: i32, name: &str) -> Result<usize> {
+    let (sql, values) = Query::delete()
+        .from_table(FeedTags::Table)
+        .and_where(Expr::col(FeedTags::Feed).eq(feed))
+        .and_where(
+            Expr::col(FeedTags::Tag).in_subquery(
+                Query::select()
+                    .column(Tags::Id)
+                    .from(Tags::Table)
+                    .and_where(Expr::col(Tags::Name).eq(name.trim()))
+                    .to_owned(),
+            ),
+        )
+        .build_rusqlite(SqliteQueryBuilder);
+    let removed = db.execute(sql.as_str(), &*values.as_params())?;
+
+    let (sql, values) = Query::delete()
+        .from_table(Tags::Table)
+        .and_where(
+            Expr::col(Tags::Id).not_in_subquery(
+                Query::select()
+                    .column(FeedTags::Tag)
+                    .from(FeedTags::Table)
+                    .to_owned(),
+            ),
+        )
+        .build_rusqlite(SqliteQueryBuilder);
+    db.execute(sql.as_str(), &*values.as_params())?;
+
+    Ok(removed)
+}
+
+pub fn read_tags(db: &Connection, feed: i32) -> Result<Vec<String>> {
+    let (sql, values) = Query::select()
+        .column((Tags::Table, Tags::Name))
+        .from(Tags::Table)
+        .inner_join(
+            FeedTags::Table,
+            Expr::col((FeedTags::Table, FeedTags::Tag)).equals((Tags::Table, Tags::Id)),
+        )
+        .and_where(Expr::col((FeedTags::Table, FeedTags::Feed)).eq(feed))
+        .order_by((Tags::Table, Tags::Name), Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| x.get(0))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<String>, _>>()?)
+}
+
+pub fn read_feeds_by_tag(db: &Connection, name: &str) -> Result<Vec<Feed>> {
+    let (sql, values) = select_feeds()
+        .inner_join(
+            FeedTags::Table,
+            Expr::col((FeedTags::Table, FeedTags::Feed)).equals((Feeds::Table, Feeds::Id)),
+        )
+        .inner_join(
+            Tags::Table,
+            Expr::col((Tags::Table, Tags::Id)).equals((FeedTags::Table, FeedTags::Tag)),
+        )
+        .and_where(Expr::col((Tags::Table, Tags::Name)).eq(name.trim()))
+        .order_by((Feeds::Table, Feeds::TitleSortKey), Order::Asc)
+        .order_by((Feeds::Table, Feeds::Id), Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    let mut stmt = db.prepare_cached(sql.as_str())?;
+    let rows = stmt.query_map(&*values.as_params(), |x| Feed::try_from(x))?;
+
+    Ok(rows.collect::<std::result::Result<Vec<Feed>, _>>()?)
+}
@@ -0,0 +1,115 @@
+use chrono::Utc;
+use quick_xml::{escape::escape, events::Event, Reader};
+use rusqlite::Connection;
+
+use crate::error::{Error, Result};
+use crate::models::feeds::{self, FeedToCreate, FeedToUpdate};
+
+/// A feed listed in an OPML document, with the link to its site if the document has one.
+pub struct Outline {
+    pub feed: FeedToCreate,
+    pub html_url: Option<String>,
+}
+
+/// Subscribes to every feed listed in an OPML document, all at once or not at all. Outlines nested
+/// in folders are flattened.
+pub fn import_opml(db: &Connection, xml: &str) -> Result<Vec<i32>> {
+    let outlines = parse_opml(xml)?;
+    let tx = db.unchecked_transaction()?;
+
+    let mut ids = Vec::with_capacity(outlines.len());
+    for outline in outlines {
+        let id = feeds::create(&tx, &outline.feed)?.id;
+        if let Some(html_url) = outline.html_url {
+            feeds::update_with(&tx, &FeedToUpdate::for_id(id).html_url(html_url).build())?;
+        }
+        ids.push(id);
+    }
+
+    tx.commit()?;
+    Ok(ids)
+}
+
+pub fn parse_opml(xml: &str) -> Result<Vec<Outline>> {
+    let mut reader = Reader::from_str(xml);
+    let mut feeds = vec![];
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.name().as_ref() == b"outline" => {
+                let mut title = None;
+                let mut text = None;
+                let mut link = None;
+                let mut html_url = None;
+
+                for attr in e.attributes() {
+                    let attr = attr.map_err(|_| Error::OpmlParsingFailure)?;
+                    let value = attr
+                        .decode_and_unescape_value(&reader)
+                        .map_err(|_| Error::OpmlParsingFailure)?
+                        .trim()
+                        .to_string();
+
+                    match attr.key.as_ref() {
+                        b"title" => title = Some(value),
+                        b"text" => text = Some(value),
+                        b"xmlUrl" => link = Some(value),
+                        b"htmlUrl" => html_url = Some(value),
+                        _ => {}
+                    }
+                }
+
+                if let Some(link) = link.filter(|x| !x.is_empty()) {
+                    feeds.push(Outline {
+                        feed: FeedToCreate {
+                            title: title
+                                .filter(|x| !x.is_empty())
+                                .or(text)
+                                .unwrap_or_else(|| link.clone()),
+                            link,
+                        },
+                        html_url: html_url.filter(|x| !x.is_empty()),
+                    });
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => return Err(Error::OpmlParsingFailure),
+            _ => {}
+        }
+    }
+
+    Ok(feeds)
+}
+
+/// Serializes the subscribed feeds into an OPML 2.0 document.
+pub fn export_opml(db: &Connection) -> Result<String> {
+    let mut outlines = String::new();
+    feeds::for_each(db, |x| {
+        let title = escape(x.display_title());
+        let html_url = x
+            .html_url
+            .as_deref()
+            .map(|x| format!(" htmlUrl=\"{}\"", escape(x)))
+            .unwrap_or_default();
+        outlines.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{}\"\
+             {html_url}/>\n",
+            escape(&x.link),
+        ));
+        Ok(())
+    })?;
+
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<opml version=\"2.0\">
+  <head>
+    <title>Collie subscriptions</title>
+    <dateCreated>{}</dateCreated>
+  </head>
+  <body>
+{outlines}  </body>
+</opml>
+",
+        Utc::now().to_rfc2822(),
+    ))
+}
@@ -1,79 +1,472 @@
-use chrono::Utc;
+use chrono::{DateTime, FixedOffset, Utc};
 use rusqlite::Connection;
+use serde::Serialize;
+use std::{cmp::Reverse, sync::Mutex, thread, time::Duration};
 
-use crate::models::feeds::FeedStatus;
-use crate::syndication::RawItem;
+use crate::error::{Error, Result};
+use crate::models::database::DbPool;
+use crate::syndication::{RawItem, Validators};
+use crate::util::normalize_url;
 use crate::{
     models::{
-        feeds::{self, FeedToUpdate},
-        items::{self, ItemStatus, ItemToCreate},
+        feeds::{self, Feed, FeedMetadata, FeedToCreate, FeedToUpdate},
+        filters,
+        items::{self, Item, ItemStatus, ItemToCreate},
+        settings,
+    },
+    syndication::{
+        fetch_article, fetch_feed, fetch_feed_items_if_modified, fetch_icon,
+        request_websub_subscription, sanitize_html,
     },
-    syndication::fetch_feed_items,
 };
 
+/// Sent as each feed of a batch refresh completes.
+#[derive(Serialize, Debug, Clone)]
+pub struct RefreshProgress {
+    pub feed: i32,
+    pub new_items: usize,
+    pub error: Option<String>,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct RefreshReport {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub new_items: usize,
+    pub errors: Vec<(i32, String)>,
+}
+
+/// How a fetch that failed for a reason that may pass, such as a timeout, is tried again.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How many times to fetch in all, counting the first.
+    pub attempts: u32,
+    /// The wait before the first retry, which doubles before each further one.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Subscribes to the feed found at `link`, which may be either the feed itself or a web page that
+/// advertises it. The title and feed link are taken from what was actually fetched.
+pub fn subscribe_by_url(db: &Connection, link: &str, proxy: Option<&str>) -> Result<Feed> {
+    let raw = fetch_feed(&normalize_url(link), proxy)?;
+    let feed = feeds::create(
+        db,
+        &FeedToCreate {
+            title: if raw.title.trim().is_empty() {
+                raw.link.clone()
+            } else {
+                raw.title
+            },
+            link: raw.link,
+        },
+    )?;
+
+    match raw.html_url {
+        Some(html_url) => {
+            set_html_url(db, feed.id, html_url)?;
+            feeds::read_required(db, feed.id)
+        }
+        None => Ok(feed),
+    }
+}
+
+/// Downloads the icon of the feed's website, falling back to the feed's own host, and stores it.
+pub fn fetch_favicon(db: &Connection, id: i32, proxy: Option<&str>) -> Result<Vec<u8>> {
+    let feed = feeds::read_required(db, id)?;
+    let site = feed.html_url.as_deref().unwrap_or(&feed.link);
+    let icon = fetch_icon(site, proxy)?.ok_or(Error::FaviconNotFound(id))?;
+
+    feeds::update_favicon(db, id, &icon.data, icon.content_type.as_deref())?;
+    Ok(icon.data)
+}
+
+/// Returns the full content of the item. For an item whose feed only provided a summary, the
+/// article is downloaded from the item's link and stored in place of the summary first.
+pub fn fetch_full_content(db: &Connection, id: i32, proxy: Option<&str>) -> Result<String> {
+    let item = items::read(db, id)?.ok_or(Error::NotFound(id))?;
+    if !item.is_summary_only() {
+        return Ok(item.description);
+    }
+
+    let content = fetch_article(&item.link, proxy)?
+        .map(|x| sanitize_html(&x, Some(&item.link)))
+        .ok_or_else(|| Error::SyndicationNotFound(item.link.clone()))?;
+    items::update_content(db, id, &content)?;
+
+    Ok(content)
+}
+
+/// Fetches the item again, from its feed if the feed still lists it or else from its own link, and
+/// stores its title and content if they changed, returning whether they did. Whether the item was
+/// read or saved is kept.
+pub fn refresh_item(db: &Connection, id: i32, proxy: Option<&str>) -> Result<bool> {
+    let item = items::read(db, id)?.ok_or(Error::NotFound(id))?;
+    let feed = feeds::read_required(db, item.feed.id)?;
+
+    let fetched =
+        fetch_feed_items_if_modified(&feed.link, proxy, &Validators::default(), &feed.headers.0)?;
+    let raw = fetched
+        .iter()
+        .flat_map(|x| &x.items)
+        .find(|x| match &item.guid {
+            Some(guid) => x.guid.as_ref() == Some(guid),
+            None => x.link.as_deref().map(str::trim) == Some(item.link.as_str()),
+        });
+
+    let refreshed = match raw {
+        Some(raw) => to_item(&feed, raw, item.published_at),
+        None => {
+            let content = fetch_article(&item.link, proxy)?
+                .map(|x| sanitize_html(&x, Some(&item.link)))
+                .ok_or_else(|| Error::SyndicationNotFound(item.link.clone()))?;
+            ItemToCreate {
+                guid: item.guid.clone(),
+                author: item.author.clone(),
+                title: item.title.clone(),
+                description: content,
+                content_is_html: true,
+                summary: item.summary.clone(),
+                link: item.link.clone(),
+                status: item.status,
+                is_hidden: item.is_hidden,
+                published_at: item.published_at,
+                feed: feed.id,
+            }
+        }
+    };
+
+    let changed = refreshed.title != item.title
+        || refreshed.description != item.description
+        || refreshed.summary != item.summary
+        || refreshed.content_is_html != item.content_is_html;
+    if changed {
+        items::update_fetched(db, id, &refreshed)?;
+    }
+
+    Ok(changed)
+}
+
+/// Gathers the items to announce: those stored since the last notification of each feed that asks
+/// for notifications, or since the feed was added if none was sent yet. Each checkpoint is then
+/// moved past what was gathered.
+pub fn collect_notifications(db: &Connection) -> Result<Vec<Item>> {
+    let mut gathered = vec![];
+    for feed in feeds::read_notifying(db)? {
+        let (since, since_id) = match feed.last_notified_at {
+            // Checkpoints older than the item id only mark a time, after which the items are new.
+            Some(at) => (at, feed.last_notified_item_id.unwrap_or(i32::MAX)),
+            // No item is stored before its feed, so all of them are new.
+            None => (feed.created_at, 0),
+        };
+        let items = items::new_items_since(db, feed.id, since, since_id)?;
+        if let Some(last) = items.last() {
+            feeds::update_last_notified_at(db, feed.id, last.created_at, last.id)?;
+        }
+        gathered.extend(items);
+    }
+
+    Ok(gathered)
+}
+
 pub fn create_new_items(db: &Connection, proxy: Option<&str>) -> Vec<ItemToCreate> {
-    let pairs = get_links_to_check(db);
+    let feeds = get_feeds_to_check(db);
 
     let mut inserted = vec![];
-    for (feed, link) in pairs {
-        if let Ok(mut items) = fetch_feed_items(&link, proxy) {
-            items.sort_by_key(|x| x.published_at);
-            inserted.extend(insert_new_items(db, feed, &items));
+    for feed in feeds {
+        if let Ok(items) = fetch_new_items(db, &feed, proxy, &RetryPolicy::default()) {
+            inserted.extend(items);
         };
     }
 
     inserted
 }
 
-fn get_links_to_check(db: &Connection) -> Vec<(i32, String)> {
-    if let Ok(feeds) = feeds::read_all(db) {
-        let current = Utc::now().fixed_offset();
-        let filtered = feeds.iter().filter(|x| x.status == FeedStatus::Subscribed);
-
-        filtered
-            .map(|x| {
-                let _ = feeds::update(
-                    db,
-                    &FeedToUpdate {
-                        id: x.id,
-                        title: None,
-                        link: None,
-                        status: None,
-                        checked_at: Some(current),
-                    },
-                );
-                (x.id, x.link.clone())
-            })
-            .collect()
+/// Fetches a single feed right away, regardless of when it was last checked, and returns how many
+/// new items were stored.
+pub fn refresh_feed(db: &Connection, id: i32, proxy: Option<&str>) -> Result<usize> {
+    refresh_feed_with_retry(db, id, proxy, &RetryPolicy::default())
+}
+
+/// Like `refresh_feed`, but retries transient failures as `retry` says.
+#[tracing::instrument(skip(db, proxy, retry), fields(link), err)]
+pub fn refresh_feed_with_retry(
+    db: &Connection,
+    id: i32,
+    proxy: Option<&str>,
+    retry: &RetryPolicy,
+) -> Result<usize> {
+    let feed = feeds::read_required(db, id)?;
+    tracing::Span::current().record("link", feed.link.as_str());
+    feeds::touch_checked_at(db, feed.id, Utc::now().fixed_offset())?;
+
+    Ok(fetch_new_items(db, &feed, proxy, retry)?.len())
+}
+
+/// Subscribes to pushes of the feed's updates at the WebSub hub it advertised on its last refresh,
+/// for the hub to deliver to `callback`. Each push should then refresh the feed. Fails with
+/// `NoWebSubHub` when the feed advertised no hub.
+pub fn subscribe_websub(
+    db: &Connection,
+    id: i32,
+    callback: &str,
+    proxy: Option<&str>,
+) -> Result<()> {
+    let feed = feeds::read_required(db, id)?;
+    let (Some(hub), Some(topic)) = (&feed.hub_url, &feed.topic_url) else {
+        return Err(Error::NoWebSubHub(id));
+    };
+
+    request_websub_subscription(hub, topic, callback, proxy)
+}
+
+/// Refreshes every subscribed feed, running at most `concurrency` fetches at a time, and calls
+/// `on_progress` as each one completes. A failing feed is recorded in the report and does not stop
+/// the others.
+pub fn refresh_all(
+    pool: &DbPool,
+    concurrency: usize,
+    proxy: Option<&str>,
+    on_progress: impl Fn(RefreshProgress) + Sync,
+) -> Result<RefreshReport> {
+    let ids = feeds::read_all(&*pool.get()?)?
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+
+    let report = Mutex::new(RefreshReport {
+        total: ids.len(),
+        ..Default::default()
+    });
+    let queue = Mutex::new(ids.into_iter());
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1) {
+            scope.spawn(|| loop {
+                let Some(id) = queue.lock().unwrap().next() else {
+                    break;
+                };
+
+                let result = pool
+                    .get()
+                    .map_err(Error::from)
+                    .and_then(|db| refresh_feed(&db, id, proxy));
+
+                let mut report = report.lock().unwrap();
+                match &result {
+                    Ok(new_items) => {
+                        report.succeeded += 1;
+                        report.new_items += new_items;
+                    }
+                    Err(e) => {
+                        report.failed += 1;
+                        report.errors.push((id, e.to_string()));
+                    }
+                }
+                on_progress(RefreshProgress {
+                    feed: id,
+                    new_items: *result.as_ref().unwrap_or(&0),
+                    error: result.err().map(|x| x.to_string()),
+                    completed: report.succeeded + report.failed,
+                    total: report.total,
+                });
+            });
+        }
+    });
+
+    let mut report = report.into_inner().unwrap();
+    report.errors.sort_by_key(|x| x.0);
+
+    Ok(report)
+}
+
+fn get_feeds_to_check(db: &Connection) -> Vec<Feed> {
+    let current = Utc::now().fixed_offset();
+    if let Ok(feeds) = feeds::read_stale(db, current) {
+        for feed in &feeds {
+            if let Err(e) = feeds::touch_checked_at(db, feed.id, current) {
+                tracing::warn!(feed = feed.id, "failed to record the check: {e}");
+            }
+        }
+        feeds
     } else {
         vec![]
     }
 }
 
-fn insert_new_items(db: &Connection, feed: i32, items: &[RawItem]) -> Vec<ItemToCreate> {
+fn set_html_url(db: &Connection, feed: i32, html_url: String) -> Result<usize> {
+    feeds::update(db, &FeedToUpdate::for_id(feed).html_url(html_url).build())
+}
+
+/// Fetches the feed's items, unless the server reports that nothing changed since the last fetch,
+/// and stores those not seen before. A transient failure is retried; whether the fetch finally
+/// failed is recorded on the feed. A server that is rate limiting the feed does not count as a
+/// failure: the feed is put off until the server said to come back instead.
+fn fetch_new_items(
+    db: &Connection,
+    feed: &Feed,
+    proxy: Option<&str>,
+    retry: &RetryPolicy,
+) -> Result<Vec<ItemToCreate>> {
+    let mut result = try_fetch_new_items(db, feed, proxy);
+    let mut delay = retry.base_delay;
+    for _ in 1..retry.attempts {
+        match &result {
+            Err(e) if e.is_transient() => {
+                tracing::warn!(feed = feed.id, link = %feed.link, "retrying in {delay:?}: {e}");
+                thread::sleep(delay);
+                delay *= 2;
+                result = try_fetch_new_items(db, feed, proxy);
+            }
+            _ => break,
+        }
+    }
+
+    if let Err(Error::RateLimited(_, retry_after)) = &result {
+        tracing::warn!(feed = feed.id, link = %feed.link, "rate limited until {retry_after}");
+        feeds::update_retry_after(db, feed.id, Some(*retry_after))?;
+        return result;
+    }
+    if feed.retry_after.is_some() {
+        feeds::update_retry_after(db, feed.id, None)?;
+    }
+    feeds::update_health(
+        db,
+        feed.id,
+        result.as_ref().err().map(|x| x.to_string()).as_deref(),
+    )?;
+
+    result
+}
+
+fn try_fetch_new_items(
+    db: &Connection,
+    feed: &Feed,
+    proxy: Option<&str>,
+) -> Result<Vec<ItemToCreate>> {
+    let validators = Validators {
+        etag: feed.etag.clone(),
+        last_modified: feed.last_modified.clone(),
+    };
+    let Some(mut fetched) =
+        fetch_feed_items_if_modified(&feed.link, proxy, &validators, &feed.headers.0)?
+    else {
+        return Ok(vec![]);
+    };
+
+    feeds::update_validators(
+        db,
+        feed.id,
+        fetched.validators.etag.as_deref(),
+        fetched.validators.last_modified.as_deref(),
+    )?;
+    feeds::update_websub(
+        db,
+        feed.id,
+        fetched.websub.as_ref().map(|x| x.hub.as_str()),
+        fetched.websub.as_ref().map(|x| x.topic.as_str()),
+    )?;
+    let metadata = FeedMetadata {
+        title: &fetched.title,
+        html_url: fetched.html_url.as_deref(),
+        content_type: fetched.content_type.as_deref(),
+    };
+    if let Some(update) = feed.diff(&metadata) {
+        feeds::update(db, &update)?;
+    }
+
+    let limit = match feed.max_items_per_fetch {
+        Some(limit) => limit.max(0) as usize,
+        None => settings::max_items_per_fetch(db).unwrap_or(0) as usize,
+    };
+    // Dates in the future count as now, as they are stored.
+    let now = Utc::now();
+    let published_at = |x: &RawItem| {
+        x.published_at
+            .map(|at| items::clamp_published_at(at, now).0)
+    };
+    if limit > 0 {
+        // Newest first, keeping the feed's order among items published at the same time. Items
+        // without a date count as the oldest.
+        fetched.items.sort_by_key(|x| Reverse(published_at(x)));
+        fetched.items.truncate(limit);
+    }
+    fetched.items.sort_by_key(published_at);
+
+    Ok(insert_new_items(db, feed, &fetched.items))
+}
+
+/// Stores the items not seen before, with their HTML sanitized and its links resolved against the
+/// item's own link, or else the feed's, and the feed's filter rules applied. Items hidden by a rule
+/// are stored but not returned.
+fn insert_new_items(db: &Connection, feed: &Feed, items: &[RawItem]) -> Vec<ItemToCreate> {
     let current = Utc::now().fixed_offset();
+    let rules = filters::read_for_feed(db, feed.id).unwrap_or_default();
 
-    let args = items.iter().map(|x| ItemToCreate {
-        author: x.author.clone().map(|x| x.trim().to_string()),
-        title: x.title.trim().to_string(),
-        link: x.link.clone().unwrap_or("#".to_string()).trim().to_string(),
-        description: x
-            .content
-            .clone()
-            .unwrap_or(String::new())
-            .trim()
-            .to_string(),
-        status: ItemStatus::Unread,
-        published_at: x.published_at.unwrap_or(current),
-        feed,
+    let args = items.iter().map(|x| {
+        let mut item = to_item(feed, x, current);
+        filters::apply(&rules, &mut item);
+
+        item
     });
 
     let mut inserted = vec![];
     for arg in args {
-        if items::create(db, &arg).is_ok() {
+        let exists = items::exists(db, feed.id, arg.guid.as_deref(), &arg.link).unwrap_or(false)
+            || items::content_exists(db, feed.id, &arg.content_hash()).unwrap_or(false);
+        if !exists && items::create(db, &arg).is_ok() && !arg.is_hidden {
             inserted.push(arg);
         }
     }
 
     inserted
 }
+
+/// Turns a fetched item into an unread one of `feed`, published at `published_at` unless it says
+/// when it was.
+fn to_item(feed: &Feed, x: &RawItem, published_at: DateTime<FixedOffset>) -> ItemToCreate {
+    let base = x
+        .link
+        .as_deref()
+        .or(feed.html_url.as_deref())
+        .unwrap_or(&feed.link);
+    let sanitize = |x: &String| sanitize_html(x.trim(), Some(base)).trim().to_string();
+    // Plain text is stored as is, for the renderer to escape.
+    let render = |text: &String| match x.content_is_html {
+        true => sanitize(text),
+        false => text.trim().to_string(),
+    };
+
+    ItemToCreate {
+        guid: x.guid.clone(),
+        author: x.author.clone().map(|x| x.trim().to_string()),
+        title: x.title.trim().to_string(),
+        link: x.link.clone().unwrap_or("#".to_string()).trim().to_string(),
+        description: x
+            .content
+            .as_ref()
+            .or(x.summary.as_ref())
+            .map(render)
+            .unwrap_or_default(),
+        content_is_html: x.content_is_html,
+        summary: x.summary.as_ref().map(|summary| match x.content {
+            Some(_) => sanitize(summary),
+            None => render(summary),
+        }),
+        status: ItemStatus::Unread,
+        is_hidden: false,
+        published_at: x.published_at.unwrap_or(published_at),
+        feed: feed.id,
+    }
+}
@@ -0,0 +1,109 @@
+use reqwest::Url;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// How many requests a host may take back to back before the limit kicks in.
+const BURST: u32 = 4;
+
+/// Spaces out requests to the same host with a token bucket per host, so that refreshing many
+/// feeds of one site does not hammer it. Requests to different hosts do not wait on each other.
+pub struct HostRateLimiter {
+    state: Mutex<State>,
+}
+
+struct State {
+    per_minute: u32,
+    buckets: HashMap<String, Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl HostRateLimiter {
+    /// Allows `per_minute` requests a minute to each host. 0 lifts the limit.
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            state: Mutex::new(State {
+                per_minute,
+                buckets: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn set_limit(&self, per_minute: u32) {
+        let mut state = self.state.lock().unwrap();
+        if state.per_minute != per_minute {
+            state.per_minute = per_minute;
+            state.buckets.clear();
+        }
+    }
+
+    /// Takes a request to `host` made at `now` into account and returns how long it has to wait
+    /// before going out. Requests that have to wait queue up behind each other in the order they
+    /// were reserved.
+    pub fn reserve(&self, host: &str, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        if state.per_minute == 0 {
+            return Duration::ZERO;
+        }
+
+        let rate = f64::from(state.per_minute) / 60.0;
+        let capacity = f64::from(BURST.min(state.per_minute));
+        let bucket = state
+            .buckets
+            .entry(host.to_ascii_lowercase())
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity) - 1.0;
+        bucket.updated = bucket.updated.max(now);
+
+        match bucket.tokens < 0.0 {
+            true => Duration::from_secs_f64(-bucket.tokens / rate),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// Blocks until a request to the host of `link` may go out. Links without a host are not
+    /// limited.
+    pub fn acquire(&self, link: &str) {
+        let Some(host) = Url::parse(link)
+            .ok()
+            .and_then(|x| x.host_str().map(str::to_string))
+        else {
+            return;
+        };
+
+        let wait = self.reserve(&host, Instant::now());
+        if !wait.is_zero() {
+            tracing::debug!(host, ?wait, "waiting for the host's rate limit");
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// The limiter all fetches go through.
+fn limiter() -> &'static HostRateLimiter {
+    static LIMITER: OnceLock<HostRateLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| HostRateLimiter::new(0))
+}
+
+/// Sets how many requests a minute each host gets from now on, as the
+/// `requests_per_host_per_minute` setting says.
+pub fn set_requests_per_host_per_minute(per_minute: u32) {
+    limiter().set_limit(per_minute);
+}
+
+/// Blocks until a request to the host of `link` may go out under the configured limit.
+#[cfg(not(test))]
+pub fn wait_for(link: &str) {
+    limiter().acquire(link);
+}
@@ -1,32 +1,366 @@
 use chrono::{DateTime, FixedOffset, Utc};
-use std::str::FromStr;
+use regex::Regex;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::HashMap, str::FromStr};
 
 use crate::error::{Error, Result};
 
 #[cfg_attr(test, derive(Debug, PartialEq))]
 pub struct RawItem {
+    pub guid: Option<String>,
     pub title: String,
     pub author: Option<String>,
     pub link: Option<String>,
+    /// The full content, when the feed provides more than a summary.
     pub content: Option<String>,
+    pub summary: Option<String>,
+    /// Whether the content, or else the summary, is HTML rather than plain text. RSS is taken to be
+    /// HTML, as it is in practice, and anything not marked as HTML otherwise is taken to be text.
+    pub content_is_html: bool,
     pub published_at: Option<DateTime<FixedOffset>>,
 }
 
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct RawFeed {
+    pub title: String,
+    pub link: String,
+    pub html_url: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+/// What fetching a link as a feed turned up, without anything being stored.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FeedProbe {
+    pub title: String,
+    pub format: FeedFormat,
+    pub item_count: usize,
+    /// The feed's own link, which differs from the one probed when that was a page advertising it.
+    pub link: String,
+}
+
+pub struct Icon {
+    pub data: Vec<u8>,
+    pub content_type: Option<String>,
+}
+
+/// Where a feed supporting [WebSub](https://www.w3.org/TR/websub/) pushes its updates: the hub to
+/// subscribe at, and the topic, the feed's own link, to subscribe to.
+#[derive(Debug, PartialEq)]
+pub struct WebSub {
+    pub hub: String,
+    pub topic: String,
+}
+
+/// A feed fetched for its items, with what else the response said about the feed.
+pub struct FetchedItems {
+    pub title: String,
+    pub items: Vec<RawItem>,
+    pub html_url: Option<String>,
+    pub websub: Option<WebSub>,
+    /// The media type the server labelled the feed with, without parameters such as the charset.
+    pub content_type: Option<String>,
+    pub validators: Validators,
+}
+
+/// A fetched document, with the media type the server labelled it with.
+struct Body {
+    text: String,
+    content_type: Option<String>,
+}
+
+/// The cache validators a server sent along with a feed, to be sent back on the next request.
+#[derive(Default, Debug, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
 pub fn fetch_feed_title(link: &str, proxy: Option<&str>) -> Result<String> {
-    let content = fetch_content(link, proxy)?;
-    match content.parse::<Feed>()? {
-        Feed::Atom(atom) => Ok(atom.title().to_string()),
-        Feed::RSS(rss) => Ok(rss.title().to_string()),
+    Ok(Feed::parse(&fetch_content(link, proxy)?)?.title())
+}
+
+/// Fetches `link` and returns the feed behind it. If `link` points to an HTML page instead of a
+/// feed, the first feed advertised by the page is fetched instead.
+pub fn fetch_feed(link: &str, proxy: Option<&str>) -> Result<RawFeed> {
+    let (link, feed) = fetch_discovered(link, proxy)?;
+
+    Ok(RawFeed {
+        title: feed.title(),
+        link,
+        html_url: feed.html_url(),
+    })
+}
+
+/// Checks that `link`, or the feed its page advertises, is a feed with items, as `fetch_feed` would
+/// find it. Fails with `Unreachable` when nothing could be fetched, `SyndicationNotFound` when what
+/// was fetched is not a feed, and `EmptyFeed` when the feed has no items.
+pub fn probe_feed(link: &str, proxy: Option<&str>) -> Result<FeedProbe> {
+    let (link, feed) = fetch_discovered(link, proxy).map_err(|e| match e {
+        Error::ReqwestError { .. } | Error::IoError { .. } => Error::Unreachable(link.to_string()),
+        Error::SyndicationParsingFailure => Error::SyndicationNotFound(link.to_string()),
+        e => e,
+    })?;
+
+    let item_count = feed_items(&feed).len();
+    if item_count == 0 {
+        return Err(Error::EmptyFeed(link));
+    }
+
+    Ok(FeedProbe {
+        title: feed.title(),
+        format: feed.format(),
+        item_count,
+        link,
+    })
+}
+
+/// Fetches and parses `link`, or else the first feed advertised by the page at `link`, and returns
+/// it with the link it was found at.
+fn fetch_discovered(link: &str, proxy: Option<&str>) -> Result<(String, Feed)> {
+    let body = fetch_content(link, proxy)?;
+    if let Ok(feed) = Feed::parse(&body) {
+        return Ok((link.to_string(), feed));
+    }
+
+    let discovered = discover_feed_links(&body.text, link)
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::SyndicationNotFound(link.to_string()))?;
+    let feed = Feed::parse(&fetch_content(&discovered, proxy)?)?;
+
+    Ok((discovered, feed))
+}
+
+/// Fetches the icon of the site `link` belongs to: the first icon advertised by the site's homepage
+/// that can be downloaded, or else `/favicon.ico`. Returns `None` when the site has no icon.
+pub fn fetch_icon(link: &str, proxy: Option<&str>) -> Result<Option<Icon>> {
+    let Some(origin) = Url::parse(link)
+        .ok()
+        .filter(Url::has_host)
+        .and_then(|x| x.join("/").ok())
+    else {
+        return Ok(None);
+    };
+
+    let mut candidates = fetch_content(origin.as_str(), proxy)
+        .map(|x| discover_icon_links(&x.text, origin.as_str()))
+        .unwrap_or_default();
+    candidates.extend(origin.join("favicon.ico").ok().map(String::from));
+
+    for candidate in candidates {
+        let icon = fetch_binary(&candidate, proxy).ok().flatten().filter(|x| {
+            !x.data.is_empty()
+                && !x
+                    .content_type
+                    .as_deref()
+                    .is_some_and(|x| x.starts_with("text/"))
+        });
+        if icon.is_some() {
+            return Ok(icon);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Collects the feed links advertised with `<link rel="alternate">` in an HTML document, resolved
+/// against `base`.
+pub fn discover_feed_links(html: &str, base: &str) -> Vec<String> {
+    find_links(html, base)
+        .into_iter()
+        .filter(|x| x.rel.iter().any(|x| x == "alternate"))
+        .filter(|x| {
+            matches!(
+                x.kind.as_deref(),
+                Some("application/rss+xml" | "application/atom+xml" | "application/feed+json")
+            )
+        })
+        .map(|x| x.href)
+        .collect()
+}
+
+/// Collects the icons advertised with `<link rel="icon">` or `<link rel="shortcut icon">` in an HTML
+/// document, resolved against `base`.
+pub fn discover_icon_links(html: &str, base: &str) -> Vec<String> {
+    find_links(html, base)
+        .into_iter()
+        .filter(|x| x.rel.iter().any(|x| x == "icon"))
+        .map(|x| x.href)
+        .collect()
+}
+
+/// Fetches the web page at `link` and extracts its article, as `extract_article` does.
+pub fn fetch_article(link: &str, proxy: Option<&str>) -> Result<Option<String>> {
+    Ok(extract_article(&fetch_content(link, proxy)?.text))
+}
+
+/// Elements that are never part of an article: scripts, styles, navigation, forms and page chrome.
+const NOT_ARTICLE: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "nav", "header", "footer", "aside", "form",
+];
+
+/// Extracts the readable part of an HTML page: the contents of its `<article>` with the most text,
+/// as an index page has several, or else of its `<main>` or `<body>`, without comments, scripts,
+/// styles, navigation, forms and page chrome. The page is parsed as a browser would, so markup in
+/// scripts or comments and unclosed tags do not throw it off. Returns `None` when no text is left.
+pub fn extract_article(html: &str) -> Option<String> {
+    let mut document = Html::parse_document(html);
+    let removed = document
+        .tree
+        .nodes()
+        .filter(|x| match x.value() {
+            Node::Comment(_) => true,
+            Node::Element(e) => NOT_ARTICLE.contains(&e.name()),
+            _ => false,
+        })
+        .map(|x| x.id())
+        .collect::<Vec<_>>();
+    for id in removed {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let root = document.root_element();
+    let text_len = |x: &ElementRef| x.text().map(|x| x.trim().len()).sum::<usize>();
+    let largest = |name: &str| {
+        root.descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|x| x.value().name() == name)
+            .min_by_key(|x| Reverse(text_len(x)))
+    };
+    let article = largest("article")
+        .or_else(|| largest("main"))
+        .or_else(|| largest("body"))
+        .unwrap_or(root);
+
+    match text_len(&article) {
+        0 => None,
+        _ => Some(article.inner_html().trim().to_string()),
     }
 }
 
+/// Makes feed HTML safe to render: only allowlisted tags, attributes and URL schemes are kept, so
+/// scripts, embedded frames, event handlers and `javascript:` links are dropped however they are
+/// spelled. 1x1 tracking images are removed too, and relative links and image sources are resolved
+/// against `base`.
+pub fn sanitize_html(html: &str, base: Option<&str>) -> String {
+    let mut builder = ammonia::Builder::default();
+    builder.rm_tags(["base", "meta", "link"]);
+    if let Some(base) = base.and_then(|x| Url::parse(x).ok()) {
+        builder.url_relative(ammonia::UrlRelative::RewriteWithBase(base));
+    }
+    let html = builder.clean(html).to_string();
+
+    // The output is well-formed, with every attribute value double-quoted, so a tracking image can
+    // be told by its size.
+    let img_re = Regex::new(r#"<img(?:\s+[^\s="<>/]+(?:="[^"]*")?)*\s*/?>"#).unwrap();
+    img_re
+        .replace_all(&html, |tag: &regex::Captures| {
+            let is_pixel = |name| {
+                ["0", "1", "1px"]
+                    .iter()
+                    .any(|x| tag[0].contains(&format!(" {name}=\"{x}\"")))
+            };
+            match is_pixel("width") && is_pixel("height") {
+                true => String::new(),
+                false => tag[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+struct HtmlLink {
+    rel: Vec<String>,
+    kind: Option<String>,
+    href: String,
+}
+
+fn find_links(html: &str, base: &str) -> Vec<HtmlLink> {
+    let tag_re = Regex::new(r"(?is)<link\b[^>]*>").unwrap();
+    let attr_re = Regex::new(r#"(?is)([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    let base = Url::parse(base).ok();
+
+    tag_re
+        .find_iter(html)
+        .filter_map(|tag| {
+            let mut rel = None;
+            let mut kind = None;
+            let mut href = None;
+
+            for attr in attr_re.captures_iter(tag.as_str()) {
+                let value = attr.get(2).or(attr.get(3)).map_or("", |x| x.as_str());
+                match attr[1].to_lowercase().as_str() {
+                    "rel" => rel = Some(value.to_lowercase()),
+                    "type" => kind = Some(value.to_lowercase()),
+                    "href" => href = Some(value.replace("&amp;", "&")),
+                    _ => {}
+                }
+            }
+
+            let href = href?;
+            Some(HtmlLink {
+                rel: rel?.split_whitespace().map(str::to_string).collect(),
+                kind,
+                href: match &base {
+                    Some(base) => base.join(&href).ok().map(String::from)?,
+                    None => href,
+                },
+            })
+        })
+        .collect()
+}
+
 pub fn fetch_feed_items(link: &str, proxy: Option<&str>) -> Result<Vec<RawItem>> {
-    let content = fetch_content(link, proxy)?;
-    match content.parse::<Feed>()? {
-        Feed::Atom(atom) => Ok(atom
+    Ok(feed_items(&Feed::parse(&fetch_content(link, proxy)?)?))
+}
+
+/// Like `fetch_feed_items`, but makes a conditional request with `validators` and sends `headers`
+/// along. Returns `None` when the server reports the feed as not modified.
+pub fn fetch_feed_items_if_modified(
+    link: &str,
+    proxy: Option<&str>,
+    validators: &Validators,
+    headers: &HashMap<String, String>,
+) -> Result<Option<FetchedItems>> {
+    let Some((body, validators)) = fetch_content_if_modified(link, proxy, validators, headers)?
+    else {
+        return Ok(None);
+    };
+
+    let feed = Feed::parse(&body)?;
+    Ok(Some(FetchedItems {
+        title: feed.title(),
+        items: feed_items(&feed),
+        html_url: feed.html_url(),
+        websub: feed.websub(link),
+        content_type: body
+            .content_type
+            .as_deref()
+            .and_then(|x| x.split(';').next())
+            .map(|x| x.trim().to_ascii_lowercase())
+            .filter(|x| !x.is_empty()),
+        validators,
+    }))
+}
+
+fn feed_items(feed: &Feed) -> Vec<RawItem> {
+    match feed {
+        Feed::Atom(atom) => atom
             .entries()
             .iter()
             .map(|x| RawItem {
+                guid: Some(x.id().trim())
+                    .filter(|x| !x.is_empty())
+                    .map(str::to_string),
                 title: x.title().to_string(),
                 author: Some(
                     x.authors()
@@ -41,20 +375,36 @@ pub fn fetch_feed_items(link: &str, proxy: Option<&str>) -> Result<Vec<RawItem>>
                     .map(atom_syndication::Content::value)
                     .filter(std::option::Option::is_some)
                     .map(|x| x.unwrap().to_string()),
+                summary: x.summary().map(|x| x.value.clone()),
+                content_is_html: match x.content() {
+                    Some(content) if content.value().is_some() => content
+                        .content_type()
+                        .is_some_and(|x| x.to_ascii_lowercase().contains("html")),
+                    _ => x
+                        .summary()
+                        .is_some_and(|x| x.r#type != atom_syndication::TextType::Text),
+                },
                 published_at: x.published().map(|x| x.with_timezone(&Utc).fixed_offset()),
             })
-            .collect()),
-        Feed::RSS(rss) => Ok(rss
+            .collect(),
+        Feed::RSS(rss) => rss
             .items()
             .iter()
             .map(|x| RawItem {
+                guid: x
+                    .guid()
+                    .map(|x| x.value().trim())
+                    .filter(|x| !x.is_empty())
+                    .map(str::to_string),
                 title: x.title().unwrap_or("Untitled").trim().to_string(),
                 author: x
                     .author()
                     .map(|x| x.trim().to_string())
                     .or(x.dublin_core_ext().map(|x| x.creators().join(","))),
                 link: x.link().map(std::string::ToString::to_string),
-                content: x.description().map(std::string::ToString::to_string),
+                content: x.content().map(std::string::ToString::to_string),
+                summary: x.description().map(std::string::ToString::to_string),
+                content_is_html: true,
                 published_at: x
                     .pub_date()
                     .map(|x| {
@@ -62,33 +412,474 @@ pub fn fetch_feed_items(link: &str, proxy: Option<&str>) -> Result<Vec<RawItem>>
                             .map(|x| x.with_timezone(&Utc).fixed_offset())
                     })
                     .filter(std::result::Result::is_ok)
-                    .map(std::result::Result::unwrap),
+                    .map(std::result::Result::unwrap)
+                    .or_else(|| {
+                        // RSS 1.0 dates its items with `dc:date` instead of `pubDate`.
+                        x.dublin_core_ext()
+                            .and_then(|x| x.dates().first())
+                            .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+                            .map(|x| x.with_timezone(&Utc).fixed_offset())
+                    }),
+            })
+            .collect(),
+        Feed::Json(json) => json
+            .items
+            .iter()
+            .map(|x| RawItem {
+                guid: match &x.id {
+                    serde_json::Value::String(id) => Some(id.trim().to_string()),
+                    serde_json::Value::Number(id) => Some(id.to_string()),
+                    _ => None,
+                }
+                .filter(|x| !x.is_empty()),
+                title: x.title.as_deref().unwrap_or("Untitled").trim().to_string(),
+                author: x.authors.as_ref().map_or_else(
+                    || x.author.as_ref().and_then(|x| x.name.clone()),
+                    |authors| {
+                        Some(
+                            authors
+                                .iter()
+                                .filter_map(|x| x.name.as_deref().map(str::trim))
+                                .collect::<Vec<_>>()
+                                .join(","),
+                        )
+                    },
+                ),
+                link: x.url.clone(),
+                content: x.content_html.clone().or(x.content_text.clone()),
+                summary: x.summary.clone(),
+                content_is_html: x.content_html.is_some(),
+                published_at: x
+                    .date_published
+                    .as_deref()
+                    .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+                    .map(|x| x.with_timezone(&Utc).fixed_offset()),
             })
-            .collect()),
+            .collect(),
     }
 }
 
+/// Reads fixtures from disk instead of fetching. Links on the `fixtures.test` host stand for the
+/// file of the same name in `src/tests/fixtures`; anything else is read as a path.
+///
+/// A fixture whose name ends in `.gz` is gunzipped, as the client does with what a server
+/// compressed, and a link with `?fail=n` times out the first `n` times it is fetched on a thread, as if the server
+/// were flaky.
 #[cfg(test)]
-fn fetch_content(link: &str, _proxy: Option<&str>) -> Result<String> {
-    use std::fs;
-    Ok(fs::read_to_string(link)?)
+fn fetch_content(link: &str, _proxy: Option<&str>) -> Result<Body> {
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        fs,
+        io::{self, Read},
+    };
+
+    thread_local! {
+        static FETCHES: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+    }
+
+    let failures = Url::parse(link).ok().and_then(|url| {
+        url.query_pairs()
+            .find(|(key, _)| key == "fail")
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+    });
+    if let Some(failures) = failures {
+        let attempt = FETCHES.with(|x| {
+            let mut fetches = x.borrow_mut();
+            let attempt = fetches.entry(link.to_string()).or_default();
+            *attempt += 1;
+            *attempt
+        });
+        if attempt <= failures {
+            return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+        }
+    }
+
+    let path = fixture_path(link);
+    let (name, gzipped) = match path.strip_suffix(".gz") {
+        Some(name) => (name, true),
+        None => (path.as_str(), false),
+    };
+    let content_type = match name.rsplit('.').next() {
+        Some("atom") => Some("application/atom+xml".to_string()),
+        Some("rss") => Some("application/rss+xml".to_string()),
+        Some("rdf") => Some("application/rdf+xml".to_string()),
+        Some("json") => Some("application/feed+json".to_string()),
+        Some("html") => Some("text/html".to_string()),
+        _ => None,
+    };
+
+    let mut data = fs::read(&path)?;
+    if gzipped {
+        let mut gunzipped = vec![];
+        flate2::read::GzDecoder::new(&data[..]).read_to_end(&mut gunzipped)?;
+        data = gunzipped;
+    }
+    Ok(Body {
+        text: decode_text(&data, content_type.as_deref()),
+        content_type,
+    })
 }
 
-#[cfg(not(test))]
-fn fetch_content(link: &str, proxy: Option<&str>) -> Result<String> {
-    let client = if let Some(proxy_url) = proxy {
-        match reqwest::Proxy::all(proxy_url) {
-            Ok(p) => reqwest::blocking::Client::builder().proxy(p).build()?,
-            Err(_) => reqwest::blocking::Client::new(),
+#[cfg(test)]
+fn fetch_binary(link: &str, _proxy: Option<&str>) -> Result<Option<Icon>> {
+    use std::{fs, io::ErrorKind};
+
+    let path = fixture_path(link);
+    let content_type = match path.rsplit('.').next() {
+        Some("ico") => Some("image/x-icon".to_string()),
+        Some("png") => Some("image/png".to_string()),
+        _ => None,
+    };
+
+    match fs::read(path) {
+        Ok(data) => Ok(Some(Icon { data, content_type })),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Accepts any request `websub_request` builds for a hub on `fixtures.test`, as a hub would before
+/// verifying it.
+#[cfg(test)]
+pub fn request_websub_subscription(
+    hub: &str,
+    topic: &str,
+    callback: &str,
+    _proxy: Option<&str>,
+) -> Result<()> {
+    let request = websub_request(&reqwest::blocking::Client::new(), hub, topic, callback)?;
+    match request.url().host_str() {
+        Some("fixtures.test") => Ok(()),
+        _ => Err(Error::Unreachable(hub.to_string())),
+    }
+}
+
+#[cfg(test)]
+fn fixture_path(link: &str) -> String {
+    match Url::parse(link) {
+        Ok(url) if url.host_str() == Some("fixtures.test") => {
+            format!("src/tests/fixtures{}", url.path())
         }
-    } else {
-        reqwest::blocking::Client::new()
+        _ => link.to_string(),
+    }
+}
+
+/// Serves fixtures with an ETag derived from their content, and answers as not modified when the
+/// request's ETag matches. A link with `?token=x` is refused unless the request `feed_request`
+/// builds is authorized with `Bearer x`, as a private feed would, and one with `?retry_after=x` is
+/// answered with a 429 and `Retry-After: x`.
+#[cfg(test)]
+fn fetch_content_if_modified(
+    link: &str,
+    proxy: Option<&str>,
+    validators: &Validators,
+    headers: &HashMap<String, String>,
+) -> Result<Option<(Body, Validators)>> {
+    let retry_after = Url::parse(link).ok().and_then(|url| {
+        url.query_pairs()
+            .find(|(key, _)| key == "retry_after")
+            .map(|(_, value)| value.to_string())
+    });
+    if let Some(retry_after) = retry_after {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, retry_after.parse().unwrap());
+        check_rate_limit(
+            link,
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+            &headers,
+            Utc::now(),
+        )?;
+    }
+
+    let token = Url::parse(link).ok().and_then(|url| {
+        url.query_pairs()
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.to_string())
+    });
+    if let Some(token) = token {
+        let request = feed_request(&reqwest::blocking::Client::new(), link, validators, headers)?;
+        let authorized = request.headers().get(reqwest::header::AUTHORIZATION)
+            == Some(&format!("Bearer {token}").parse().unwrap());
+        if !authorized {
+            return Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied).into());
+        }
+    }
+
+    let body = fetch_content(link, proxy)?;
+    let etag = format!("\"{}\"", sha1_smol::Sha1::from(&body.text).hexdigest());
+    if validators.etag.as_ref() == Some(&etag) {
+        return Ok(None);
+    }
+
+    Ok(Some((
+        body,
+        Validators {
+            etag: Some(etag),
+            last_modified: None,
+        },
+    )))
+}
+
+/// Asks `hub` to push updates of `topic` to `callback`. The hub accepts the request and later
+/// verifies it with the callback, which answers for the subscriber.
+#[cfg(not(test))]
+pub fn request_websub_subscription(
+    hub: &str,
+    topic: &str,
+    callback: &str,
+    proxy: Option<&str>,
+) -> Result<()> {
+    crate::rate_limit::wait_for(hub);
+    let client = client(proxy)?;
+    client
+        .execute(websub_request(&client, hub, topic, callback)?)?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+#[cfg(not(test))]
+fn fetch_content(link: &str, proxy: Option<&str>) -> Result<Body> {
+    crate::rate_limit::wait_for(link);
+    read_body(client(proxy)?.get(link).send()?.error_for_status()?)
+}
+
+/// Downloads `link` as bytes. Returns `None` when the server does not answer with a success.
+#[cfg(not(test))]
+fn fetch_binary(link: &str, proxy: Option<&str>) -> Result<Option<Icon>> {
+    crate::rate_limit::wait_for(link);
+    let response = client(proxy)?.get(link).send()?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    Ok(Some(Icon {
+        content_type: content_type(&response),
+        data: response.bytes()?.to_vec(),
+    }))
+}
+
+#[cfg(not(test))]
+fn fetch_content_if_modified(
+    link: &str,
+    proxy: Option<&str>,
+    validators: &Validators,
+    headers: &HashMap<String, String>,
+) -> Result<Option<(Body, Validators)>> {
+    use reqwest::header::{ETAG, LAST_MODIFIED};
+
+    crate::rate_limit::wait_for(link);
+    let client = client(proxy)?;
+    let response = client.execute(feed_request(&client, link, validators, headers)?)?;
+    check_rate_limit(link, response.status(), response.headers(), Utc::now())?;
+
+    let response = response.error_for_status()?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(str::to_string)
+    };
+    let validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
     };
+
+    Ok(Some((read_body(response)?, validators)))
+}
+
+/// Builds the request that fetches the feed at `link`: with the feed's own `headers`, and the
+/// `validators` of its last fetch so that the server can answer that nothing changed.
+pub fn feed_request(
+    client: &reqwest::blocking::Client,
+    link: &str,
+    validators: &Validators,
+    headers: &HashMap<String, String>,
+) -> Result<reqwest::blocking::Request> {
+    use reqwest::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+
+    let mut request = client.get(link);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    Ok(request.build()?)
+}
+
+/// Builds the request that asks `hub` to push updates of `topic` to `callback`: a form `POST`, as
+/// the WebSub spec has subscribers send.
+pub fn websub_request(
+    client: &reqwest::blocking::Client,
+    hub: &str,
+    topic: &str,
+    callback: &str,
+) -> Result<reqwest::blocking::Request> {
     Ok(client
-        .get(link)
-        .header("User-Agent", "Mozilla/5.0")
-        .send()?
-        .text()?)
+        .post(hub)
+        .form(&[
+            ("hub.mode", "subscribe"),
+            ("hub.topic", topic),
+            ("hub.callback", callback),
+        ])
+        .build()?)
+}
+
+/// Fails with `RateLimited` when the server answered `link` with a 429, until when its
+/// `Retry-After` header, as of `now`, says.
+pub fn check_rate_limit(
+    link: &str,
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    if status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Ok(());
+    }
+
+    let retry_after = headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|x| x.to_str().ok());
+    Err(Error::RateLimited(
+        link.to_string(),
+        parse_retry_after(retry_after, now),
+    ))
+}
+
+/// Reads the `Retry-After` of a 429, as seconds from `now` or an HTTP date, into when to fetch
+/// again. A missing or unreadable header means `DEFAULT_RETRY_AFTER_MINUTES`, and no server may
+/// put the feed off for longer than `MAX_RETRY_AFTER_MINUTES`.
+pub fn parse_retry_after(value: Option<&str>, now: DateTime<Utc>) -> DateTime<FixedOffset> {
+    /// How long to wait when the server does not say.
+    const DEFAULT_RETRY_AFTER_MINUTES: i64 = 60;
+    const MAX_RETRY_AFTER_MINUTES: i64 = 24 * 60;
+
+    let value = value.map(str::trim).unwrap_or_default();
+    let at = match value.parse::<u32>() {
+        Ok(seconds) => now + chrono::Duration::seconds(seconds.into()),
+        Err(_) => DateTime::parse_from_rfc2822(value)
+            .map(|x| x.with_timezone(&Utc))
+            .unwrap_or(now + chrono::Duration::minutes(DEFAULT_RETRY_AFTER_MINUTES)),
+    };
+
+    at.clamp(
+        now,
+        now + chrono::Duration::minutes(MAX_RETRY_AFTER_MINUTES),
+    )
+    .fixed_offset()
+}
+
+#[cfg(not(test))]
+fn content_type(response: &reqwest::blocking::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Reads the response as text, decoded from the charset the server labelled it with.
+#[cfg(not(test))]
+fn read_body(response: reqwest::blocking::Response) -> Result<Body> {
+    let content_type = content_type(&response);
+    let bytes = response.bytes()?;
+
+    Ok(Body {
+        text: decode_text(&bytes, content_type.as_deref()),
+        content_type,
+    })
+}
+
+/// Decodes text in the charset given by its byte order mark, else by `content_type`, else by its XML
+/// declaration, or as UTF-8 if none names one. The declaration is then changed to say UTF-8, so
+/// that the feed parsers do not decode the text a second time.
+fn decode_text(data: &[u8], content_type: Option<&str>) -> String {
+    let charset = content_type
+        .and_then(|x| {
+            x.split(';')
+                .filter_map(|x| x.trim().split_once('='))
+                .find(|(key, _)| key.eq_ignore_ascii_case("charset"))
+        })
+        .and_then(|(_, value)| encoding_rs::Encoding::for_label(value.trim_matches('"').as_bytes()))
+        .or_else(|| declared_encoding(data))
+        .unwrap_or(encoding_rs::UTF_8);
+
+    // `decode` prefers a byte order mark over the charset it is given, and drops the mark.
+    let text = charset.decode(data).0;
+
+    xml_declaration_encoding()
+        .replace(&text, "${1}UTF-8${3}")
+        .into_owned()
+}
+
+/// The encoding the XML declaration at the start of `data` names, if any. The declaration is ASCII
+/// in every encoding a feed is likely to use besides UTF-16, which comes with a byte order mark.
+fn declared_encoding(data: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    let head = String::from_utf8_lossy(&data[..data.len().min(1024)]);
+    let label = xml_declaration_encoding().captures(&head)?[2].to_string();
+
+    encoding_rs::Encoding::for_label(label.as_bytes())
+}
+
+/// Matches the `encoding` of an XML declaration, capturing what comes before its value, the value
+/// and the closing quote.
+fn xml_declaration_encoding() -> Regex {
+    Regex::new(r#"^(\s*<\?xml\s[^>]*?\bencoding\s*=\s*["'])([A-Za-z0-9._:-]+)(["'])"#).unwrap()
+}
+
+/// Returns the client all fetches go through. It is built once and kept for as long as the proxy
+/// setting stays the same, so that connections are reused across fetches.
+#[cfg(not(test))]
+fn client(proxy: Option<&str>) -> Result<reqwest::blocking::Client> {
+    use reqwest::{blocking::Client, redirect::Policy};
+    use std::{sync::Mutex, time::Duration};
+
+    /// Identifies collie to the servers it fetches from.
+    const USER_AGENT: &str = concat!(
+        "collie/",
+        env!("CARGO_PKG_VERSION"),
+        " (+https://github.com/parksb/collie)"
+    );
+    /// How long to wait for a server to accept the connection.
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    /// How long a whole request, including reading the body, may take.
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+    const MAX_REDIRECTS: usize = 10;
+
+    static CLIENT: Mutex<Option<(Option<String>, Client)>> = Mutex::new(None);
+
+    let mut cached = CLIENT.lock().unwrap();
+    if let Some((cached_proxy, client)) = &*cached {
+        if cached_proxy.as_deref() == proxy {
+            return Ok(client.clone());
+        }
+    }
+
+    // reqwest's `gzip`, `brotli` and `deflate` features have the client ask for compressed bodies
+    // and decompress them.
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(Policy::limited(MAX_REDIRECTS));
+    if let Some(proxy) = proxy.and_then(|x| reqwest::Proxy::all(x).ok()) {
+        builder = builder.proxy(proxy);
+    }
+
+    let client = builder.build()?;
+    *cached = Some((proxy.map(str::to_string), client.clone()));
+    Ok(client)
 }
 
 // borrowed from https://github.com/rust-syndication/syndication
@@ -97,6 +888,129 @@ fn fetch_content(link: &str, proxy: Option<&str>) -> Result<String> {
 pub enum Feed {
     Atom(atom_syndication::Feed),
     RSS(rss::Channel),
+    Json(JsonFeed),
+}
+
+/// A [JSON Feed](https://www.jsonfeed.org/version/1.1/), keeping only what is read from it.
+#[derive(Clone, Deserialize)]
+pub struct JsonFeed {
+    title: String,
+    home_page_url: Option<String>,
+    feed_url: Option<String>,
+    #[serde(default)]
+    hubs: Vec<JsonFeedHub>,
+    #[serde(default)]
+    items: Vec<JsonFeedItem>,
+}
+
+#[derive(Clone, Deserialize)]
+struct JsonFeedItem {
+    #[serde(default)]
+    id: serde_json::Value,
+    url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    authors: Option<Vec<JsonFeedAuthor>>,
+    /// Replaced by `authors` in version 1.1.
+    author: Option<JsonFeedAuthor>,
+}
+
+#[derive(Clone, Deserialize)]
+struct JsonFeedHub {
+    url: String,
+}
+
+#[derive(Clone, Deserialize)]
+struct JsonFeedAuthor {
+    name: Option<String>,
+}
+
+impl Feed {
+    /// Parses a fetched document, going by its media type when the server gave one: JSON Feeds are
+    /// parsed as JSON, and anything else as RSS or Atom.
+    fn parse(body: &Body) -> Result<Self> {
+        let is_json = body
+            .content_type
+            .as_deref()
+            .and_then(|x| x.split(';').next())
+            .is_some_and(|x| x.trim().ends_with("json"));
+
+        if is_json {
+            serde_json::from_str(&body.text)
+                .map(Self::Json)
+                .map_err(|_| Error::SyndicationParsingFailure)
+        } else {
+            body.text.parse()
+        }
+    }
+
+    fn format(&self) -> FeedFormat {
+        match self {
+            Self::Atom(_) => FeedFormat::Atom,
+            Self::RSS(_) => FeedFormat::Rss,
+            Self::Json(_) => FeedFormat::Json,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Self::Atom(atom) => atom.title().to_string(),
+            Self::RSS(rss) => rss.title().to_string(),
+            Self::Json(json) => json.title.clone(),
+        }
+    }
+
+    /// The link to the website the feed belongs to, if it has one.
+    pub fn html_url(&self) -> Option<String> {
+        let link = match self {
+            Self::Atom(atom) => atom
+                .links()
+                .iter()
+                .find(|x| x.rel() == "alternate")
+                .map(|x| x.href()),
+            Self::RSS(rss) => Some(rss.link()),
+            Self::Json(json) => json.home_page_url.as_deref(),
+        };
+
+        link.map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_string)
+    }
+
+    /// The WebSub hub the feed advertises, if any, with the feed's `self` link as the topic, or
+    /// else `link`, where the feed was fetched from.
+    pub fn websub(&self, link: &str) -> Option<WebSub> {
+        let find = |links: &[atom_syndication::Link], rel: &str| {
+            links
+                .iter()
+                .find(|x| x.rel() == rel)
+                .map(|x| x.href().to_string())
+        };
+        let (hub, topic) = match self {
+            Self::Atom(atom) => (find(atom.links(), "hub"), find(atom.links(), "self")),
+            Self::RSS(rss) => match rss.atom_ext() {
+                Some(atom) => (find(&atom.links, "hub"), find(&atom.links, "self")),
+                None => (None, None),
+            },
+            Self::Json(json) => (
+                json.hubs.first().map(|x| x.url.clone()),
+                json.feed_url.clone(),
+            ),
+        };
+
+        let hub = hub
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())?;
+        let topic = topic
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .unwrap_or_else(|| link.to_string());
+
+        Some(WebSub { hub, topic })
+    }
 }
 
 impl FromStr for Feed {
@@ -107,7 +1021,10 @@ impl FromStr for Feed {
             Ok(feed) => Ok(Self::Atom(feed)),
             Err(_) => match rss::Channel::from_str(s) {
                 Ok(channel) => Ok(Self::RSS(channel)),
-                Err(_) => Err(Error::SyndicationParsingFailure),
+                // Some servers label JSON Feeds as plain text.
+                Err(_) => serde_json::from_str(s)
+                    .map(Self::Json)
+                    .map_err(|_| Error::SyndicationParsingFailure),
             },
         }
     }
@@ -0,0 +1,111 @@
+use pretty_assertions::assert_eq;
+use rusqlite::Connection;
+
+use crate::backup::{self, ImportSummary};
+use crate::error::Error;
+use crate::models::{
+    feeds::{self, FeedStatus, FeedToUpdate},
+    items::{self, ItemReadOption, ItemStatus, ItemToCreate},
+};
+
+use super::{create_feed, db, item};
+
+fn create_item(db: &Connection, feed: i32, guid: &str, is_hidden: bool) {
+    items::create(
+        db,
+        &ItemToCreate {
+            guid: Some(guid.to_string()),
+            is_hidden,
+            published_at: "2023-08-28T01:33:24+00:00".parse().unwrap(),
+            ..item(feed, guid)
+        },
+    )
+    .unwrap();
+}
+
+fn states(db: &Connection) -> Vec<(String, String, bool, bool)> {
+    items::read_all(
+        db,
+        &ItemReadOption {
+            include_hidden: true,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .into_iter()
+    .map(|x| {
+        (
+            x.guid.unwrap(),
+            x.status.to_string(),
+            x.is_saved,
+            x.is_hidden,
+        )
+    })
+    .collect()
+}
+
+#[test]
+fn import_state_restores_an_exported_state() {
+    let source = db();
+    for link in ["https://a.example.com/feed", "https://b.example.com/feed"] {
+        create_feed(&source, link);
+    }
+    feeds::update(
+        &source,
+        &FeedToUpdate::for_id(2)
+            .title("Renamed")
+            .status(FeedStatus::Unsubscribed)
+            .build(),
+    )
+    .unwrap();
+    create_item(&source, 1, "read", false);
+    create_item(&source, 1, "saved", false);
+    create_item(&source, 2, "unread", false);
+    create_item(&source, 2, "hidden", true);
+    items::mark_read(&source, 1).unwrap();
+    items::toggle_star(&source, 2).unwrap();
+
+    let json = backup::export_state(&source).unwrap();
+    let target = db();
+    assert_eq!(
+        ImportSummary {
+            feeds_created: 2,
+            items_created: 4,
+            items_updated: 0,
+        },
+        backup::import_state(&target, &json).unwrap()
+    );
+    assert_eq!(states(&source), states(&target));
+    let renamed = feeds::read_required(&target, 2).unwrap();
+    assert_eq!("Renamed", renamed.display_title());
+    assert_eq!(FeedStatus::Unsubscribed, renamed.status);
+
+    assert_eq!(
+        ImportSummary::default(),
+        backup::import_state(&target, &json).unwrap()
+    );
+
+    items::mark_unread(&target, 1).unwrap();
+    let hidden = items::find_id(&target, 2, Some("hidden"), "")
+        .unwrap()
+        .unwrap();
+    items::update_state(&target, hidden, &ItemStatus::Unread, false, false).unwrap();
+    assert_eq!(
+        2,
+        backup::import_state(&target, &json).unwrap().items_updated
+    );
+    assert_eq!(states(&source), states(&target));
+}
+
+#[test]
+fn import_state_rejects_other_documents() {
+    let db = db();
+    assert!(matches!(
+        backup::import_state(&db, "<opml/>"),
+        Err(Error::BackupParsingFailure)
+    ));
+    assert!(matches!(
+        backup::import_state(&db, r#"{"version": 99, "feeds": []}"#),
+        Err(Error::BackupParsingFailure)
+    ));
+}
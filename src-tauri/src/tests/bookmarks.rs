@@ -0,0 +1,62 @@
+use pretty_assertions::assert_eq;
+use std::fs;
+
+use crate::bookmarks::{import_bookmarks, parse_bookmarks};
+use crate::models::{database, feeds};
+
+fn fixture() -> String {
+    fs::read_to_string("src/tests/fixtures/bookmarks.html").unwrap()
+}
+
+#[test]
+fn parse_bookmarks_collects_web_links_once() {
+    assert_eq!(
+        vec![
+            "http://fixtures.test/hnrss-org-frontpage.rss",
+            "http://fixtures.test/discovery.html",
+            "http://fixtures.test/article.html",
+        ],
+        parse_bookmarks(&fixture())
+    );
+}
+
+#[test]
+fn import_bookmarks_subscribes_to_the_feeds_found() {
+    let dir = std::env::temp_dir().join(format!("collie-bookmarks-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let pool = database::open_pool(&dir, 2).unwrap();
+
+    let import = import_bookmarks(&pool, &fixture(), 2, None).unwrap();
+    let feeds = feeds::read_all(&pool.get().unwrap()).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(
+        vec![
+            "http://fixtures.test/hnrss-org-frontpage.rss",
+            "http://fixtures.test/discovery.html",
+        ],
+        import
+            .subscribed
+            .iter()
+            .map(|x| x.0.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["http://fixtures.test/article.html"],
+        import
+            .skipped
+            .iter()
+            .map(|x| x.0.as_str())
+            .collect::<Vec<_>>()
+    );
+
+    let mut links = feeds.into_iter().map(|x| x.link).collect::<Vec<_>>();
+    links.sort();
+    assert_eq!(
+        vec![
+            "http://fixtures.test/formats.rss",
+            "http://fixtures.test/hnrss-org-frontpage.rss",
+        ],
+        links
+    );
+}
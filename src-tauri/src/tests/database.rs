@@ -0,0 +1,151 @@
+use pretty_assertions::assert_eq;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::models::{database, feeds};
+
+use super::{db, feed};
+
+#[test]
+fn vacuum_reclaims_space_outside_transactions() {
+    let db = db();
+    let feeds = (0..200)
+        .map(|x| feed(&"x".repeat(1000), &format!("https://example.com/{x}")))
+        .collect::<Vec<_>>();
+    feeds::create_many(&db, &feeds).unwrap();
+    db.execute("DELETE FROM feeds", []).unwrap();
+
+    let before = database::stats(&db).unwrap();
+    assert_eq!(0, before.feed_count);
+    assert!(before.freelist_count > 0);
+
+    {
+        let _tx = db.unchecked_transaction().unwrap();
+        assert!(matches!(database::vacuum(&db), Err(Error::Forbidden)));
+    }
+
+    database::vacuum(&db).unwrap();
+    let after = database::stats(&db).unwrap();
+    assert_eq!(0, after.freelist_count);
+    assert!(after.size_bytes < before.size_bytes);
+    assert_eq!(after.page_count * after.page_size, after.size_bytes);
+}
+
+#[test]
+fn init_creates_the_database_where_configured() {
+    let dir = std::env::temp_dir().join(format!("collie-init-{}", std::process::id()));
+    let config = database::DbConfig {
+        path: dir.join("nested/portable.db"),
+        pool_size: 1,
+        passphrase: None,
+    };
+
+    let pool = database::init(&config).unwrap();
+    feeds::create(&pool.get().unwrap(), &feed("A", "https://example.com/feed")).unwrap();
+    drop(pool);
+    let reopened = database::init(&config).unwrap();
+    let count = feeds::count(&reopened.get().unwrap()).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(1, count);
+
+    assert_eq!(
+        PathBuf::from("app").join(database::DB_FILE_NAME),
+        database::DbConfig::new(Path::new("app")).path
+    );
+    std::env::set_var(database::DB_PATH_VAR, "elsewhere/collie.db");
+    let overridden = database::DbConfig::new(Path::new("app")).path;
+    std::env::remove_var(database::DB_PATH_VAR);
+    assert_eq!(PathBuf::from("elsewhere/collie.db"), overridden);
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+#[test]
+fn init_refuses_a_passphrase_without_sqlcipher() {
+    let dir = std::env::temp_dir().join(format!("collie-plain-{}", std::process::id()));
+    let result = database::init(&database::DbConfig {
+        path: dir.join("collie.db"),
+        pool_size: 1,
+        passphrase: Some("secret".to_string()),
+    });
+
+    assert!(matches!(result, Err(Error::EncryptionUnavailable)));
+    assert!(!dir.exists());
+}
+
+#[cfg(feature = "sqlcipher")]
+#[test]
+fn encrypted_databases_reopen_only_with_their_passphrase() {
+    let dir = std::env::temp_dir().join(format!("collie-encrypted-{}", std::process::id()));
+    let config = |passphrase: &str| database::DbConfig {
+        path: dir.join("collie.db"),
+        pool_size: 1,
+        passphrase: Some(passphrase.to_string()),
+    };
+
+    let pool = database::init(&config("secret")).unwrap();
+    feeds::create(&pool.get().unwrap(), &feed("A", "https://example.com/feed")).unwrap();
+    drop(pool);
+
+    let reopened = database::init(&config("secret")).unwrap();
+    let count = feeds::count(&reopened.get().unwrap()).unwrap();
+    drop(reopened);
+    let wrong = database::init(&config("guess"));
+    let plain = rusqlite::Connection::open(dir.join("collie.db"))
+        .unwrap()
+        .query_row("SELECT COUNT(*) FROM feeds", [], |x| x.get::<_, i64>(0));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(1, count);
+    assert!(wrong.is_err());
+    assert!(plain.is_err());
+}
+
+#[test]
+fn pooled_connections_read_and_write_concurrently() {
+    let dir = std::env::temp_dir().join(format!("collie-wal-{}", std::process::id()));
+    let pool = database::init(&database::DbConfig {
+        path: dir.join("collie.db"),
+        pool_size: 3,
+        passphrase: None,
+    })
+    .unwrap();
+    let writer = pool.get().unwrap();
+    let reader = pool.get().unwrap();
+    let other_writer = pool.get().unwrap();
+
+    let journal_mode: String = reader
+        .pragma_query_value(None, "journal_mode", |x| x.get(0))
+        .unwrap();
+    assert_eq!("wal", journal_mode);
+
+    let (written, wait_for_write) = std::sync::mpsc::channel();
+    let committer = std::thread::spawn(move || {
+        let tx = writer.unchecked_transaction().unwrap();
+        feeds::create(&tx, &feed("A", "https://a.example.com/feed")).unwrap();
+        written.send(()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        tx.commit().unwrap();
+    });
+    wait_for_write.recv().unwrap();
+
+    // Readers see the last commit while a write is in progress, and a second writer waits for the
+    // first instead of failing.
+    assert_eq!(0, feeds::count(&reader).unwrap());
+    feeds::create(&other_writer, &feed("B", "https://b.example.com/feed")).unwrap();
+    committer.join().unwrap();
+
+    let count = feeds::count(&reader).unwrap();
+    drop((reader, other_writer, pool));
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(2, count);
+}
+
+#[test]
+fn in_memory_databases_are_independent() {
+    let first = database::open_in_memory().unwrap();
+    let second = database::open_in_memory().unwrap();
+    feeds::create(&first, &feed("Example", "https://example.com/feed")).unwrap();
+
+    assert_eq!(1, feeds::count(&first).unwrap());
+    assert_eq!(0, feeds::count(&second).unwrap());
+}
@@ -0,0 +1,1025 @@
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use pretty_assertions::assert_eq;
+use rusqlite::Connection;
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::error::Error;
+use crate::models::{
+    database,
+    feeds::{
+        self, FeedMetadata, FeedPayload, FeedSort, FeedStatus, FeedToCreate, FeedToUpdate,
+        FEED_SCHEMA_VERSION,
+    },
+};
+
+use super::{db, feed};
+
+#[test]
+fn create_rejects_duplicate_link() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://Example.com/feed/")).unwrap();
+
+    let err = feeds::create(&db, &feed("Example again", "https://example.com/feed")).unwrap_err();
+    assert!(matches!(err, Error::AlreadyExists(1)));
+    assert_eq!(feeds::count(&db).unwrap(), 1);
+}
+
+#[test]
+fn create_normalizes_link() {
+    let db = db();
+    feeds::create(&db, &feed("Root", "HTTPS://EXAMPLE.COM/")).unwrap();
+    feeds::create(&db, &feed("Nested", "https://example.com/a/b//?q=1")).unwrap();
+
+    let links = feeds::read_all(&db)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.link)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "https://example.com".to_string(),
+            "https://example.com/a/b?q=1".to_string(),
+        ],
+        links,
+    );
+}
+
+#[test]
+fn search_matches_titles_literally() {
+    let db = db();
+    feeds::create(&db, &feed("C++ Weekly", "https://example.com/cpp")).unwrap();
+    feeds::create(&db, &feed("Rust Blog", "https://example.com/rust")).unwrap();
+
+    let titles = |query| {
+        feeds::search(&db, query)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.display_title().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["C++ Weekly".to_string()], titles("C++"));
+    assert_eq!(vec!["Rust Blog".to_string()], titles("rust \"blog"));
+    assert!(titles("  ").is_empty());
+
+    feeds::update(&db, &FeedToUpdate::for_id(2).title("Ferris News").build()).unwrap();
+    assert!(titles("rust").is_empty());
+    assert_eq!(vec!["Ferris News".to_string()], titles("ferris"));
+}
+
+#[test]
+fn read_required_reports_missing_feed() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+
+    assert_eq!("Example", feeds::read_required(&db, 1).unwrap().title);
+    assert!(matches!(
+        feeds::read_required(&db, 42),
+        Err(Error::NotFound(42))
+    ));
+}
+
+#[test]
+fn create_many_is_all_or_nothing() {
+    let db = db();
+    let ids = feeds::create_many(
+        &db,
+        &[
+            feed("A", "https://a.example.com/feed"),
+            feed("B", "https://b.example.com/feed"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(vec![1, 2], ids);
+
+    let err = feeds::create_many(
+        &db,
+        &[
+            feed("C", "https://c.example.com/feed"),
+            feed("A again", "https://a.example.com/feed/"),
+        ],
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::AlreadyExists(1)));
+    assert_eq!(2, feeds::count(&db).unwrap());
+}
+
+#[test]
+fn read_stale_respects_fetch_intervals() {
+    let db = db();
+    feeds::create_many(
+        &db,
+        &[
+            feed("A", "https://a.example.com/feed"),
+            feed("B", "https://b.example.com/feed"),
+            feed("C", "https://c.example.com/feed"),
+            feed("D", "https://d.example.com/feed"),
+            feed("E", "https://e.example.com/feed"),
+        ],
+    )
+    .unwrap();
+
+    let now = Utc::now().fixed_offset();
+    for (id, checked_minutes_ago, interval, status) in [
+        (1, 10, 5, FeedStatus::Subscribed),
+        (2, 30, 0, FeedStatus::Subscribed),
+        (3, 10, 60, FeedStatus::Subscribed),
+        (4, 60, 0, FeedStatus::Unsubscribed),
+        (5, 20, 15, FeedStatus::Subscribed),
+    ] {
+        feeds::update(
+            &db,
+            &FeedToUpdate::for_id(id)
+                .status(status)
+                .checked_at(now - Duration::minutes(checked_minutes_ago))
+                .fetch_interval_minutes(interval)
+                .build(),
+        )
+        .unwrap();
+    }
+
+    let now = now.with_timezone(&FixedOffset::east_opt(9 * 3600).unwrap());
+    let ids = feeds::read_stale(&db, now)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    assert_eq!(vec![2, 1, 5], ids);
+}
+
+#[test]
+fn new_feeds_are_stale_right_away() {
+    let db = db();
+    feeds::create(&db, &feed("A", "https://a.example.com/feed")).unwrap();
+    let now = Utc::now().fixed_offset();
+    feeds::update(
+        &db,
+        &FeedToUpdate::for_id(1)
+            .checked_at(now - Duration::minutes(30))
+            .fetch_interval_minutes(0)
+            .build(),
+    )
+    .unwrap();
+    feeds::create(&db, &feed("B", "https://b.example.com/feed")).unwrap();
+
+    let ids = feeds::read_stale(&db, now)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    assert_eq!(vec![2, 1], ids);
+}
+
+#[test]
+fn read_stale_backs_off_failing_feeds() {
+    let db = db();
+    feeds::create_many(
+        &db,
+        &[
+            feed("Healthy", "https://a.example.com/feed"),
+            feed("Failing", "https://b.example.com/feed"),
+        ],
+    )
+    .unwrap();
+    for _ in 0..5 {
+        feeds::update_health(&db, 2, Some("404 Not Found")).unwrap();
+    }
+
+    let stale_ids = |checked_minutes_ago| {
+        let now = Utc::now().fixed_offset();
+        for id in [1, 2] {
+            feeds::update(
+                &db,
+                &FeedToUpdate::for_id(id)
+                    .checked_at(now - Duration::minutes(checked_minutes_ago))
+                    .fetch_interval_minutes(5)
+                    .build(),
+            )
+            .unwrap();
+        }
+
+        feeds::read_stale(&db, now)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.id)
+            .collect::<Vec<_>>()
+    };
+
+    // Five failures stretch the 5 minute interval to 5 * 2^5 = 160 minutes.
+    assert_eq!(vec![1], stale_ids(10));
+    assert_eq!(vec![1], stale_ids(150));
+    assert_eq!(vec![1, 2], stale_ids(170));
+
+    feeds::update_health(&db, 2, None).unwrap();
+    assert_eq!(vec![1, 2], stale_ids(10));
+}
+
+#[test]
+fn delete_archives_until_restored_or_purged() {
+    let db = db();
+    feeds::create(&db, &feed("Kept", "https://example.com/kept")).unwrap();
+    feeds::create(&db, &feed("Archived", "https://example.com/archived")).unwrap();
+
+    feeds::delete(&db, 2).unwrap();
+    assert!(feeds::read(&db, 2).unwrap().is_none());
+    assert_eq!(feeds::count(&db).unwrap(), 1);
+    let archived = feeds::read_archived(&db).unwrap();
+    assert_eq!(vec![2], archived.iter().map(|x| x.id).collect::<Vec<_>>());
+    assert!(archived[0].deleted_at.is_some());
+
+    feeds::create(&db, &feed("Archived", "https://example.com/archived/")).unwrap();
+    assert!(feeds::read(&db, 2).unwrap().unwrap().deleted_at.is_none());
+    assert_eq!(feeds::count(&db).unwrap(), 2);
+
+    feeds::delete(&db, 2).unwrap();
+    feeds::restore(&db, 2).unwrap();
+    assert!(feeds::read_archived(&db).unwrap().is_empty());
+
+    feeds::purge(&db, 2).unwrap();
+    assert!(feeds::read(&db, 2).unwrap().is_none());
+    assert!(feeds::read_archived(&db).unwrap().is_empty());
+}
+
+thread_local! {
+    static TRACED: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+}
+
+/// Runs `read` and returns the query plan of the last statement it ran.
+fn last_query_plan(db: &mut Connection, read: impl FnOnce(&Connection)) -> Vec<String> {
+    db.trace(Some(|sql| {
+        TRACED.with(|x| x.borrow_mut().push(sql.to_string()))
+    }));
+    read(db);
+    db.trace(None);
+
+    let sql = TRACED.with(|x| x.borrow().last().cloned()).unwrap();
+    let mut stmt = db.prepare(&format!("EXPLAIN QUERY PLAN {sql}")).unwrap();
+    let plan = stmt
+        .query_map([], |x| x.get::<_, String>("detail"))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    plan
+}
+
+#[test]
+fn read_stale_uses_checked_at_index() {
+    let mut db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+
+    let plan = last_query_plan(&mut db, |db| {
+        feeds::read_stale(db, Utc::now().into()).unwrap();
+    });
+    assert!(
+        plan.iter().any(|x| x.contains("idx_feeds_checked_at")),
+        "{plan:?}"
+    );
+}
+
+#[test]
+fn read_stale_picks_up_feeds_checked_in_the_future_again() {
+    let db = db();
+    feeds::create(&db, &feed("Touched", "https://example.com/1")).unwrap();
+    feeds::create(&db, &feed("Updated", "https://example.com/2")).unwrap();
+
+    let future = DateTime::parse_from_rfc3339("2099-01-01T00:00:00Z").unwrap();
+    feeds::touch_checked_at(&db, 1, future).unwrap();
+    feeds::update(&db, &FeedToUpdate::for_id(2).checked_at(future).build()).unwrap();
+
+    for feed in feeds::read_all(&db).unwrap() {
+        assert!(feed.checked_at <= Utc::now(), "{feed:?}");
+    }
+    let stale = feeds::read_stale(&db, (Utc::now() + Duration::days(1)).into())
+        .unwrap()
+        .into_iter()
+        .map(|x| x.title)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["Touched", "Updated"], stale);
+}
+
+#[test]
+fn read_recent_returns_the_last_checked_subscribed_feeds() {
+    let mut db = db();
+    for (title, link) in [
+        ("never checked", "https://example.com/1"),
+        ("old", "https://example.com/2"),
+        ("unsubscribed", "https://example.com/3"),
+        ("new", "https://example.com/4"),
+        ("newer", "https://example.com/5"),
+    ] {
+        feeds::create(&db, &feed(title, link)).unwrap();
+    }
+    let now = Utc::now();
+    for (id, minutes_ago) in [(2, 30), (3, 1), (4, 20), (5, 10)] {
+        feeds::touch_checked_at(&db, id, (now - Duration::minutes(minutes_ago)).into()).unwrap();
+    }
+    feeds::unsubscribe(&db, 3).unwrap();
+
+    let titles = |limit| {
+        feeds::read_recent(&db, limit)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.title)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["newer", "new"], titles(2));
+    assert_eq!(vec!["newer", "new", "old"], titles(10));
+
+    let plan = last_query_plan(&mut db, |db| {
+        feeds::read_recent(db, 2).unwrap();
+    });
+    assert!(
+        plan.iter().any(|x| x.contains("idx_feeds_checked_at")),
+        "{plan:?}"
+    );
+    assert!(!plan.iter().any(|x| x.contains("TEMP B-TREE")), "{plan:?}");
+}
+
+#[test]
+fn create_returns_the_stored_feed() {
+    let db = db();
+    feeds::create(&db, &feed("First", "https://example.com/first")).unwrap();
+
+    let created = feeds::create(&db, &feed("Second", "https://Example.com/second/")).unwrap();
+    assert_eq!(
+        (
+            2,
+            "Second".to_string(),
+            "https://example.com/second".to_string()
+        ),
+        (created.id, created.title, created.link),
+    );
+    assert_eq!(FeedStatus::Subscribed, created.status);
+}
+
+#[test]
+fn create_validates_title_and_link() {
+    let db = db();
+
+    for arg in [
+        feed("  ", "https://example.com/feed"),
+        feed("Example", "example.com/feed"),
+        feed("Example", "ftp://example.com/feed"),
+        feed("Example", "https://"),
+    ] {
+        assert!(matches!(
+            feeds::create(&db, &arg).unwrap_err(),
+            Error::InvalidFeed(_)
+        ));
+    }
+    assert_eq!(feeds::count(&db).unwrap(), 0);
+}
+
+#[test]
+fn timestamps_are_stored_in_utc() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+
+    let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+    let checked_at = DateTime::parse_from_rfc3339("2023-08-01T09:30:00.250+09:00").unwrap();
+    feeds::update(
+        &db,
+        &FeedToUpdate::for_id(1)
+            .checked_at(checked_at.with_timezone(&tokyo))
+            .build(),
+    )
+    .unwrap();
+
+    let stored: String = db
+        .query_row("SELECT checked_at FROM feeds WHERE id = 1", [], |x| {
+            x.get(0)
+        })
+        .unwrap();
+    assert_eq!("2023-08-01T00:30:00.250Z", stored);
+    assert_eq!(checked_at, feeds::read_required(&db, 1).unwrap().checked_at);
+}
+
+#[test]
+fn upsert_by_link_returns_existing_feeds() {
+    let db = db();
+    let created = feeds::upsert_by_link(&db, &feed("A", "https://a.example.com/feed")).unwrap();
+    let again =
+        feeds::upsert_by_link(&db, &feed("A again", "HTTPS://A.example.com/feed/")).unwrap();
+    assert_eq!((created.id, "A".to_string()), (again.id, again.title));
+    assert_eq!(1, feeds::count(&db).unwrap());
+
+    feeds::delete(&db, created.id).unwrap();
+    let restored = feeds::upsert_by_link(&db, &feed("A", "https://a.example.com/feed")).unwrap();
+    assert_eq!(created.id, restored.id);
+    assert!(restored.deleted_at.is_none());
+
+    let other = feeds::upsert_by_link(&db, &feed("B", "https://b.example.com/feed")).unwrap();
+    assert_ne!(created.id, other.id);
+    assert_eq!(1, other.position);
+}
+
+#[test]
+fn read_all_skips_unsubscribed_feeds() {
+    let db = db();
+    feeds::create_many(
+        &db,
+        &[
+            feed("A", "https://a.example.com/feed"),
+            feed("B", "https://b.example.com/feed"),
+            feed("C", "https://c.example.com/feed"),
+        ],
+    )
+    .unwrap();
+    feeds::unsubscribe(&db, 2).unwrap();
+
+    let ids = |feeds: Vec<feeds::Feed>| feeds.into_iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(vec![1, 3], ids(feeds::read_all(&db).unwrap()));
+    assert_eq!(
+        vec![2],
+        ids(feeds::read_by_status(&db, &FeedStatus::Unsubscribed).unwrap())
+    );
+    assert_eq!(
+        vec![1, 2, 3],
+        ids(feeds::read_including_unsubscribed(&db).unwrap())
+    );
+}
+
+#[test]
+fn find_duplicates_groups_feeds_by_normalized_link() {
+    let db = db();
+    feeds::create_many(
+        &db,
+        &[
+            feed("A", "https://a.example.com/feed"),
+            feed("B", "https://b.example.com/feed"),
+            feed("A again", "https://a.example.com/old"),
+            feed("C", "https://c.example.com/feed"),
+            feed("A once more", "https://a.example.com/older"),
+            feed("B again", "https://b.example.com/feed?page=2"),
+        ],
+    )
+    .unwrap();
+    // Stored before links were normalized on the way in.
+    db.execute_batch(
+        "UPDATE feeds SET link = 'https://A.example.com/feed/?utm_source=x' WHERE id = 3;
+        UPDATE feeds SET link = 'http://a.example.com:80/feed' WHERE id = 5;",
+    )
+    .unwrap();
+    feeds::unsubscribe(&db, 5).unwrap();
+
+    let groups = feeds::find_duplicates(&db)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.into_iter().map(|x| x.id).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![vec![1, 3, 5]], groups);
+}
+
+#[test]
+fn feed_status_serializes_as_it_is_stored() {
+    for status in [FeedStatus::Subscribed, FeedStatus::Unsubscribed] {
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(format!("\"{status}\""), json);
+        assert_eq!(status, serde_json::from_str::<FeedStatus>(&json).unwrap());
+        assert_eq!(status, status.to_string().parse::<FeedStatus>().unwrap());
+    }
+    assert_eq!(
+        FeedStatus::Unsubscribed,
+        serde_json::from_str::<FeedStatus>("\"Unsubscribed\"").unwrap()
+    );
+
+    let db = db();
+    feeds::create(&db, &feed("Feed", "https://example.com/feed")).unwrap();
+    let update: FeedToUpdate =
+        serde_json::from_str(r#"{"id": 1, "status": "unsubscribed"}"#).unwrap();
+    feeds::update(&db, &update).unwrap();
+    let stored: String = db
+        .query_row("SELECT status FROM feeds WHERE id = 1", [], |x| x.get(0))
+        .unwrap();
+    assert_eq!("unsubscribed", stored);
+    assert_eq!(
+        FeedStatus::Unsubscribed,
+        feeds::read_required(&db, 1).unwrap().status
+    );
+}
+
+#[test]
+fn count_by_status_skips_unknown_statuses() {
+    let db = db();
+    feeds::create_many(
+        &db,
+        &[
+            feed("A", "https://a.example.com/feed"),
+            feed("B", "https://b.example.com/feed"),
+            feed("C", "https://c.example.com/feed"),
+            feed("D", "https://d.example.com/feed"),
+        ],
+    )
+    .unwrap();
+    feeds::unsubscribe(&db, 2).unwrap();
+    feeds::delete(&db, 3).unwrap();
+    // Written by some other version, past the CHECK constraint.
+    db.execute_batch(
+        "PRAGMA ignore_check_constraints = ON;
+        UPDATE feeds SET status = 'paused' WHERE id = 4;",
+    )
+    .unwrap();
+
+    assert_eq!(
+        HashMap::from([(FeedStatus::Subscribed, 1), (FeedStatus::Unsubscribed, 1)]),
+        feeds::count_by_status(&db).unwrap()
+    );
+}
+
+#[test]
+fn edited_feeds_convert_to_updates() {
+    let db = db();
+    let stored = feeds::create(&db, &feed("Feed", "https://example.com/feed")).unwrap();
+
+    let update = FeedToUpdate::from(feeds::read_required(&db, stored.id).unwrap());
+    assert_eq!(Some(""), update.title.as_deref());
+    assert_eq!(Some("https://example.com/feed"), update.link.as_deref());
+    assert_eq!(Some(FeedStatus::Subscribed), update.status);
+    assert!(update.checked_at.is_none() && update.remote_title.is_none());
+
+    let mut edited = feeds::read_required(&db, stored.id).unwrap();
+    assert!(edited.edits_from(&stored).is_none());
+    edited.user_title = Some("Mine".to_string());
+    edited.fetch_interval_minutes = 30;
+    edited.checked_at = Utc::now().fixed_offset();
+
+    let update = edited.edits_from(&stored).unwrap();
+    assert_eq!(Some("Mine"), update.title.as_deref());
+    assert_eq!(Some(30), update.fetch_interval_minutes);
+    assert!(update.link.is_none() && update.status.is_none() && update.checked_at.is_none());
+
+    feeds::update(&db, &update).unwrap();
+    let saved = feeds::read_required(&db, stored.id).unwrap();
+    assert_eq!("Mine", saved.display_title());
+    assert_eq!(stored.checked_at, saved.checked_at);
+}
+
+#[test]
+fn diff_only_updates_what_changed() {
+    let db = db();
+    let feed = feeds::create(&db, &feed("Feed", "https://example.com/feed")).unwrap();
+    feeds::update(
+        &db,
+        &FeedToUpdate::for_id(feed.id)
+            .html_url("https://example.com/")
+            .content_type("application/rss+xml")
+            .build(),
+    )
+    .unwrap();
+    let feed = feeds::read_required(&db, feed.id).unwrap();
+
+    let unchanged = FeedMetadata {
+        title: " Feed ",
+        html_url: Some("https://example.com/"),
+        content_type: Some("application/rss+xml"),
+    };
+    assert!(feed.diff(&unchanged).is_none());
+    assert!(feed
+        .diff(&FeedMetadata {
+            title: "",
+            html_url: None,
+            content_type: None,
+        })
+        .is_none());
+
+    let update = feed
+        .diff(&FeedMetadata {
+            title: "Renamed",
+            ..unchanged
+        })
+        .unwrap();
+    assert_eq!(Some("Renamed".to_string()), update.remote_title);
+    assert!(update.html_url.is_none() && update.content_type.is_none());
+
+    feeds::update(&db, &update).unwrap();
+    let feed = feeds::read_required(&db, feed.id).unwrap();
+    assert_eq!("Renamed", feed.title);
+}
+
+#[test]
+fn unsubscribe_and_subscribe_toggle_status() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+    assert!(feeds::is_subscribed(&db, 1).unwrap());
+
+    assert_eq!(1, feeds::unsubscribe(&db, 1).unwrap());
+    assert!(!feeds::is_subscribed(&db, 1).unwrap());
+    assert_eq!(1, feeds::subscribe(&db, 1).unwrap());
+    assert!(feeds::is_subscribed(&db, 1).unwrap());
+
+    assert!(matches!(
+        feeds::unsubscribe(&db, 2),
+        Err(Error::NotFound(2))
+    ));
+    assert!(matches!(
+        feeds::is_subscribed(&db, 2),
+        Err(Error::NotFound(2))
+    ));
+}
+
+#[test]
+fn touch_checked_at_sets_only_checked_at() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+    let before = feeds::read_required(&db, 1).unwrap();
+
+    let when = DateTime::parse_from_rfc3339("2023-08-01T09:30:00+09:00").unwrap();
+    assert_eq!(1, feeds::touch_checked_at(&db, 1, when).unwrap());
+    assert_eq!(0, feeds::touch_checked_at(&db, 2, when).unwrap());
+
+    let after = feeds::read_required(&db, 1).unwrap();
+    assert_eq!(when, after.checked_at);
+    assert_eq!(
+        (before.title, before.link, before.fetch_interval_minutes),
+        (after.title, after.link, after.fetch_interval_minutes)
+    );
+}
+
+#[test]
+fn timestamps_in_older_shapes_are_still_read() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+
+    for (stored, expected) in [
+        ("2023-08-01 09:30:00.5+09:00", "2023-08-01T00:30:00.500Z"),
+        ("2023-08-01 00:30:00", "2023-08-01T00:30:00.000Z"),
+    ] {
+        db.execute("UPDATE feeds SET checked_at = ?", [stored])
+            .unwrap();
+        let checked_at = feeds::read_required(&db, 1).unwrap().checked_at;
+        assert_eq!(expected, database::format_timestamp(&checked_at));
+    }
+
+    db.execute("UPDATE feeds SET checked_at = 'yesterday'", [])
+        .unwrap();
+    assert!(feeds::read(&db, 1).is_err());
+}
+
+#[test]
+fn reorder_shifts_the_feeds_in_between() {
+    let db = db();
+    feeds::create_many(
+        &db,
+        &[
+            feed("A", "https://a.example.com/feed"),
+            feed("B", "https://b.example.com/feed"),
+            feed("C", "https://c.example.com/feed"),
+            feed("D", "https://d.example.com/feed"),
+        ],
+    )
+    .unwrap();
+    let order = || {
+        feeds::read_all(&db)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.id, x.position))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![(1, 0), (2, 1), (3, 2), (4, 3)], order());
+
+    feeds::reorder(&db, 4, 1).unwrap();
+    assert_eq!(vec![(1, 0), (4, 1), (2, 2), (3, 3)], order());
+
+    feeds::reorder(&db, 1, 2).unwrap();
+    assert_eq!(vec![(4, 0), (2, 1), (1, 2), (3, 3)], order());
+
+    feeds::reorder(&db, 4, 99).unwrap();
+    assert_eq!(vec![(2, 0), (1, 1), (3, 2), (4, 3)], order());
+
+    feeds::purge(&db, 1).unwrap();
+    feeds::create(&db, &feed("E", "https://e.example.com/feed")).unwrap();
+    assert_eq!(vec![(2, 0), (3, 1), (4, 2), (5, 3)], order());
+
+    assert!(matches!(feeds::reorder(&db, 1, 0), Err(Error::NotFound(1))));
+}
+
+#[test]
+fn update_sets_several_fields_at_once() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+    feeds::unsubscribe(&db, 1).unwrap();
+    let checked_at = DateTime::parse_from_rfc3339("2023-08-01T12:00:00Z").unwrap();
+
+    let updated = feeds::update(
+        &db,
+        &FeedToUpdate::for_id(1)
+            .title("Renamed")
+            .status(FeedStatus::Subscribed)
+            .checked_at(checked_at)
+            .build(),
+    )
+    .unwrap();
+
+    assert_eq!(1, updated);
+    let feed = feeds::read_required(&db, 1).unwrap();
+    assert_eq!(
+        (Some("Renamed"), FeedStatus::Subscribed, checked_at),
+        (feed.user_title.as_deref(), feed.status, feed.checked_at)
+    );
+}
+
+#[test]
+fn update_with_rolls_back_with_the_callers_transaction() {
+    let db = db();
+    feeds::create(&db, &feed("First", "https://example.com/1")).unwrap();
+    feeds::create(&db, &feed("Second", "https://example.com/2")).unwrap();
+
+    let tx = db.unchecked_transaction().unwrap();
+    feeds::update_with(&tx, &FeedToUpdate::for_id(1).title("Renamed").build()).unwrap();
+    let err = feeds::update_with(&tx, &FeedToUpdate::for_id(3).title("Missing").build());
+    assert!(matches!(err, Err(Error::NotFound(3))));
+    drop(tx);
+
+    assert_eq!(
+        "First",
+        feeds::read_required(&db, 1).unwrap().display_title()
+    );
+}
+
+#[test]
+fn update_detects_no_op_and_missing_feeds() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+
+    let update = |id, title: Option<&str>| {
+        feeds::update(
+            &db,
+            &FeedToUpdate {
+                id,
+                title: title.map(str::to_string),
+                link: None,
+                status: None,
+                checked_at: None,
+                fetch_interval_minutes: None,
+                html_url: None,
+                remote_title: None,
+                content_type: None,
+            },
+        )
+    };
+
+    assert_eq!(0, update(1, None).unwrap());
+    assert_eq!(1, update(1, Some("Renamed")).unwrap());
+    assert!(matches!(update(2, None), Err(Error::NotFound(2))));
+    assert!(matches!(
+        update(2, Some("Missing")),
+        Err(Error::NotFound(2))
+    ));
+}
+
+#[test]
+fn read_sorted_ignores_case_and_accents() {
+    let db = db();
+    for (title, link) in [
+        ("Zebra", "https://example.com/zebra"),
+        ("Öko", "https://example.com/oko"),
+        ("apple", "https://example.com/apple"),
+        ("Banana", "https://example.com/banana"),
+    ] {
+        feeds::create(&db, &feed(title, link)).unwrap();
+    }
+    feeds::update(&db, &FeedToUpdate::for_id(4).title("élan").build()).unwrap();
+
+    let titles = |sort| {
+        feeds::read_sorted(&db, &sort)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.display_title().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        vec!["apple", "élan", "Öko", "Zebra"],
+        titles(FeedSort::TitleAsc)
+    );
+    assert_eq!(
+        vec!["Zebra", "Öko", "élan", "apple"],
+        titles(FeedSort::TitleDesc)
+    );
+}
+
+#[test]
+fn updates_bump_updated_at_but_not_created_at() {
+    let db = db();
+    let before = Utc::now() - Duration::seconds(1);
+    let created = feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+    assert!(created.created_at > before);
+    assert_eq!(created.created_at, created.updated_at);
+
+    db.execute(
+        "UPDATE feeds SET created_at = '2020-01-01T00:00:00.000Z', updated_at = created_at",
+        [],
+    )
+    .unwrap();
+    feeds::update(&db, &FeedToUpdate::for_id(1).title("Renamed").build()).unwrap();
+
+    let updated = feeds::read_required(&db, 1).unwrap();
+    assert_eq!("2020-01-01T00:00:00+00:00", updated.created_at.to_rfc3339());
+    assert!(updated.updated_at > before);
+}
+
+#[test]
+fn read_sorted_puts_recently_added_first() {
+    let db = db();
+    for (title, link) in [
+        ("old", "https://example.com/1"),
+        ("new", "https://example.com/2"),
+        ("also new", "https://example.com/3"),
+    ] {
+        feeds::create(&db, &feed(title, link)).unwrap();
+    }
+    db.execute(
+        "UPDATE feeds SET created_at = '2020-01-01T00:00:00.000Z' WHERE id = 1",
+        [],
+    )
+    .unwrap();
+
+    let titles = feeds::read_sorted(&db, &FeedSort::CreatedAtDesc)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.title)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["also new", "new", "old"], titles);
+}
+
+#[test]
+fn find_by_title_prefix_escapes_wildcards() {
+    let db = db();
+    for (title, link) in [
+        ("rust blog", "https://example.com/1"),
+        ("Rust Weekly", "https://example.com/2"),
+        ("100% Rust", "https://example.com/3"),
+        ("100 Days", "https://example.com/4"),
+        ("a_b", "https://example.com/5"),
+        ("axb", "https://example.com/6"),
+    ] {
+        feeds::create(&db, &feed(title, link)).unwrap();
+    }
+
+    let titles = |prefix, limit| {
+        feeds::find_by_title_prefix(&db, prefix, limit)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.title)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["rust blog", "Rust Weekly"], titles("RUST", 10));
+    assert_eq!(vec!["rust blog"], titles("rust", 1));
+    assert_eq!(vec!["100% Rust"], titles("100%", 10));
+    assert_eq!(vec!["a_b"], titles("a_", 10));
+}
+
+#[test]
+fn bulk_operations_change_every_feed_in_one_go() {
+    let db = db();
+    for i in 1..=3 {
+        feeds::create(&db, &feed("Feed", &format!("https://example.com/{i}"))).unwrap();
+    }
+    feeds::unsubscribe(&db, 3).unwrap();
+
+    assert_eq!(0, feeds::bulk_unsubscribe(&db, &[]).unwrap());
+    // More ids than SQLite lets a single statement bind, with the missing ones skipped.
+    let ids = (1..=40_000).collect::<Vec<_>>();
+    assert_eq!(2, feeds::bulk_unsubscribe(&db, &ids).unwrap());
+    assert!(feeds::read_all(&db).unwrap().is_empty());
+    assert_eq!(3, feeds::read_including_unsubscribed(&db).unwrap().len());
+
+    assert_eq!(2, feeds::bulk_delete(&db, &[1, 3, 42]).unwrap());
+    assert_eq!(0, feeds::bulk_delete(&db, &[1, 3]).unwrap());
+    assert_eq!(2, feeds::read_archived(&db).unwrap().len());
+}
+
+#[test]
+fn feed_payloads_carry_the_schema_version() {
+    let db = db();
+    feeds::create(&db, &feed("Feed", "https://example.com/feed")).unwrap();
+
+    let json = serde_json::to_value(FeedPayload::new(feeds::read_all(&db).unwrap())).unwrap();
+    assert_eq!(3, FEED_SCHEMA_VERSION);
+    assert_eq!(FEED_SCHEMA_VERSION, json["schema_version"]);
+
+    // The shape of schema version 3. Changing it means bumping `FEED_SCHEMA_VERSION`.
+    let mut fields = json["data"][0]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    fields.sort();
+    assert_eq!(
+        vec![
+            "checked_at",
+            "consecutive_failures",
+            "content_type",
+            "created_at",
+            "deleted_at",
+            "etag",
+            "fetch_interval_minutes",
+            "headers",
+            "html_url",
+            "hub_url",
+            "id",
+            "is_muted",
+            "last_error",
+            "last_modified",
+            "last_notified_at",
+            "last_notified_item_id",
+            "last_read_item_id",
+            "link",
+            "max_items_per_fetch",
+            "notify_on_new",
+            "position",
+            "retry_after",
+            "status",
+            "title",
+            "topic_url",
+            "updated_at",
+            "user_title",
+        ],
+        fields
+    );
+
+    let json = serde_json::to_value(FeedPayload::new(feeds::read(&db, 2).unwrap())).unwrap();
+    assert_eq!(
+        serde_json::json!({ "schema_version": 3, "data": null }),
+        json
+    );
+}
+
+#[test]
+fn feed_commands_send_payloads() {
+    use crate::commands::{feeds as commands, tags as tag_commands};
+    use crate::error::Result;
+    use crate::models::feeds::{Feed, FeedSummary};
+    use crate::DbState;
+    use tauri::State;
+
+    type Sent<T> = Result<FeedPayload<T>>;
+    type Db<'a> = State<'a, DbState>;
+
+    // Every command that sends feeds wraps them, as these signatures pin down.
+    let _: fn(Db, FeedToCreate) -> Sent<Feed> = commands::create_feed;
+    let _: fn(Db) -> Sent<Vec<FeedSummary>> = commands::read_sidebar;
+    let _: fn(Db) -> Sent<Vec<Feed>> = commands::read_all_feeds;
+    let _: fn(Db) -> Sent<Vec<Feed>> = commands::read_feeds_including_unsubscribed;
+    let _: fn(Db, FeedStatus) -> Sent<Vec<Feed>> = commands::read_feeds_by_status;
+    let _: fn(Db) -> Sent<Vec<Vec<Feed>>> = commands::find_duplicate_feeds;
+    let _: fn(Db, i32) -> Sent<Option<Feed>> = commands::read_feed;
+    let _: fn(Db) -> Sent<Vec<Feed>> = commands::read_archived_feeds;
+    let _: fn(Db, i32) -> Sent<Vec<Feed>> = commands::read_unhealthy_feeds;
+    let _: fn(Db, u64) -> Sent<Vec<Feed>> = commands::read_recent_feeds;
+    let _: fn(Db, String) -> Sent<Vec<Feed>> = commands::search_feeds;
+    let _: fn(Db, String, u64) -> Sent<Vec<Feed>> = commands::search_feeds_by_title;
+    let _: fn(Db, String) -> Sent<Vec<Feed>> = tag_commands::read_feeds_by_tag;
+
+    let db = db();
+    let created = crate::producer::subscribe_by_url(
+        &db,
+        "http://fixtures.test/hnrss-org-frontpage.rss",
+        None,
+    )
+    .unwrap();
+    let json = serde_json::to_value(FeedPayload::new(created)).unwrap();
+    assert_eq!(FEED_SCHEMA_VERSION, json["schema_version"]);
+    assert_eq!(1, json["data"]["id"]);
+
+    let json = serde_json::to_value(FeedPayload::new(feeds::read_sidebar(&db).unwrap())).unwrap();
+    assert_eq!(FEED_SCHEMA_VERSION, json["schema_version"]);
+    assert_eq!(1, json["data"][0]["id"]);
+    assert_eq!(0, json["data"][0]["unread_count"]);
+}
+
+#[test]
+fn update_normalizes_and_checks_links() {
+    let db = db();
+    feeds::create(&db, &feed("A", "https://example.com/a")).unwrap();
+    feeds::create(&db, &feed("B", "https://example.com/b")).unwrap();
+
+    feeds::update(
+        &db,
+        &FeedToUpdate::for_id(1)
+            .link("HTTPS://Example.COM/c/?utm_source=x".to_string())
+            .build(),
+    )
+    .unwrap();
+    assert_eq!(
+        "https://example.com/c",
+        feeds::read_required(&db, 1).unwrap().link
+    );
+
+    let update =
+        |link: &str| feeds::update(&db, &FeedToUpdate::for_id(1).link(link.to_string()).build());
+    assert!(matches!(
+        update("https://EXAMPLE.com/b/"),
+        Err(Error::AlreadyExists(2))
+    ));
+    assert!(matches!(
+        update("ftp://example.com/d"),
+        Err(Error::InvalidFeed(_))
+    ));
+    assert!(update("https://example.com/c").is_ok());
+    assert_eq!(
+        "https://example.com/c",
+        feeds::read_required(&db, 1).unwrap().link
+    );
+}
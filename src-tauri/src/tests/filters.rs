@@ -0,0 +1,58 @@
+use pretty_assertions::assert_eq;
+
+use crate::error::Error;
+use crate::models::{
+    feeds,
+    filters::{self, FilterAction, FilterRule, FilterRuleToCreate, FilterRuleToUpdate},
+};
+
+use super::{db, feed};
+
+fn rule(pattern: &str, action: FilterAction, feed: Option<i32>) -> FilterRuleToCreate {
+    FilterRuleToCreate {
+        pattern: pattern.to_string(),
+        action,
+        feed,
+    }
+}
+
+#[test]
+fn filter_rules_can_be_created_updated_and_deleted() {
+    let db = db();
+    feeds::create(&db, &feed("A", "https://example.com/a")).unwrap();
+
+    let id = filters::create(&db, &rule(" Sponsored ", FilterAction::Hide, None)).unwrap();
+    filters::create(&db, &rule("ads", FilterAction::MarkRead, Some(1))).unwrap();
+    assert_eq!(
+        FilterRule {
+            id,
+            pattern: "Sponsored".to_string(),
+            action: FilterAction::Hide,
+            feed: None,
+        },
+        filters::read_all(&db).unwrap()[0]
+    );
+    assert_eq!(2, filters::read_for_feed(&db, 1).unwrap().len());
+    assert_eq!(1, filters::read_for_feed(&db, 2).unwrap().len());
+
+    filters::update(
+        &db,
+        &FilterRuleToUpdate {
+            id,
+            pattern: "promoted".to_string(),
+            action: FilterAction::MarkRead,
+            feed: Some(1),
+        },
+    )
+    .unwrap();
+    assert_eq!(0, filters::read_for_feed(&db, 2).unwrap().len());
+
+    assert!(matches!(
+        filters::create(&db, &rule("  ", FilterAction::Hide, None)),
+        Err(Error::InvalidValue(_))
+    ));
+
+    assert_eq!(1, filters::delete(&db, id).unwrap());
+    feeds::purge(&db, 1).unwrap();
+    assert!(filters::read_all(&db).unwrap().is_empty());
+}
@@ -0,0 +1,457 @@
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use pretty_assertions::assert_eq;
+use rusqlite::Connection;
+use std::cell::RefCell;
+
+use crate::error::Error;
+use crate::models::{
+    feeds,
+    items::{self, ItemOrder, ItemReadOption, ItemStatus, ItemToCreate, TimelineCursor},
+    tags,
+};
+
+use super::{create_feed, create_item, db, item};
+
+fn create_item_at(db: &Connection, feed: i32, title: &str, published_at: DateTime<FixedOffset>) {
+    items::create(
+        db,
+        &ItemToCreate {
+            published_at,
+            ..item(feed, title)
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn purge_removes_the_feeds_items() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    create_item(&db, 1, "first");
+    create_item(&db, 1, "second");
+    create_item(&db, 2, "third");
+    tags::add_tag(&db, 1, "News").unwrap();
+
+    assert_eq!(4, feeds::purge(&db, 1).unwrap());
+    let remaining: Vec<i32> = db
+        .prepare("SELECT feed FROM items")
+        .unwrap()
+        .query_map([], |x| x.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(vec![2], remaining);
+    assert!(tags::read_tags(&db, 1).unwrap().is_empty());
+    assert_eq!(0, feeds::purge(&db, 1).unwrap());
+}
+
+#[test]
+fn merge_moves_items_and_keeps_their_state() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    create_item(&db, 1, "shared");
+    create_item(&db, 2, "only-in-b");
+    create_item(&db, 2, "shared");
+    // The same item, under the other feed's link.
+    db.execute(
+        "UPDATE items SET link = 'https://example.com/1/shared' WHERE id = 3",
+        [],
+    )
+    .unwrap();
+    items::mark_read(&db, 3).unwrap();
+    items::toggle_star(&db, 3).unwrap();
+    tags::add_tag(&db, 2, "News").unwrap();
+
+    assert_eq!(1, feeds::merge(&db, 1, 2).unwrap());
+    assert!(feeds::read(&db, 2).unwrap().is_none());
+    assert!(tags::read_tags(&db, 2).unwrap().is_empty());
+
+    let merged = items::read_all(
+        &db,
+        &items::ItemReadOption {
+            feed: Some(1),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(vec![1, 2], merged.iter().map(|x| x.id).collect::<Vec<_>>());
+    assert!(matches!(merged[0].status, ItemStatus::Read));
+    assert!(merged[0].is_saved);
+    assert!(matches!(merged[1].status, ItemStatus::Unread));
+
+    assert!(matches!(feeds::merge(&db, 1, 2), Err(Error::NotFound(2))));
+    assert!(matches!(
+        feeds::merge(&db, 1, 1),
+        Err(Error::InvalidValue(_))
+    ));
+}
+
+#[test]
+fn read_sidebar_counts_unread_items_per_feed() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    create_feed(&db, "https://example.com/c");
+    create_item(&db, 1, "first");
+    create_item(&db, 1, "second");
+    create_item(&db, 1, "third");
+    create_item(&db, 3, "fourth");
+    items::mark_read(&db, 2).unwrap();
+    feeds::update_favicon(&db, 1, &[0], None).unwrap();
+    feeds::unsubscribe(&db, 3).unwrap();
+
+    let sidebar = feeds::read_sidebar(&db)
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.feed.id, x.unread_count, x.has_favicon))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(1, 2, true), (2, 0, false)], sidebar);
+}
+
+#[test]
+fn muted_feeds_are_left_out_of_the_total_unread_count() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    create_item(&db, 1, "first");
+    create_item(&db, 1, "second");
+    create_item(&db, 2, "third");
+
+    feeds::set_muted(&db, 1, true).unwrap();
+    assert!(feeds::read_required(&db, 1).unwrap().is_muted);
+    assert_eq!(1, items::unread_count(&db, None).unwrap());
+    assert_eq!(2, items::unread_count(&db, Some(1)).unwrap());
+
+    feeds::set_muted(&db, 1, false).unwrap();
+    assert_eq!(3, items::unread_count(&db, None).unwrap());
+    assert!(matches!(
+        feeds::set_muted(&db, 3, true),
+        Err(Error::NotFound(3))
+    ));
+}
+
+#[test]
+fn mark_read_and_unread_update_unread_counts() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    create_item(&db, 1, "first");
+    create_item(&db, 1, "second");
+    create_item(&db, 2, "third");
+
+    assert_eq!(3, items::unread_count(&db, None).unwrap());
+    assert_eq!(2, items::unread_count(&db, Some(1)).unwrap());
+
+    items::mark_read(&db, 1).unwrap();
+    assert_eq!(1, items::unread_count(&db, Some(1)).unwrap());
+
+    items::mark_unread(&db, 1).unwrap();
+    assert_eq!(2, items::unread_count(&db, Some(1)).unwrap());
+
+    items::mark_feed_read(&db, 1).unwrap();
+    assert_eq!(0, items::unread_count(&db, Some(1)).unwrap());
+    assert_eq!(1, items::unread_count(&db, None).unwrap());
+}
+
+#[test]
+fn toggle_star_flips_and_reports_state() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_item(&db, 1, "first");
+    create_item(&db, 1, "second");
+
+    assert!(items::toggle_star(&db, 2).unwrap());
+    assert_eq!(1, items::read_starred(&db).unwrap().len());
+
+    assert!(!items::toggle_star(&db, 2).unwrap());
+    assert!(items::read_starred(&db).unwrap().is_empty());
+
+    assert!(matches!(
+        items::toggle_star(&db, 42).unwrap_err(),
+        Error::NotFound(42)
+    ));
+}
+
+#[test]
+fn prune_keeps_latest_unread_and_saved_items() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    for title in ["1", "2", "3", "4", "5"] {
+        create_item(&db, 1, title);
+    }
+    create_item(&db, 2, "6");
+    for id in [1, 2, 3, 4, 6] {
+        items::mark_read(&db, id).unwrap();
+    }
+    items::toggle_star(&db, 2).unwrap();
+
+    assert_eq!(2, items::prune_items(&db, 1, 2).unwrap());
+    assert_eq!(3, count(&db, 1));
+    assert_eq!(1, count(&db, 2));
+
+    assert_eq!(2, items::prune_all(&db, 0).unwrap());
+    assert_eq!(2, count(&db, 1));
+    assert_eq!(0, count(&db, 2));
+}
+
+fn count(db: &Connection, feed: i32) -> i64 {
+    db.query_row("SELECT COUNT(*) FROM items WHERE feed = ?", [feed], |x| {
+        x.get(0)
+    })
+    .unwrap()
+}
+
+#[test]
+fn mark_read_in_bulk() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    for (feed, title) in [(1, "1"), (1, "2"), (2, "3"), (2, "4")] {
+        create_item(&db, feed, title);
+    }
+
+    assert_eq!(0, items::mark_read_bulk(&db, &[]).unwrap());
+    assert_eq!(2, items::mark_read_bulk(&db, &[1, 3, 42]).unwrap());
+    assert_eq!(2, items::unread_count(&db, None).unwrap());
+
+    assert_eq!(2, items::mark_all_read(&db).unwrap());
+    assert_eq!(0, items::unread_count(&db, None).unwrap());
+    assert_eq!(0, items::mark_all_read(&db).unwrap());
+}
+
+#[test]
+fn read_by_ids_keeps_the_requested_order() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    for title in ["1", "2", "3"] {
+        create_item(&db, 1, title);
+    }
+
+    let titles = |ids: &[i32]| {
+        items::read_by_ids(&db, ids)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.title)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["3", "1", "2"], titles(&[3, 1, 42, 2, 3]));
+    assert!(titles(&[]).is_empty());
+}
+
+#[test]
+fn read_items_between_includes_since_and_excludes_until() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    let at = |x| DateTime::parse_from_rfc3339(x).unwrap();
+    for (feed, title, published_at) in [
+        (1, "before", "2023-08-27T23:59:59+00:00"),
+        (1, "midnight", "2023-08-28T00:00:00+00:00"),
+        (2, "noon", "2023-08-28T21:00:00+09:00"),
+        (1, "evening", "2023-08-28T20:00:00+00:00"),
+        (1, "next day", "2023-08-29T00:00:00+00:00"),
+    ] {
+        create_item_at(&db, feed, title, at(published_at));
+    }
+
+    let titles = |feed| {
+        items::read_items_between(
+            &db,
+            feed,
+            at("2023-08-28T09:00:00+09:00"),
+            at("2023-08-29T00:00:00+00:00"),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|x| x.title)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["evening", "noon", "midnight"], titles(None));
+    assert_eq!(vec!["evening", "midnight"], titles(Some(1)));
+}
+
+#[test]
+fn future_publication_dates_are_clamped_to_now() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    let at = |x| DateTime::parse_from_rfc3339(x).unwrap();
+    // Stored dates keep milliseconds only.
+    let before = Utc::now() - Duration::milliseconds(1);
+    create_item_at(&db, 1, "future", at("2099-01-01T00:00:00+00:00"));
+    create_item_at(
+        &db,
+        1,
+        "soon",
+        Utc::now().fixed_offset() + Duration::minutes(5),
+    );
+
+    let item = items::read(&db, 1).unwrap().unwrap();
+    assert!(item.published_at >= before && item.published_at <= Utc::now());
+    let original: Option<String> = db
+        .query_row(
+            "SELECT original_published_at FROM items WHERE id = 1",
+            [],
+            |x| x.get(0),
+        )
+        .unwrap();
+    assert_eq!(Some("2099-01-01T00:00:00.000Z".to_string()), original);
+
+    assert!(items::read(&db, 2).unwrap().unwrap().published_at > Utc::now());
+}
+
+thread_local! {
+    static TRACED: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+}
+
+#[test]
+fn read_timeline_pages_through_every_feed_by_index() {
+    let mut db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    let at = |x| DateTime::parse_from_rfc3339(x).unwrap();
+    for (feed, title, published_at) in [
+        (1, "1", "2023-08-28T01:00:00Z"),
+        (2, "2", "2023-08-28T02:00:00Z"),
+        (1, "3", "2023-08-28T03:00:00Z"),
+        (2, "4", "2023-08-28T04:00:00Z"),
+    ] {
+        create_item_at(&db, feed, title, at(published_at));
+    }
+    items::mark_read(&db, 3).unwrap();
+    feeds::update_favicon(&db, 2, &[1, 2, 3], Some("image/png")).unwrap();
+
+    let page = |offset, unread_only| {
+        items::read_timeline(&db, offset, 2, unread_only)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.item.title, x.feed_favicon.is_some()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        vec![("4".to_string(), true), ("3".to_string(), false)],
+        page(0, false)
+    );
+    assert_eq!(
+        vec![("2".to_string(), true), ("1".to_string(), false)],
+        page(2, false)
+    );
+    assert_eq!(
+        vec![("4".to_string(), true), ("2".to_string(), true)],
+        page(0, true)
+    );
+
+    db.trace(Some(|sql| {
+        TRACED.with(|x| x.borrow_mut().push(sql.to_string()))
+    }));
+    items::read_timeline(&db, 0, 50, true).unwrap();
+    db.trace(None);
+
+    let sql = TRACED.with(|x| x.borrow().last().cloned()).unwrap();
+    let mut stmt = db.prepare(&format!("EXPLAIN QUERY PLAN {sql}")).unwrap();
+    let plan = stmt
+        .query_map([], |x| x.get::<_, String>("detail"))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(
+        plan.iter().any(|x| x.contains("idx_items_published_at")),
+        "{plan:?}"
+    );
+    assert!(!plan.iter().any(|x| x.contains("TEMP B-TREE")), "{plan:?}");
+}
+
+#[test]
+fn read_timeline_after_stays_put_when_items_arrive() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    let at = |x| DateTime::parse_from_rfc3339(x).unwrap();
+    for title in ["1", "2", "3", "4", "5"] {
+        create_item_at(&db, 1, title, at("2023-08-28T00:00:00Z"));
+    }
+
+    let page = |cursor: Option<&TimelineCursor>| {
+        let (page, next) = items::read_timeline_after(&db, cursor, 2, false).unwrap();
+        let titles = page.into_iter().map(|x| x.item.title).collect::<Vec<_>>();
+        (titles, next)
+    };
+    let (first, next) = page(None);
+    assert_eq!(vec!["5", "4"], first);
+
+    create_item_at(&db, 1, "new", at("2023-08-29T00:00:00Z"));
+    let (second, next) = page(next.as_ref());
+    assert_eq!(vec!["3", "2"], second);
+    let (third, next) = page(next.as_ref());
+    assert_eq!(vec!["1"], third);
+    assert_eq!(None, next);
+}
+
+#[test]
+fn for_each_visits_what_read_all_returns_until_an_error() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    for title in ["1", "2", "3"] {
+        create_item(&db, 1, title);
+    }
+    let opt = ItemReadOption {
+        order_by: Some(ItemOrder::ReceivedDateDesc),
+        ..Default::default()
+    };
+
+    let mut titles = vec![];
+    items::for_each(&db, &opt, |x| {
+        titles.push(x.title);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(vec!["3", "2", "1"], titles);
+
+    let mut visited = 0;
+    let result = items::for_each(&db, &opt, |_| {
+        visited += 1;
+        Err(Error::Unknown)
+    });
+    assert!(matches!(result, Err(Error::Unknown)));
+    assert_eq!(1, visited);
+}
+
+#[test]
+fn read_positions_are_forgotten_when_their_item_is_pruned() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    create_feed(&db, "https://example.com/b");
+    for title in ["1", "2", "3", "4", "5"] {
+        create_item(&db, 1, title);
+    }
+    create_item(&db, 2, "6");
+    for id in [1, 3] {
+        items::mark_read(&db, id).unwrap();
+    }
+
+    feeds::set_read_position(&db, 1, 3).unwrap();
+    feeds::set_read_position(&db, 2, 6).unwrap();
+    assert_eq!(
+        Some(3),
+        feeds::read_required(&db, 1).unwrap().last_read_item_id
+    );
+    assert!(matches!(
+        feeds::set_read_position(&db, 1, 6),
+        Err(Error::InvalidValue(_))
+    ));
+    assert!(matches!(
+        feeds::set_read_position(&db, 42, 6),
+        Err(Error::NotFound(42))
+    ));
+
+    assert_eq!(2, items::prune_items(&db, 1, 2).unwrap());
+    assert_eq!(
+        None,
+        feeds::read_required(&db, 1).unwrap().last_read_item_id
+    );
+    assert_eq!(
+        Some(6),
+        feeds::read_required(&db, 2).unwrap().last_read_item_id
+    );
+}
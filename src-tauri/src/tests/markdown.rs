@@ -0,0 +1,84 @@
+use chrono::DateTime;
+use pretty_assertions::assert_eq;
+use rusqlite::Connection;
+
+use crate::error::Error;
+use crate::markdown::{export_feed_markdown, html_to_markdown};
+use crate::models::{
+    feeds,
+    items::{self, ItemToCreate},
+};
+
+use super::{db, feed, item};
+
+fn create_item(db: &Connection, title: &str, description: &str, published_at: &str) {
+    items::create(
+        db,
+        &ItemToCreate {
+            description: description.to_string(),
+            link: format!("https://example.com/{title}"),
+            published_at: DateTime::parse_from_rfc3339(published_at).unwrap(),
+            ..item(1, title)
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn html_to_markdown_converts_common_elements() {
+    let html = r#"<h2>Intro</h2>
+<p>Some <strong>bold</strong> and <em>italic</em> text with <a href="https://example.com/?a=1&amp;b=2">a link</a>.</p>
+<ul><li>one</li><li><code>two</code></li></ul>
+<blockquote><p>Quoted</p></blockquote>
+<pre><code>let x = 1 &lt; 2;</code></pre>
+<p><img src="https://example.com/a.png" alt="A"><br>Tom &amp; Jerry<script>alert(1)</script></p>"#;
+
+    assert_eq!(
+        "## Intro
+
+Some **bold** and *italic* text with [a link](https://example.com/?a=1&b=2).
+
+- one
+- `two`
+
+> Quoted
+
+```
+let x = 1 < 2;
+```
+
+![A](https://example.com/a.png)
+Tom & Jerry",
+        html_to_markdown(html)
+    );
+}
+
+#[test]
+fn export_feed_markdown_orders_items_chronologically() {
+    let db = db();
+    feeds::create(&db, &feed("Blog", "https://example.com/feed")).unwrap();
+    create_item(&db, "second", "<p>Later</p>", "2023-02-01T00:00:00Z");
+    create_item(&db, "first", "", "2023-01-01T00:00:00Z");
+
+    assert_eq!(
+        "# Blog
+
+<https://example.com/feed>
+
+## [first](https://example.com/first)
+
+*2023-01-01*
+
+## [second](https://example.com/second)
+
+*2023-02-01*
+
+Later
+",
+        export_feed_markdown(&db, 1).unwrap()
+    );
+    assert!(matches!(
+        export_feed_markdown(&db, 2),
+        Err(Error::NotFound(2))
+    ));
+}
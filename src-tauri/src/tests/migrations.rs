@@ -0,0 +1,154 @@
+use pretty_assertions::assert_eq;
+use rusqlite::Connection;
+
+use crate::models::{database, migrations};
+
+#[test]
+fn run_applies_each_migration_once() {
+    let db = Connection::open_in_memory().unwrap();
+    assert_eq!(0, migrations::version(&db).unwrap());
+
+    migrations::run(&db).unwrap();
+    assert_eq!(
+        migrations::MIGRATIONS.len(),
+        migrations::version(&db).unwrap()
+    );
+
+    migrations::run(&db).unwrap();
+    assert_eq!(
+        migrations::MIGRATIONS.len(),
+        migrations::version(&db).unwrap()
+    );
+}
+
+#[test]
+fn run_adopts_unversioned_databases() {
+    let db = database::open_in_memory().unwrap();
+    db.pragma_update(None, "user_version", 0).unwrap();
+
+    migrations::run(&db).unwrap();
+    assert_eq!(
+        migrations::MIGRATIONS.len(),
+        migrations::version(&db).unwrap()
+    );
+}
+
+#[test]
+fn run_merges_feeds_sharing_a_link_before_indexing_links() {
+    // The version right before `create_lookup_indexes`.
+    let version = 6;
+    let db = Connection::open_in_memory().unwrap();
+    for migration in &migrations::MIGRATIONS[..version] {
+        migration(&db).unwrap();
+    }
+    db.pragma_update(None, "user_version", version).unwrap();
+    db.execute_batch(
+        "INSERT INTO feeds (title, link, checked_at) VALUES
+            ('First', 'https://example.com/feed', '2023-01-01 00:00:00+00:00'),
+            ('Second', 'https://example.com/feed', '2023-01-01 00:00:00+00:00');
+        INSERT INTO items (fingerprint, title, description, link, published_at, feed)
+            VALUES ('x', 'Item', '', 'https://example.com/item', '2023-01-01 00:00:00+00:00', 2);",
+    )
+    .unwrap();
+
+    migrations::run(&db).unwrap();
+
+    let feeds: i64 = db
+        .query_row("SELECT COUNT(*) FROM feeds", [], |x| x.get(0))
+        .unwrap();
+    let feed: i32 = db
+        .query_row("SELECT feed FROM items", [], |x| x.get(0))
+        .unwrap();
+    assert_eq!((1, 1), (feeds, feed));
+}
+
+#[test]
+fn run_rewrites_timestamps_in_utc() {
+    // The version right before `normalize_timestamps`.
+    let version = 8;
+    let db = Connection::open_in_memory().unwrap();
+    for migration in &migrations::MIGRATIONS[..version] {
+        migration(&db).unwrap();
+    }
+    db.pragma_update(None, "user_version", version).unwrap();
+    db.execute_batch(
+        "INSERT INTO feeds (title, link, checked_at) VALUES
+            ('Feed', 'https://example.com/feed', '2023-08-01 09:30:00.123456+09:00');",
+    )
+    .unwrap();
+
+    migrations::run(&db).unwrap();
+
+    let (checked_at, deleted_at): (String, Option<String>) = db
+        .query_row("SELECT checked_at, deleted_at FROM feeds", [], |x| {
+            Ok((x.get(0)?, x.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(
+        ("2023-08-01T00:30:00.123Z".to_string(), None),
+        (checked_at, deleted_at)
+    );
+}
+
+#[test]
+fn run_unsubscribes_feeds_with_unknown_statuses() {
+    // The version right before `normalize_feed_statuses`.
+    let version = 29;
+    let db = Connection::open_in_memory().unwrap();
+    for migration in &migrations::MIGRATIONS[..version] {
+        migration(&db).unwrap();
+    }
+    db.pragma_update(None, "user_version", version).unwrap();
+    db.execute_batch(
+        "INSERT INTO feeds (title, link, checked_at) VALUES
+            ('Kept', 'https://example.com/a', '2023-01-01T00:00:00.000Z'),
+            ('Paused', 'https://example.com/b', '2023-01-01T00:00:00.000Z');
+        PRAGMA ignore_check_constraints = ON;
+        UPDATE feeds SET status = 'paused' WHERE id = 2;
+        PRAGMA ignore_check_constraints = OFF;",
+    )
+    .unwrap();
+
+    migrations::run(&db).unwrap();
+
+    let statuses = db
+        .prepare("SELECT status FROM feeds ORDER BY id")
+        .unwrap()
+        .query_map([], |x| x.get::<_, String>(0))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(vec!["subscribed", "unsubscribed"], statuses);
+    assert!(db
+        .execute("UPDATE feeds SET status = 'paused' WHERE id = 1", [])
+        .is_err());
+}
+
+#[test]
+fn run_clamps_checks_in_the_future() {
+    // The version right before `clamp_feeds_checked_at`.
+    let version = 32;
+    let db = Connection::open_in_memory().unwrap();
+    for migration in &migrations::MIGRATIONS[..version] {
+        migration(&db).unwrap();
+    }
+    db.pragma_update(None, "user_version", version).unwrap();
+    db.execute_batch(
+        "INSERT INTO feeds (title, link, checked_at) VALUES
+            ('Past', 'https://example.com/a', '2023-01-01T00:00:00.000Z'),
+            ('Future', 'https://example.com/b', '2099-01-01T00:00:00.000Z');",
+    )
+    .unwrap();
+
+    migrations::run(&db).unwrap();
+
+    let checked_at = db
+        .prepare("SELECT checked_at FROM feeds ORDER BY id")
+        .unwrap()
+        .query_map([], |x| x.get::<_, String>(0))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!("2023-01-01T00:00:00.000Z", checked_at[0]);
+    assert!(checked_at[1].as_str() < "2099", "{checked_at:?}");
+}
@@ -0,0 +1,64 @@
+mod backup;
+mod bookmarks;
+mod database;
+mod feeds;
+mod filters;
+mod items;
+mod markdown;
+mod migrations;
+mod opml;
+mod producer;
+mod rate_limit;
+mod settings;
+mod syndication;
+mod tags;
+mod util;
+
+// Fixtures shared by the tests of every module. Each module's own fixtures stay with it.
+
+use chrono::Utc;
+use rusqlite::Connection;
+
+use crate::models::{
+    self,
+    feeds::FeedToCreate,
+    items::{ItemStatus, ItemToCreate},
+};
+
+fn db() -> Connection {
+    models::database::open_in_memory().unwrap()
+}
+
+fn feed(title: &str, link: &str) -> FeedToCreate {
+    FeedToCreate {
+        title: title.to_string(),
+        link: link.to_string(),
+    }
+}
+
+/// Stores a feed titled after its link and returns its id.
+fn create_feed(db: &Connection, link: &str) -> i32 {
+    models::feeds::create(db, &feed(link, link)).unwrap().id
+}
+
+/// An unread item of `feed` published now, with nothing but a title and a link made from it.
+/// Tests that care about other fields set them with struct update syntax.
+fn item(feed: i32, title: &str) -> ItemToCreate {
+    ItemToCreate {
+        guid: None,
+        author: None,
+        title: title.to_string(),
+        description: String::new(),
+        content_is_html: true,
+        summary: None,
+        link: format!("https://example.com/{feed}/{title}"),
+        status: ItemStatus::Unread,
+        is_hidden: false,
+        published_at: Utc::now().into(),
+        feed,
+    }
+}
+
+fn create_item(db: &Connection, feed: i32, title: &str) {
+    models::items::create(db, &item(feed, title)).unwrap();
+}
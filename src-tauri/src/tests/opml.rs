@@ -0,0 +1,91 @@
+use pretty_assertions::assert_eq;
+use std::fs;
+
+use crate::models::feeds::{self, FeedStatus, FeedToUpdate};
+use crate::opml;
+
+use super::db;
+
+fn fixture(path: &str) -> String {
+    fs::read_to_string(format!("src/tests/fixtures/{}", path)).unwrap()
+}
+
+#[test]
+fn parse_opml_flattens_outlines() {
+    let feeds = opml::parse_opml(&fixture("feedly.opml")).unwrap();
+    assert_eq!(
+        vec![
+            (
+                "Hacker News: Front Page".to_string(),
+                "https://hnrss.org/frontpage".to_string()
+            ),
+            (
+                "Rust Blog".to_string(),
+                "https://blog.rust-lang.org/feed.xml".to_string()
+            ),
+            (
+                "Tom & Jerry".to_string(),
+                "https://example.com/feed?a=1&b=2".to_string()
+            ),
+        ],
+        feeds
+            .into_iter()
+            .map(|x| (x.feed.title, x.feed.link))
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[test]
+fn import_opml_creates_feeds() {
+    let db = db();
+
+    let ids = opml::import_opml(&db, &fixture("feedly.opml")).unwrap();
+    assert_eq!(vec![1, 2, 3], ids);
+    assert_eq!(3, feeds::count(&db).unwrap());
+}
+
+#[test]
+fn parse_opml_rejects_malformed_xml() {
+    assert!(opml::parse_opml("<opml><body><outline xmlUrl=\"a></body>").is_err());
+}
+
+#[test]
+fn export_opml_round_trips_subscribed_feeds() {
+    let db = db();
+    opml::import_opml(&db, &fixture("feedly.opml")).unwrap();
+    feeds::update(
+        &db,
+        &FeedToUpdate::for_id(2)
+            .status(FeedStatus::Unsubscribed)
+            .build(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        Some("https://news.ycombinator.com/".to_string()),
+        feeds::read_required(&db, 1).unwrap().html_url
+    );
+
+    let xml = opml::export_opml(&db).unwrap();
+    assert!(xml.contains("<opml version=\"2.0\">"));
+    assert!(xml.contains(" htmlUrl=\"https://news.ycombinator.com/\""));
+    assert_eq!(
+        vec![
+            (
+                "Hacker News: Front Page".to_string(),
+                "https://hnrss.org/frontpage".to_string(),
+                Some("https://news.ycombinator.com/".to_string())
+            ),
+            (
+                "Tom & Jerry".to_string(),
+                "https://example.com/feed?a=1&b=2".to_string(),
+                None
+            ),
+        ],
+        opml::parse_opml(&xml)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.feed.title, x.feed.link, x.html_url))
+            .collect::<Vec<_>>(),
+    );
+}
@@ -0,0 +1,645 @@
+use chrono::{DateTime, Utc};
+use pretty_assertions::assert_eq;
+use std::{collections::HashMap, fs, sync::Mutex, time::Duration};
+
+use crate::error::Error;
+use crate::models::{
+    database,
+    feeds::{self, FeedToCreate, FeedToUpdate},
+    filters::{self, FilterAction, FilterRuleToCreate},
+    items::{self, ItemReadOption, ItemStatus},
+    settings::{self, SettingKey, SettingToUpdate},
+};
+use crate::producer::{self, RetryPolicy};
+
+use super::{create_feed, create_item, db, feed};
+
+fn fixture(path: &str) -> String {
+    format!("http://fixtures.test/{path}")
+}
+
+#[test]
+fn refresh_feed_stores_only_new_items() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+    assert_eq!(0, producer::refresh_feed(&db, 1, None).unwrap());
+    assert_eq!(
+        3,
+        items::count_all(&db, &ItemReadOption::default()).unwrap()
+    );
+}
+
+#[test]
+fn refresh_feed_reports_missing_feed() {
+    let db = db();
+    assert!(matches!(
+        producer::refresh_feed(&db, 1, None),
+        Err(Error::NotFound(1))
+    ));
+}
+
+#[test]
+fn refresh_feed_skips_items_republished_under_new_guids() {
+    let db = db();
+    feeds::create(&db, &feed("Republished", &fixture("republished.rss"))).unwrap();
+
+    assert_eq!(1, producer::refresh_feed(&db, 1, None).unwrap());
+    assert_eq!(
+        1,
+        items::count_all(&db, &ItemReadOption::default()).unwrap()
+    );
+}
+
+#[test]
+fn refresh_all_isolates_failing_feeds() {
+    let dir = std::env::temp_dir().join(format!("collie-refresh-all-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let pool = database::open_pool(&dir, 4).unwrap();
+
+    feeds::create_many(
+        &pool.get().unwrap(),
+        &[
+            feed("RSS", &fixture("hnrss-org-frontpage.rss")),
+            feed("Missing", &fixture("missing.rss")),
+        ],
+    )
+    .unwrap();
+
+    let progress = Mutex::new(vec![]);
+    let report =
+        producer::refresh_all(&pool, 2, None, |x| progress.lock().unwrap().push(x)).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(
+        (2, 1, 1, 3),
+        (
+            report.total,
+            report.succeeded,
+            report.failed,
+            report.new_items
+        )
+    );
+    assert_eq!(
+        vec![2],
+        report.errors.iter().map(|x| x.0).collect::<Vec<_>>()
+    );
+
+    let mut progress = progress.into_inner().unwrap();
+    assert_eq!(
+        vec![1, 2],
+        progress.iter().map(|x| x.completed).collect::<Vec<_>>()
+    );
+    progress.sort_by_key(|x| x.feed);
+    assert_eq!(
+        vec![(1, 3, false), (2, 0, true)],
+        progress
+            .iter()
+            .map(|x| (x.feed, x.new_items, x.error.is_some()))
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[test]
+fn refresh_feed_records_failures_until_it_succeeds() {
+    let db = db();
+    feeds::create(&db, &feed("Flaky", &fixture("flaky.rss"))).unwrap();
+
+    assert!(producer::refresh_feed(&db, 1, None).is_err());
+    assert!(producer::refresh_feed(&db, 1, None).is_err());
+    let failing = feeds::read_required(&db, 1).unwrap();
+    assert_eq!(2, failing.consecutive_failures);
+    assert!(failing.last_error.is_some());
+    assert!(feeds::read_unhealthy(&db, 3).unwrap().is_empty());
+    assert_eq!(
+        vec![1],
+        feeds::read_unhealthy(&db, 2)
+            .unwrap()
+            .iter()
+            .map(|x| x.id)
+            .collect::<Vec<_>>()
+    );
+
+    db.execute(
+        "UPDATE feeds SET link = ?",
+        [fixture("hnrss-org-frontpage.rss")],
+    )
+    .unwrap();
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+    let healthy = feeds::read_required(&db, 1).unwrap();
+    assert_eq!(
+        (0, None),
+        (healthy.consecutive_failures, healthy.last_error)
+    );
+    assert!(feeds::read_unhealthy(&db, 1).unwrap().is_empty());
+}
+
+#[test]
+fn refresh_feed_applies_filter_rules() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+    feeds::create(&db, &feed("Other", &fixture("formats.rss"))).unwrap();
+    for (pattern, action, feed) in [
+        ("LINUX MODULES", FilterAction::Hide, None),
+        ("smacker", FilterAction::MarkRead, Some(1)),
+        ("forever chemicals", FilterAction::Hide, Some(2)),
+    ] {
+        filters::create(
+            &db,
+            &FilterRuleToCreate {
+                pattern: pattern.to_string(),
+                action,
+                feed,
+            },
+        )
+        .unwrap();
+    }
+
+    assert_eq!(2, producer::refresh_feed(&db, 1, None).unwrap());
+
+    let visible = items::read_all(&db, &ItemReadOption::default()).unwrap();
+    assert_eq!(2, visible.len());
+    let smacker = visible
+        .iter()
+        .find(|x| x.title.contains("Smacker"))
+        .unwrap();
+    assert!(matches!(smacker.status, ItemStatus::Read));
+    assert_eq!(
+        3,
+        items::count_all(
+            &db,
+            &ItemReadOption {
+                include_hidden: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    );
+    assert_eq!(1, items::unread_count(&db, None).unwrap());
+}
+
+#[test]
+fn refresh_feed_keeps_plain_text_content_unescaped() {
+    let db = db();
+    feeds::create(&db, &feed("Content types", &fixture("content-types.atom"))).unwrap();
+    producer::refresh_feed(&db, 1, None).unwrap();
+
+    let items = items::read_all(&db, &ItemReadOption::default()).unwrap();
+    let item = |title: &str| items.iter().find(|x| x.title == title).unwrap();
+    assert!(!item("Text").content_is_html);
+    assert_eq!("Use <b> for bold.", item("Text").description);
+    assert!(item("HTML").content_is_html);
+    assert!(!item("Untyped").content_is_html);
+}
+
+#[test]
+fn refresh_feed_sends_the_feeds_headers() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Private", &fixture("hnrss-org-frontpage.rss?token=secret")),
+    )
+    .unwrap();
+    assert!(producer::refresh_feed(&db, 1, None).is_err());
+
+    feeds::set_headers(
+        &db,
+        1,
+        &HashMap::from([("Authorization".to_string(), "Bearer secret".to_string())]),
+    )
+    .unwrap();
+    let feed = feeds::read_required(&db, 1).unwrap();
+    assert!(!format!("{feed:?}").contains("Bearer"));
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+
+    assert!(matches!(
+        feeds::set_headers(
+            &db,
+            1,
+            &HashMap::from([("Not a name".to_string(), "x".to_string())]),
+        ),
+        Err(Error::InvalidValue(_))
+    ));
+}
+
+#[test]
+fn refresh_feed_retries_transient_failures() {
+    let db = db();
+    for link in [
+        "hnrss-org-frontpage.rss?fail=2",
+        "hnrss-org-frontpage.rss?fail=3",
+    ] {
+        feeds::create(&db, &feed(link, &fixture(link))).unwrap();
+    }
+    let retry = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+    };
+
+    assert_eq!(
+        3,
+        producer::refresh_feed_with_retry(&db, 1, None, &retry).unwrap()
+    );
+    assert_eq!(
+        0,
+        feeds::read_required(&db, 1).unwrap().consecutive_failures
+    );
+
+    assert!(producer::refresh_feed_with_retry(&db, 2, None, &retry).is_err());
+    let failed = feeds::read_required(&db, 2).unwrap();
+    assert_eq!(1, failed.consecutive_failures);
+    assert!(failed.last_error.is_some());
+}
+
+#[test]
+fn refresh_feed_defers_rate_limited_feeds() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Busy", &fixture("hnrss-org-frontpage.rss?retry_after=3600")),
+    )
+    .unwrap();
+
+    let before = Utc::now();
+    assert!(matches!(
+        producer::refresh_feed(&db, 1, None),
+        Err(Error::RateLimited(_, _))
+    ));
+    let deferred = feeds::read_required(&db, 1).unwrap();
+    let retry_after = deferred.retry_after.unwrap();
+    assert!(retry_after >= before + chrono::Duration::seconds(3599));
+    assert!(retry_after <= Utc::now() + chrono::Duration::seconds(3600));
+    assert_eq!(
+        (0, None),
+        (deferred.consecutive_failures, deferred.last_error)
+    );
+
+    let stale = |at: DateTime<Utc>| {
+        feeds::read_stale(&db, at.fixed_offset())
+            .unwrap()
+            .iter()
+            .map(|x| x.id)
+            .collect::<Vec<_>>()
+    };
+    assert!(stale(Utc::now()).is_empty());
+    assert!(stale(retry_after.with_timezone(&Utc) - chrono::Duration::seconds(1)).is_empty());
+    assert_eq!(vec![1], stale(retry_after.with_timezone(&Utc)));
+
+    db.execute(
+        "UPDATE feeds SET link = ?",
+        [fixture("hnrss-org-frontpage.rss")],
+    )
+    .unwrap();
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+    assert_eq!(None, feeds::read_required(&db, 1).unwrap().retry_after);
+    assert_eq!(vec![1], stale(Utc::now()));
+}
+
+#[test]
+fn refresh_feed_does_not_retry_missing_feeds() {
+    let db = db();
+    feeds::create(&db, &feed("Missing", &fixture("missing.rss"))).unwrap();
+    let retry = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(60),
+    };
+
+    let err = producer::refresh_feed_with_retry(&db, 1, None, &retry).unwrap_err();
+    assert!(!err.is_transient());
+}
+
+#[test]
+fn fetch_full_content_replaces_summaries() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+    producer::refresh_feed(&db, 1, None).unwrap();
+
+    let item = items::read(&db, 1).unwrap().unwrap();
+    assert!(item.is_summary_only());
+    db.execute("UPDATE items SET link = ?", [fixture("article.html")])
+        .unwrap();
+
+    let content = producer::fetch_full_content(&db, 1, None).unwrap();
+    assert!(content.contains("<p>The whole article.</p>"));
+    assert!(!content.contains("<nav>"));
+
+    let item = items::read(&db, 1).unwrap().unwrap();
+    assert_eq!(content, item.description);
+    assert!(!item.is_summary_only());
+    assert!(item.summary.is_some());
+    assert!(matches!(
+        producer::fetch_full_content(&db, 99, None),
+        Err(Error::NotFound(99))
+    ));
+}
+
+#[test]
+fn refresh_item_restores_edited_content_and_keeps_flags() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+    producer::refresh_feed(&db, 1, None).unwrap();
+    let original = items::read(&db, 1).unwrap().unwrap();
+
+    db.execute(
+        "UPDATE items SET title = 'Old title', description = 'Old', status = 'read', is_saved = 1 WHERE id = 1",
+        [],
+    )
+    .unwrap();
+    assert!(producer::refresh_item(&db, 1, None).unwrap());
+    assert!(!producer::refresh_item(&db, 1, None).unwrap());
+
+    let item = items::read(&db, 1).unwrap().unwrap();
+    assert_eq!(
+        (original.title, original.description),
+        (item.title, item.description)
+    );
+    assert!(matches!(item.status, ItemStatus::Read));
+    assert!(item.is_saved);
+
+    db.execute(
+        "UPDATE items SET guid = NULL, link = ? WHERE id = 1",
+        [fixture("article.html")],
+    )
+    .unwrap();
+    assert!(producer::refresh_item(&db, 1, None).unwrap());
+    assert!(items::read(&db, 1)
+        .unwrap()
+        .unwrap()
+        .description
+        .contains("<p>The whole article.</p>"));
+    assert!(matches!(
+        producer::refresh_item(&db, 99, None),
+        Err(Error::NotFound(99))
+    ));
+}
+
+#[test]
+fn collect_notifications_announces_new_items_once() {
+    let db = db();
+    for (title, link) in [
+        ("Hacker News", fixture("hnrss-org-frontpage.rss")),
+        ("Quiet", fixture("formats.atom")),
+    ] {
+        feeds::create(
+            &db,
+            &FeedToCreate {
+                title: title.to_string(),
+                link,
+            },
+        )
+        .unwrap();
+    }
+    feeds::set_notify_on_new(&db, 2, false).unwrap();
+    producer::refresh_feed(&db, 1, None).unwrap();
+    producer::refresh_feed(&db, 2, None).unwrap();
+
+    let announced = producer::collect_notifications(&db).unwrap();
+    assert_eq!(3, announced.len());
+    assert!(announced.iter().all(|x| x.feed.id == 1));
+    assert!(producer::collect_notifications(&db).unwrap().is_empty());
+
+    // Stored timestamps have millisecond precision.
+    std::thread::sleep(Duration::from_millis(5));
+    db.execute("DELETE FROM items WHERE id = 3", []).unwrap();
+    db.execute("UPDATE feeds SET etag = NULL", []).unwrap();
+    producer::refresh_feed(&db, 1, None).unwrap();
+    assert_eq!(1, producer::collect_notifications(&db).unwrap().len());
+}
+
+#[test]
+fn collect_notifications_announces_items_stored_in_the_same_millisecond() {
+    let db = db();
+    create_feed(&db, "https://example.com/feed");
+    create_item(&db, 1, "first");
+    assert_eq!(1, producer::collect_notifications(&db).unwrap().len());
+
+    // As if another connection stored the item right after the first, before the clock ticked.
+    create_item(&db, 1, "second");
+    db.execute(
+        "UPDATE items SET created_at = (SELECT created_at FROM items WHERE id = 1) WHERE id = 2",
+        [],
+    )
+    .unwrap();
+    let announced = producer::collect_notifications(&db).unwrap();
+    assert_eq!(
+        vec!["second"],
+        announced
+            .iter()
+            .map(|x| x.title.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert!(producer::collect_notifications(&db).unwrap().is_empty());
+}
+
+#[test]
+fn refresh_feed_stores_only_the_newest_items_up_to_the_limit() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+    settings::update(
+        &db,
+        &SettingToUpdate {
+            key: SettingKey::MaxItemsPerFetch,
+            value: "1".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(1, producer::refresh_feed(&db, 1, None).unwrap());
+    feeds::set_max_items_per_fetch(&db, 1, Some(2)).unwrap();
+    db.execute("UPDATE feeds SET etag = NULL", []).unwrap();
+    assert_eq!(1, producer::refresh_feed(&db, 1, None).unwrap());
+
+    let titles = items::read_all(&db, &ItemReadOption::default())
+        .unwrap()
+        .into_iter()
+        .map(|x| x.title)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "Hacker Smacker: Friend/foe individual writers on Hacker News",
+            "Writing Linux Modules in Ada – Part 1",
+        ],
+        titles
+    );
+    assert!(matches!(
+        feeds::set_max_items_per_fetch(&db, 1, Some(-1)),
+        Err(Error::InvalidValue(_))
+    ));
+}
+
+#[test]
+fn reset_feed_makes_the_next_refresh_fetch_everything_again() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+    producer::refresh_feed(&db, 1, None).unwrap();
+    db.execute("UPDATE items SET is_saved = 1 WHERE id = 1", [])
+        .unwrap();
+
+    assert_eq!(2, feeds::reset(&db, 1, true).unwrap());
+    let feed = feeds::read_required(&db, 1).unwrap();
+    assert_eq!((None, 0), (feed.etag, feed.checked_at.timestamp()));
+    assert_eq!(
+        1,
+        items::count_all(&db, &ItemReadOption::default()).unwrap()
+    );
+
+    assert_eq!(2, producer::refresh_feed(&db, 1, None).unwrap());
+    assert!(items::read(&db, 1).unwrap().unwrap().is_saved);
+
+    assert_eq!(3, feeds::reset(&db, 1, false).unwrap());
+    assert!(matches!(
+        feeds::reset(&db, 2, false),
+        Err(Error::NotFound(2))
+    ));
+}
+
+#[test]
+fn refresh_feed_skips_unmodified_feeds() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+    let fetched = feeds::read_required(&db, 1).unwrap();
+    assert!(fetched.etag.is_some());
+
+    // Items removed locally are not fetched again while the feed is unchanged.
+    db.execute("DELETE FROM items", []).unwrap();
+    assert_eq!(0, producer::refresh_feed(&db, 1, None).unwrap());
+    let checked = feeds::read_required(&db, 1).unwrap();
+    assert_eq!(fetched.etag, checked.etag);
+    assert!(checked.checked_at >= fetched.checked_at);
+
+    feeds::update_validators(&db, 1, None, None).unwrap();
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+}
+
+#[test]
+fn refresh_feed_stores_the_site_link() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss")),
+    )
+    .unwrap();
+    assert_eq!(None, feeds::read_required(&db, 1).unwrap().html_url);
+
+    producer::refresh_feed(&db, 1, None).unwrap();
+    assert_eq!(
+        Some("https://news.ycombinator.com/".to_string()),
+        feeds::read_required(&db, 1).unwrap().html_url
+    );
+}
+
+#[test]
+fn refresh_feed_keeps_manual_renames() {
+    let db = db();
+    feeds::create(&db, &feed("HN", &fixture("hnrss-org-frontpage.rss"))).unwrap();
+    feeds::update(&db, &FeedToUpdate::for_id(1).title("My news").build()).unwrap();
+
+    producer::refresh_feed(&db, 1, None).unwrap();
+    let feed = feeds::read_required(&db, 1).unwrap();
+    assert_eq!("Hacker News: Front Page", feed.title);
+    assert_eq!("My news", feed.display_title());
+
+    feeds::update(&db, &FeedToUpdate::for_id(1).title(" ").build()).unwrap();
+    assert_eq!(
+        "Hacker News: Front Page",
+        feeds::read_required(&db, 1).unwrap().display_title()
+    );
+}
+
+#[test]
+fn refresh_feed_stores_the_content_type() {
+    let db = db();
+    feeds::create(
+        &db,
+        &feed("Hacker News", &fixture("hnrss-org-frontpage.rss.gz")),
+    )
+    .unwrap();
+    assert_eq!(None, feeds::read_required(&db, 1).unwrap().content_type);
+
+    assert_eq!(3, producer::refresh_feed(&db, 1, None).unwrap());
+    assert_eq!(
+        Some("application/rss+xml".to_string()),
+        feeds::read_required(&db, 1).unwrap().content_type
+    );
+}
+
+#[test]
+fn refresh_feed_discovers_the_websub_hub() {
+    let db = db();
+    for (id, link) in [(1, "websub.rss"), (2, "hnrss-org-frontpage.rss")] {
+        feeds::create(&db, &feed(link, &fixture(link))).unwrap();
+        producer::refresh_feed(&db, id, None).unwrap();
+    }
+
+    let feed = feeds::read_required(&db, 1).unwrap();
+    assert_eq!(Some("https://fixtures.test/hub"), feed.hub_url.as_deref());
+    assert_eq!(
+        Some("https://example.com/feed.xml"),
+        feed.topic_url.as_deref()
+    );
+    producer::subscribe_websub(&db, 1, "https://example.com/callback", None).unwrap();
+
+    assert_eq!(None, feeds::read_required(&db, 2).unwrap().hub_url);
+    assert!(matches!(
+        producer::subscribe_websub(&db, 2, "https://example.com/callback", None),
+        Err(Error::NoWebSubHub(2))
+    ));
+}
+
+#[test]
+fn fetch_favicon_stores_the_site_icon() {
+    let db = db();
+    feeds::create(&db, &feed("Fixtures", &fixture("hnrss-org-frontpage.rss"))).unwrap();
+
+    let icon = producer::fetch_favicon(&db, 1, None).unwrap();
+    assert_eq!(fs::read("src/tests/fixtures/favicon.ico").unwrap(), icon);
+
+    let stored = feeds::read_favicon(&db, 1).unwrap().unwrap();
+    assert_eq!(
+        (icon, Some("image/x-icon".to_string())),
+        (stored.data, stored.content_type)
+    );
+}
+
+#[test]
+fn fetch_favicon_reports_missing_icons() {
+    let db = db();
+    feeds::create(&db, &feed("Example", "https://example.com/feed")).unwrap();
+
+    assert!(matches!(
+        producer::fetch_favicon(&db, 1, None),
+        Err(Error::FaviconNotFound(1))
+    ));
+    assert!(feeds::read_favicon(&db, 1).unwrap().is_none());
+}
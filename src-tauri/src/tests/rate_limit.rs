@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use crate::models::settings::{self, SettingKey, SettingToUpdate};
+use crate::rate_limit::HostRateLimiter;
+
+use super::db;
+
+#[test]
+fn reserve_spaces_out_requests_to_the_same_host() {
+    let limiter = HostRateLimiter::new(60);
+    let now = Instant::now();
+
+    for _ in 0..4 {
+        assert_eq!(Duration::ZERO, limiter.reserve("example.com", now));
+    }
+    assert_eq!(Duration::from_secs(1), limiter.reserve("example.com", now));
+    assert_eq!(Duration::from_secs(2), limiter.reserve("EXAMPLE.com", now));
+
+    // Other hosts have buckets of their own.
+    assert_eq!(Duration::ZERO, limiter.reserve("example.org", now));
+
+    // Waiting refills the bucket, but not past its burst.
+    let later = now + Duration::from_secs(60);
+    for _ in 0..4 {
+        assert_eq!(Duration::ZERO, limiter.reserve("example.com", later));
+    }
+    assert_eq!(
+        Duration::from_secs(1),
+        limiter.reserve("example.com", later)
+    );
+}
+
+#[test]
+fn reserve_does_not_wait_without_a_limit() {
+    let limiter = HostRateLimiter::new(0);
+    let now = Instant::now();
+
+    for _ in 0..100 {
+        assert_eq!(Duration::ZERO, limiter.reserve("example.com", now));
+    }
+
+    limiter.set_limit(1);
+    assert_eq!(Duration::ZERO, limiter.reserve("example.com", now));
+    assert_eq!(Duration::from_secs(60), limiter.reserve("example.com", now));
+}
+
+#[test]
+fn requests_per_host_per_minute_is_a_setting() {
+    let db = db();
+    assert_eq!(60, settings::requests_per_host_per_minute(&db).unwrap());
+
+    settings::update(
+        &db,
+        &SettingToUpdate {
+            key: SettingKey::RequestsPerHostPerMinute,
+            value: "10".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(10, settings::requests_per_host_per_minute(&db).unwrap());
+
+    assert!(settings::update(
+        &db,
+        &SettingToUpdate {
+            key: SettingKey::RequestsPerHostPerMinute,
+            value: "-1".to_string(),
+        },
+    )
+    .is_err());
+}
@@ -0,0 +1,33 @@
+use crate::error::Error;
+use crate::models::settings::{self, SettingKey, SettingToUpdate};
+
+use super::db;
+
+#[test]
+fn pause_all_and_resume_all_toggle_refreshing() {
+    let db = db();
+    assert!(!settings::is_paused(&db).unwrap());
+
+    settings::pause_all(&db).unwrap();
+    assert!(settings::is_paused(&db).unwrap());
+    assert_eq!(
+        "true",
+        settings::read(&db, &SettingKey::RefreshPaused)
+            .unwrap()
+            .value
+    );
+
+    settings::resume_all(&db).unwrap();
+    assert!(!settings::is_paused(&db).unwrap());
+
+    assert!(matches!(
+        settings::update(
+            &db,
+            &SettingToUpdate {
+                key: SettingKey::RefreshPaused,
+                value: "sometimes".to_string(),
+            },
+        ),
+        Err(Error::InvalidValue(_))
+    ));
+}
@@ -1,8 +1,9 @@
-use chrono::DateTime;
+use chrono::{DateTime, Duration, Utc};
 use pretty_assertions::assert_eq;
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::PathBuf};
 
-use crate::syndication::{self, RawItem};
+use crate::error::Error;
+use crate::syndication::{self, FeedFormat, FeedProbe, RawFeed, RawItem, Validators};
 
 fn fixture(path: &str) -> String {
     fs::canonicalize(PathBuf::from(format!("src/tests/fixtures/{}", path)))
@@ -30,24 +31,33 @@ fn fetch_feed_items_rss() {
     assert_eq!(
         vec![
             RawItem {
+                guid: Some("https://news.ycombinator.com/item?id=37288627".to_string()),
                 title: "Hacker Smacker: Friend/foe individual writers on Hacker News".to_string(),
                 author: Some("swyx".to_string()),
                 link: Some("https://github.com/samuelclay/hackersmacker".to_string()),
-                content: Some("\n<p>Article URL: <a href=\"https://github.com/samuelclay/hackersmacker\">https://github.com/samuelclay/hackersmacker</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288627\">https://news.ycombinator.com/item?id=37288627</a></p>\n<p>Points: 36</p>\n<p># Comments: 14</p>\n".to_string()),
+                content: None,
+                summary: Some("\n<p>Article URL: <a href=\"https://github.com/samuelclay/hackersmacker\">https://github.com/samuelclay/hackersmacker</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288627\">https://news.ycombinator.com/item?id=37288627</a></p>\n<p>Points: 36</p>\n<p># Comments: 14</p>\n".to_string()),
+                content_is_html: true,
                 published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T01:33:24+00:00").unwrap()),
             },
             RawItem {
+                guid: Some("https://news.ycombinator.com/item?id=37288446".to_string()),
                 title: "Writing Linux Modules in Ada – Part 1".to_string(),
                 author: Some("slondr".to_string()),
                 link: Some("http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1".to_string()),
-                content: Some("\n<p>Article URL: <a href=\"http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1\">http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288446\">https://news.ycombinator.com/item?id=37288446</a></p>\n<p>Points: 27</p>\n<p># Comments: 5</p>\n".to_string()),
+                content: None,
+                summary: Some("\n<p>Article URL: <a href=\"http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1\">http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288446\">https://news.ycombinator.com/item?id=37288446</a></p>\n<p>Points: 27</p>\n<p># Comments: 5</p>\n".to_string()),
+                content_is_html: true,
                 published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T01:05:24+00:00").unwrap()),
             },
             RawItem {
+                guid: Some("https://news.ycombinator.com/item?id=37288208".to_string()),
                 title: "Federal study links testicular cancer to ‘forever chemicals’".to_string(),
                 author: Some("EA-3167".to_string()),
                 link: Some("https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/".to_string()),
-                content: Some("\n<p>Article URL: <a href=\"https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/\">https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288208\">https://news.ycombinator.com/item?id=37288208</a></p>\n<p>Points: 62</p>\n<p># Comments: 15</p>\n".to_string()),
+                content: None,
+                summary: Some("\n<p>Article URL: <a href=\"https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/\">https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288208\">https://news.ycombinator.com/item?id=37288208</a></p>\n<p>Points: 62</p>\n<p># Comments: 15</p>\n".to_string()),
+                content_is_html: true,
                 published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T00:32:34+00:00").unwrap()),
             },
         ],
@@ -61,27 +71,443 @@ fn fetch_feed_items_atom() {
     assert_eq!(
         vec![
             RawItem {
+                guid: Some("https://news.ycombinator.com/item?id=37288627".to_string()),
                 title: "Hacker Smacker: Friend/foe individual writers on Hacker News".to_string(),
                 author: Some("swyx".to_string()),
                 link: Some("https://github.com/samuelclay/hackersmacker".to_string()),
                 content: Some("\n<p>Article URL: <a href=\"https://github.com/samuelclay/hackersmacker\">https://github.com/samuelclay/hackersmacker</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288627\">https://news.ycombinator.com/item?id=37288627</a></p>\n<p>Points: 36</p>\n<p># Comments: 14</p>\n".to_string()),
+                summary: None,
+                content_is_html: true,
                 published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T01:33:24+00:00").unwrap()),
             },
             RawItem {
+                guid: Some("https://news.ycombinator.com/item?id=37288446".to_string()),
                 title: "Writing Linux Modules in Ada – Part 1".to_string(),
                 author: Some("slondr".to_string()),
                 link: Some("http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1".to_string()),
                 content: Some("\n<p>Article URL: <a href=\"http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1\">http://www.nihamkin.com/2016/10/23/writing-linux-modules-in-ada-part-1/#writing-linux-modules-in-ada-part-1</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288446\">https://news.ycombinator.com/item?id=37288446</a></p>\n<p>Points: 27</p>\n<p># Comments: 5</p>\n".to_string()),
+                summary: None,
+                content_is_html: true,
                 published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T01:05:24+00:00").unwrap()),
             },
             RawItem {
+                guid: Some("https://news.ycombinator.com/item?id=37288208".to_string()),
                 title: "Federal study links testicular cancer to ‘forever chemicals’".to_string(),
                 author: Some("EA-3167".to_string()),
                 link: Some("https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/".to_string()),
                 content: Some("\n<p>Article URL: <a href=\"https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/\">https://undark.org/2023/08/22/federal-study-links-testicular-cancer-to-forever-chemicals/</a></p>\n<p>Comments URL: <a href=\"https://news.ycombinator.com/item?id=37288208\">https://news.ycombinator.com/item?id=37288208</a></p>\n<p>Points: 62</p>\n<p># Comments: 15</p>\n".to_string()),
+                summary: None,
+                content_is_html: true,
                 published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T00:32:34+00:00").unwrap()),
             },
         ],
         items,
     );
 }
+
+#[test]
+fn fetch_feed_rss() {
+    let link = fixture("hnrss-org-frontpage.rss");
+    let feed = syndication::fetch_feed(&link, None).unwrap();
+    assert_eq!(
+        RawFeed {
+            title: "Hacker News: Front Page".to_string(),
+            link,
+            html_url: Some("https://news.ycombinator.com/".to_string()),
+        },
+        feed,
+    );
+}
+
+#[test]
+fn fetch_feed_items_in_every_format() {
+    let item = |guid: Option<&str>| RawItem {
+        guid: guid.map(str::to_string),
+        title: "Hello, formats".to_string(),
+        author: Some("Jane".to_string()),
+        link: Some("https://example.com/posts/1".to_string()),
+        content: Some("<p>The same post everywhere.</p>".to_string()),
+        summary: Some("The same post.".to_string()),
+        content_is_html: true,
+        published_at: Some(DateTime::parse_from_rfc3339("2023-08-28T01:33:24+00:00").unwrap()),
+    };
+
+    for format in ["rss", "atom", "json"] {
+        let link = fixture(&format!("formats.{format}"));
+        assert_eq!(
+            vec![item(Some("https://example.com/posts/1"))],
+            syndication::fetch_feed_items(&link, None).unwrap(),
+            "{format}",
+        );
+        assert_eq!(
+            RawFeed {
+                title: "Formats".to_string(),
+                link: link.clone(),
+                html_url: Some("https://example.com/".to_string()),
+            },
+            syndication::fetch_feed(&link, None).unwrap(),
+            "{format}",
+        );
+    }
+
+    // RSS 1.0 items have no guid.
+    assert_eq!(
+        vec![item(None)],
+        syndication::fetch_feed_items(&fixture("formats.rdf"), None).unwrap(),
+    );
+}
+
+#[test]
+fn fetch_feed_items_tells_text_from_html() {
+    let items = syndication::fetch_feed_items(&fixture("content-types.atom"), None).unwrap();
+    assert_eq!(
+        vec![
+            ("Text", false, Some("Use <b> for bold.")),
+            ("HTML", true, Some("<p>Use <b>bold</b>.</p>")),
+            ("Untyped", false, None),
+        ],
+        items
+            .iter()
+            .map(|x| (x.title.as_str(), x.content_is_html, x.content.as_deref()))
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[test]
+fn fetch_feed_decodes_the_declared_charset() {
+    let feed = syndication::fetch_feed(&fixture("gbk.rss"), None).unwrap();
+    assert_eq!("中文博客", feed.title);
+
+    let items = syndication::fetch_feed_items(&fixture("gbk.rss"), None).unwrap();
+    assert_eq!(
+        vec![(
+            "你好，世界".to_string(),
+            Some("<p>这是第一篇文章。</p>".to_string())
+        )],
+        items
+            .into_iter()
+            .map(|x| (x.title, x.summary))
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[test]
+fn probe_feed_follows_autodiscovery() {
+    assert_eq!(
+        FeedProbe {
+            title: "Formats".to_string(),
+            format: FeedFormat::Rss,
+            item_count: 1,
+            link: "http://fixtures.test/formats.rss".to_string(),
+        },
+        syndication::probe_feed("http://fixtures.test/discovery.html", None).unwrap(),
+    );
+    assert_eq!(
+        FeedFormat::Json,
+        syndication::probe_feed(&fixture("formats.json"), None)
+            .unwrap()
+            .format,
+    );
+}
+
+#[test]
+fn probe_feed_tells_failures_apart() {
+    assert!(matches!(
+        syndication::probe_feed("http://fixtures.test/missing.rss", None),
+        Err(Error::Unreachable(_))
+    ));
+    assert!(matches!(
+        syndication::probe_feed(&fixture("article.html"), None),
+        Err(Error::SyndicationNotFound(_))
+    ));
+    assert!(matches!(
+        syndication::probe_feed(&fixture("empty.rss"), None),
+        Err(Error::EmptyFeed(_))
+    ));
+}
+
+#[test]
+fn fetch_feed_items_gzipped() {
+    assert_eq!(
+        syndication::fetch_feed_items(&fixture("hnrss-org-frontpage.rss"), None).unwrap(),
+        syndication::fetch_feed_items(&fixture("hnrss-org-frontpage.rss.gz"), None).unwrap(),
+    );
+}
+
+#[test]
+fn extract_article_keeps_only_the_article() {
+    let html = r#"
+        <html>
+        <head><script>track()</script><style>p { color: red }</style></head>
+        <body>
+            <nav><a href="/">Home</a></nav>
+            <article>
+                <h1>Title</h1>
+                <!-- share buttons -->
+                <p>First paragraph.</p>
+                <aside>Related posts</aside>
+                <p>Second paragraph.</p>
+            </article>
+            <footer>Copyright</footer>
+        </body>
+        </html>
+    "#;
+
+    let article = syndication::extract_article(html).unwrap();
+    assert_eq!(
+        vec![
+            "<h1>Title</h1>",
+            "<p>First paragraph.</p>",
+            "<p>Second paragraph.</p>"
+        ],
+        article
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        None,
+        syndication::extract_article("<body><script>x()</script></body>")
+    );
+}
+
+#[test]
+fn extract_article_picks_the_longest_of_several_articles() {
+    let html = r#"
+        <body>
+            <article><p>Teaser.</p></article>
+            <article><p>The whole story, told at length.</p></article>
+            <article><p>Another teaser.</p></article>
+        </body>
+    "#;
+
+    assert_eq!(
+        Some("<p>The whole story, told at length.</p>".to_string()),
+        syndication::extract_article(html)
+    );
+}
+
+#[test]
+fn extract_article_parses_the_page_as_a_browser_would() {
+    // Markup in a script's strings or in a comment does not end them, and an unclosed `<body>` is
+    // still the body.
+    let html = r#"
+        <html><body>
+        <script>document.write("</article><article>Ad</article>");</script>
+        <!-- <article>Draft</article> -->
+        <p>Kept <b>text</b>."#;
+
+    assert_eq!(
+        Some("<p>Kept <b>text</b>.</p>".to_string()),
+        syndication::extract_article(html)
+    );
+}
+
+#[test]
+fn sanitize_html_strips_scripts_and_resolves_links() {
+    let html = r#"<p onclick="steal()">Hi <a href='/about' target=_blank>there</a></p>
+<script>alert(1)</script><iframe src="https://ads.example.com"></iframe>
+<a href="javascript:alert(1)">x</a> <a href=" JavaScript:alert(1)">y</a>
+<img src="img/cat.png" alt="A &quot;cat&quot;"><img src="https://t.example.com/p.gif" width="1" height="1">
+<IMG SRC="data:image/svg+xml;base64,PHN2Zz4=" onerror=alert(1)><br/>"#;
+
+    assert_eq!(
+        r#"<p>Hi <a href="https://example.com/about" rel="noopener noreferrer">there</a></p>
+
+<a rel="noopener noreferrer">x</a> <a rel="noopener noreferrer">y</a>
+<img src="https://example.com/posts/img/cat.png" alt="A &quot;cat&quot;">
+<img><br>"#,
+        syndication::sanitize_html(html, Some("https://example.com/posts/1")),
+    );
+}
+
+#[test]
+fn sanitize_html_is_not_fooled_by_nested_tags_or_obfuscated_schemes() {
+    for (html, sanitized) in [
+        (
+            "<scr<script>x</script>ipt>alert(1)</script>",
+            "xipt&gt;alert(1)",
+        ),
+        (
+            "<a href=\"java\tscript:alert(1)\">x</a>",
+            r#"<a rel="noopener noreferrer">x</a>"#,
+        ),
+        (
+            "<ifr<iframe></iframe>ame src=\"java\tscript:alert(1)\">",
+            "ame src=\"java\tscript:alert(1)\"&gt;",
+        ),
+    ] {
+        assert_eq!(
+            sanitized,
+            syndication::sanitize_html(html, Some("https://example.com/posts/1"))
+        );
+    }
+}
+
+#[test]
+fn discover_feed_links() {
+    let html = r#"
+        <html>
+        <head>
+            <link rel="stylesheet" href="/style.css">
+            <link type="application/rss+xml" rel="alternate" href="/feed.xml?a=1&amp;b=2" title="RSS">
+            <LINK REL='alternate' TYPE='application/atom+xml' HREF='https://example.org/atom'>
+            <link rel="alternate" type="text/html" href="/other">
+        </head>
+        </html>
+    "#;
+
+    assert_eq!(
+        vec![
+            "https://example.com/feed.xml?a=1&b=2".to_string(),
+            "https://example.org/atom".to_string(),
+        ],
+        syndication::discover_feed_links(html, "https://example.com/blog/"),
+    );
+}
+
+#[test]
+fn discover_icon_links() {
+    let html = r#"
+        <link rel="stylesheet" href="/style.css">
+        <link rel="shortcut icon" href="/img/favicon.png">
+        <link rel="icon" type="image/svg+xml" href="https://cdn.example.com/icon.svg">
+    "#;
+
+    assert_eq!(
+        vec![
+            "https://example.com/img/favicon.png".to_string(),
+            "https://cdn.example.com/icon.svg".to_string(),
+        ],
+        syndication::discover_icon_links(html, "https://example.com/blog/"),
+    );
+}
+
+#[test]
+fn parse_retry_after_reads_seconds_and_dates() {
+    let now = DateTime::parse_from_rfc3339("2023-08-01T12:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    let in_seconds =
+        |x| (syndication::parse_retry_after(x, now).with_timezone(&Utc) - now).num_seconds();
+
+    assert_eq!(120, in_seconds(Some("120")));
+    assert_eq!(1800, in_seconds(Some("Tue, 01 Aug 2023 12:30:00 GMT")));
+    assert_eq!(0, in_seconds(Some("Tue, 01 Aug 2023 11:00:00 GMT")));
+    assert_eq!(Duration::days(1).num_seconds(), in_seconds(Some("999999")));
+    assert_eq!(Duration::hours(1).num_seconds(), in_seconds(Some("soon")));
+    assert_eq!(Duration::hours(1).num_seconds(), in_seconds(None));
+}
+
+#[test]
+fn check_rate_limit_reads_retry_after_from_429s() {
+    use reqwest::{
+        header::{HeaderMap, RETRY_AFTER},
+        StatusCode,
+    };
+
+    let now = DateTime::parse_from_rfc3339("2023-08-01T12:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    let mut headers = HeaderMap::new();
+    headers.insert(RETRY_AFTER, "120".parse().unwrap());
+    let link = "https://example.com/feed";
+
+    assert!(syndication::check_rate_limit(link, StatusCode::OK, &headers, now).is_ok());
+    assert!(
+        syndication::check_rate_limit(link, StatusCode::SERVICE_UNAVAILABLE, &headers, now).is_ok()
+    );
+    match syndication::check_rate_limit(link, StatusCode::TOO_MANY_REQUESTS, &headers, now) {
+        Err(Error::RateLimited(x, at)) => {
+            assert_eq!(
+                (link, 120),
+                (x.as_str(), (at.with_timezone(&Utc) - now).num_seconds())
+            )
+        }
+        x => panic!("{x:?}"),
+    }
+    match syndication::check_rate_limit(link, StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), now)
+    {
+        Err(Error::RateLimited(_, at)) => assert_eq!(
+            Duration::hours(1).num_seconds(),
+            (at.with_timezone(&Utc) - now).num_seconds()
+        ),
+        x => panic!("{x:?}"),
+    }
+}
+
+#[test]
+fn feed_request_sends_the_feeds_headers_and_validators() {
+    let validators = Validators {
+        etag: Some("\"abc\"".to_string()),
+        last_modified: Some("Tue, 01 Aug 2023 12:00:00 GMT".to_string()),
+    };
+    let headers = HashMap::from([
+        ("Authorization".to_string(), "Bearer secret".to_string()),
+        ("X-Api-Key".to_string(), "key".to_string()),
+    ]);
+
+    let request = syndication::feed_request(
+        &reqwest::blocking::Client::new(),
+        "https://example.com/feed",
+        &validators,
+        &headers,
+    )
+    .unwrap();
+    let header = |name| request.headers().get(name).and_then(|x| x.to_str().ok());
+    assert_eq!("https://example.com/feed", request.url().as_str());
+    assert_eq!(Some("Bearer secret"), header("authorization"));
+    assert_eq!(Some("key"), header("x-api-key"));
+    assert_eq!(Some("\"abc\""), header("if-none-match"));
+    assert_eq!(
+        Some("Tue, 01 Aug 2023 12:00:00 GMT"),
+        header("if-modified-since")
+    );
+
+    let request = syndication::feed_request(
+        &reqwest::blocking::Client::new(),
+        "https://example.com/feed",
+        &Validators::default(),
+        &HashMap::new(),
+    )
+    .unwrap();
+    assert!(request.headers().is_empty());
+}
+
+#[test]
+fn websub_request_posts_a_subscription_form() {
+    let request = syndication::websub_request(
+        &reqwest::blocking::Client::new(),
+        "https://hub.example.com/",
+        "https://example.com/feed?a=1&b=2",
+        "https://collie.example.com/websub/1",
+    )
+    .unwrap();
+    assert_eq!(reqwest::Method::POST, *request.method());
+    assert_eq!("https://hub.example.com/", request.url().as_str());
+    assert_eq!(
+        Some("application/x-www-form-urlencoded"),
+        request
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+    );
+    assert_eq!(
+        Some(
+            "hub.mode=subscribe\
+             &hub.topic=https%3A%2F%2Fexample.com%2Ffeed%3Fa%3D1%26b%3D2\
+             &hub.callback=https%3A%2F%2Fcollie.example.com%2Fwebsub%2F1"
+                .as_bytes()
+        ),
+        request.body().and_then(|x| x.as_bytes())
+    );
+
+    assert!(syndication::websub_request(
+        &reqwest::blocking::Client::new(),
+        "not a hub",
+        "https://example.com/feed",
+        "https://collie.example.com/websub/1",
+    )
+    .is_err());
+}
@@ -0,0 +1,49 @@
+use pretty_assertions::assert_eq;
+
+use crate::models::tags;
+
+use super::{create_feed, db};
+
+#[test]
+fn tags_are_case_insensitively_unique() {
+    let db = db();
+    create_feed(&db, "https://example.com/b");
+    create_feed(&db, "https://example.com/a");
+
+    tags::add_tag(&db, 1, "Tech").unwrap();
+    tags::add_tag(&db, 2, "tech").unwrap();
+    tags::add_tag(&db, 2, " TECH ").unwrap();
+    tags::add_tag(&db, 2, "News").unwrap();
+
+    assert_eq!(vec!["Tech".to_string()], tags::read_tags(&db, 1).unwrap());
+    assert_eq!(
+        vec!["News".to_string(), "Tech".to_string()],
+        tags::read_tags(&db, 2).unwrap()
+    );
+
+    let titles = tags::read_feeds_by_tag(&db, "TECH")
+        .unwrap()
+        .into_iter()
+        .map(|x| x.title)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec!["https://example.com/a", "https://example.com/b"],
+        titles
+    );
+}
+
+#[test]
+fn remove_tag_deletes_unused_tags() {
+    let db = db();
+    create_feed(&db, "https://example.com/a");
+    tags::add_tag(&db, 1, "News").unwrap();
+
+    assert_eq!(1, tags::remove_tag(&db, 1, "news").unwrap());
+    assert!(tags::read_tags(&db, 1).unwrap().is_empty());
+    assert!(tags::read_feeds_by_tag(&db, "News").unwrap().is_empty());
+
+    let remaining: i64 = db
+        .query_row("SELECT COUNT(*) FROM tags", [], |x| x.get(0))
+        .unwrap();
+    assert_eq!(0, remaining);
+}
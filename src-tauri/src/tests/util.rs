@@ -0,0 +1,61 @@
+use pretty_assertions::assert_eq;
+
+use crate::util::{normalize_url, sort_key};
+
+#[test]
+fn normalize_url_lowercases_scheme_and_host() {
+    assert_eq!(
+        "https://example.com/Feed.xml",
+        normalize_url("HTTPS://Example.COM/Feed.xml")
+    );
+}
+
+#[test]
+fn normalize_url_drops_default_ports() {
+    assert_eq!(
+        "https://example.com/feed",
+        normalize_url("https://example.com:443/feed")
+    );
+    assert_eq!(
+        "http://example.com/feed",
+        normalize_url("http://example.com:80/feed")
+    );
+    assert_eq!(
+        "http://example.com:8080/feed",
+        normalize_url("http://example.com:8080/feed")
+    );
+}
+
+#[test]
+fn normalize_url_strips_trailing_slashes() {
+    assert_eq!("https://example.com", normalize_url("https://example.com/"));
+    assert_eq!("https://example.com", normalize_url("https://example.com"));
+    assert_eq!(
+        "https://example.com/a/b?q=1",
+        normalize_url("https://example.com/a/b//?q=1")
+    );
+}
+
+#[test]
+fn normalize_url_strips_tracking_parameters() {
+    assert_eq!(
+        "https://example.com/feed?page=2",
+        normalize_url("https://example.com/feed?utm_source=x&page=2&UTM_Medium=y")
+    );
+    assert_eq!(
+        "https://example.com/feed",
+        normalize_url("https://example.com/feed?utm_campaign=z")
+    );
+}
+
+#[test]
+fn normalize_url_trims_unparsable_input() {
+    assert_eq!("not a url", normalize_url("  not a url "));
+}
+
+#[test]
+fn sort_key_folds_case_and_accents() {
+    assert_eq!("oko", sort_key(" Öko "));
+    assert_eq!("creme brulee", sort_key("Crème Brûlée"));
+    assert_eq!("zebra", sort_key("ZEBRA"));
+}
@@ -0,0 +1,48 @@
+use reqwest::Url;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Normalizes a URL so that trivially different spellings of the same address compare equal: the
+/// scheme and host are lowercased, default ports are dropped, `utm_*` tracking parameters are
+/// removed and trailing slashes are removed from the path. Anything that does not parse as a URL is
+/// only trimmed.
+pub fn normalize_url(link: &str) -> String {
+    let link = link.trim();
+    let Ok(mut url) = Url::parse(link) else {
+        return link.to_string();
+    };
+
+    if url.query().is_some() {
+        let kept = url
+            .query_pairs()
+            .filter(|(key, _)| !key.to_ascii_lowercase().starts_with("utm_"))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect::<Vec<_>>();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+
+    let is_bare = url.path() == "/" && url.query().is_none() && url.fragment().is_none();
+    let normalized = String::from(url);
+    if is_bare {
+        normalized.trim_end_matches('/').to_string()
+    } else {
+        normalized
+    }
+}
+
+/// Turns a title into a key that sorts the way people expect: case and accents are ignored, so
+/// "öko" sorts with "Oko", before "Zebra".
+pub fn sort_key(title: &str) -> String {
+    title
+        .trim()
+        .nfkd()
+        .filter(|x| !is_combining_mark(*x))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
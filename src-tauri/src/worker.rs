@@ -7,30 +7,39 @@ use tauri::Manager;
 
 use tauri::api::notification::Notification;
 
-use crate::models::database::open_connection;
-use crate::models::items::ItemToCreate;
+use crate::models::items::Item;
 use crate::models::settings;
 use crate::models::settings::SettingKey;
-use crate::producer::create_new_items;
+use crate::producer::{collect_notifications, create_new_items};
+use crate::DbState;
 
 pub fn start(app: &App) {
     let app_handle = app.handle();
     let app_id = app.config().tauri.bundle.identifier.clone();
 
-    let app_data_dir = app_handle.path_resolver().app_data_dir().unwrap();
-    let db = open_connection(&app_data_dir).unwrap();
+    let pool = app.state::<DbState>().db.clone();
 
     thread::spawn(move || loop {
-        let inserted = create_new_items(&db, proxy(&db).as_deref());
-        if !inserted.is_empty() {
-            if notification(&db) {
-                notify(&app_id, &inserted);
-            }
+        let frequency = match pool.get() {
+            Ok(db) if settings::is_paused(&db).unwrap_or(false) => polling_frequency(&db),
+            Ok(db) => {
+                let inserted = create_new_items(&db, proxy(&db).as_deref());
+                if !inserted.is_empty() {
+                    let _ = app_handle.emit_all("feed_updated", ());
+                }
+                // Checkpoints move on while notifications are off, so turning them back on does
+                // not announce everything that arrived in the meantime.
+                let items = collect_notifications(&db).unwrap_or_default();
+                if !items.is_empty() && notification(&db) {
+                    notify(&app_id, &items);
+                }
 
-            let _ = app_handle.emit_all("feed_updated", ());
-        }
+                polling_frequency(&db)
+            }
+            Err(_) => 120,
+        };
 
-        thread::sleep(time::Duration::from_secs(polling_frequency(&db)));
+        thread::sleep(time::Duration::from_secs(frequency));
     });
 }
 
@@ -57,7 +66,7 @@ fn notification(db: &Connection) -> bool {
         .unwrap_or(true)
 }
 
-fn notify(app_id: &str, args: &[ItemToCreate]) {
+fn notify(app_id: &str, args: &[Item]) {
     if args.len() <= 3 {
         for arg in args {
             let _ = Notification::new(app_id)
@@ -1,16 +1,78 @@
 import { invoke } from "@tauri-apps/api/tauri";
 
 export enum FeedStatus {
-  SUBSCRIBED = "Subscribed",
-  UNSUBSCRIBED = "Unsubscribed",
+  SUBSCRIBED = "subscribed",
+  UNSUBSCRIBED = "unsubscribed",
 }
 
 export interface Feed {
     id: number,
     title: string,
+    user_title: string | null,
     link: string,
     status: FeedStatus,
     checked_at: string,
+    fetch_interval_minutes: number,
+    deleted_at: string | null,
+    etag: string | null,
+    last_modified: string | null,
+    html_url: string | null,
+    last_error: string | null,
+    consecutive_failures: number,
+    position: number,
+    content_type: string | null,
+    is_muted: boolean,
+    headers: { [name: string]: string },
+    created_at: string,
+    updated_at: string,
+    notify_on_new: boolean,
+    last_notified_at: string | null,
+    last_notified_item_id: number | null,
+    max_items_per_fetch: number | null,
+    hub_url: string | null,
+    topic_url: string | null,
+    retry_after: string | null,
+    last_read_item_id: number | null,
+}
+
+// The shape of `Feed` this frontend is built against. It must match the backend's
+// `FEED_SCHEMA_VERSION`.
+export const FEED_SCHEMA_VERSION = 3;
+
+export interface FeedPayload<T> {
+    schema_version: number,
+    data: T,
+}
+
+export function unwrapFeedPayload<T>(payload: FeedPayload<T>): T {
+  if (payload.schema_version !== FEED_SCHEMA_VERSION) {
+    console.warn(`Feeds are in schema version ${payload.schema_version}, expected ${FEED_SCHEMA_VERSION}. Reload to update the app.`);
+  }
+
+  return payload.data;
+}
+
+export interface FeedSummary extends Feed {
+    unread_count: number,
+    has_favicon: boolean,
+}
+
+export enum FeedFormat {
+    RSS = "Rss",
+    ATOM = "Atom",
+    JSON = "Json",
+}
+
+export interface FeedProbe {
+    title: string,
+    format: FeedFormat,
+    item_count: number,
+    link: string,
+}
+
+export interface Favicon {
+    data: number[],
+    content_type: string | null,
 }
 
 export interface FeedToCreate {
@@ -23,14 +85,72 @@ export interface FeedToUpdate {
     title?: string | null,
     link?: string | null,
     status?: FeedStatus | null,
+    fetch_interval_minutes?: number | null,
+    html_url?: string | null,
+}
+
+export async function createFeed(arg: FeedToCreate): Promise<Feed | null> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed>>("create_feed", { arg }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function probeFeed(link: string): Promise<FeedProbe | null> {
+  try {
+    return invoke("probe_feed", { link });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
 }
 
-export async function createFeed(arg: FeedToCreate) {
+export async function refreshFeed(id: number): Promise<number> {
   try {
-    await invoke("create_feed", { arg });
+    return invoke("refresh_feed", { id });
   } catch (e) {
     // Do nothing
   }
+
+  return 0;
+}
+
+export async function subscribeWebSub(feedId: number, callback: string) {
+  try {
+    await invoke("subscribe_websub", { feedId, callback });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export interface RefreshProgress {
+    feed: number,
+    new_items: number,
+    error?: string | null,
+    completed: number,
+    total: number,
+}
+
+export interface RefreshReport {
+    total: number,
+    succeeded: number,
+    failed: number,
+    new_items: number,
+    errors: [number, string][],
+}
+
+export async function refreshAllFeeds(): Promise<RefreshReport | null> {
+  try {
+    return invoke("refresh_all_feeds");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
 }
 
 export async function updateFeed(arg: FeedToUpdate) {
@@ -41,9 +161,67 @@ export async function updateFeed(arg: FeedToUpdate) {
   }
 }
 
+export async function editFeed(feed: Feed) {
+  try {
+    await invoke("edit_feed", { feed });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function fetchFeedFavicon(id: number): Promise<number[] | null> {
+  try {
+    return invoke("fetch_feed_favicon", { id });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function readFeedFavicon(id: number): Promise<Favicon | null> {
+  try {
+    return invoke("read_feed_favicon", { id });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
 export async function readAllFeeds(): Promise<Feed[]> {
   try {
-    return invoke("read_all_feeds");
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_all_feeds"));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function readFeedsIncludingUnsubscribed(): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_feeds_including_unsubscribed"));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function findDuplicateFeeds(): Promise<Feed[][]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[][]>>("find_duplicate_feeds"));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function readSidebar(): Promise<FeedSummary[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<FeedSummary[]>>("read_sidebar"));
   } catch (e) {
     // Do nothing
   }
@@ -51,9 +229,29 @@ export async function readAllFeeds(): Promise<Feed[]> {
   return [];
 }
 
+export async function readFeedsByStatus(status: FeedStatus): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_feeds_by_status", { status }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function countFeedsByStatus(): Promise<Partial<Record<FeedStatus, number>>> {
+  try {
+    return invoke("count_feeds_by_status");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return {};
+}
+
 export async function readFeed(id: number): Promise<Feed | null> {
   try {
-    return invoke("read_feed", { id });
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed | null>>("read_feed", { id }));
   } catch (e) {
     // Do nothing
   }
@@ -61,6 +259,90 @@ export async function readFeed(id: number): Promise<Feed | null> {
   return null;
 }
 
+export async function subscribeFeed(id: number) {
+  try {
+    await invoke("subscribe_feed", { id });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function unsubscribeFeed(id: number) {
+  try {
+    await invoke("unsubscribe_feed", { id });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function bulkUnsubscribeFeeds(ids: number[]): Promise<number> {
+  try {
+    return invoke("bulk_unsubscribe_feeds", { ids });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return 0;
+}
+
+export async function setFeedMuted(id: number, isMuted: boolean) {
+  try {
+    await invoke("set_feed_muted", { id, isMuted });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function setFeedNotifyOnNew(id: number, notifyOnNew: boolean) {
+  try {
+    await invoke("set_feed_notify_on_new", { id, notifyOnNew });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function setReadPosition(feedId: number, itemId: number) {
+  try {
+    await invoke("set_read_position", { feedId, itemId });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function setFeedMaxItemsPerFetch(id: number, limit: number | null) {
+  try {
+    await invoke("set_feed_max_items_per_fetch", { id, limit });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function setFeedHeaders(id: number, headers: { [name: string]: string }) {
+  try {
+    await invoke("set_feed_headers", { id, headers });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function isFeedSubscribed(id: number): Promise<boolean | null> {
+  try {
+    return invoke("is_feed_subscribed", { id });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function reorderFeed(id: number, position: number) {
+  try {
+    await invoke("reorder_feed", { id, position });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
 export async function deleteFeed(id: number) {
   try {
     await invoke("delete_feed", { id });
@@ -68,3 +350,144 @@ export async function deleteFeed(id: number) {
     // Do nothing
   }
 }
+
+export async function bulkDeleteFeeds(ids: number[]): Promise<number> {
+  try {
+    return invoke("bulk_delete_feeds", { ids });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return 0;
+}
+
+export async function readArchivedFeeds(): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_archived_feeds"));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function readUnhealthyFeeds(minFailures: number): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_unhealthy_feeds", { minFailures }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function readRecentFeeds(limit: number): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_recent_feeds", { limit }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function resetFeed(id: number, keepSaved: boolean): Promise<number> {
+  try {
+    return invoke("reset_feed", { id, keepSaved });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return 0;
+}
+
+export async function restoreFeed(id: number) {
+  try {
+    await invoke("restore_feed", { id });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function purgeFeed(id: number) {
+  try {
+    await invoke("purge_feed", { id });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function mergeFeeds(keep: number, remove: number): Promise<number> {
+  try {
+    return invoke("merge_feeds", { keep, remove });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return 0;
+}
+
+export async function searchFeeds(query: string): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("search_feeds", { query }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function searchFeedsByTitle(prefix: string, limit: number): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("search_feeds_by_title", { prefix, limit }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function importOpml(xml: string): Promise<number[]> {
+  try {
+    return invoke("import_opml", { xml });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export interface BookmarkImport {
+    subscribed: [string, number][],
+    skipped: [string, string][],
+}
+
+export async function importBookmarks(html: string): Promise<BookmarkImport | null> {
+  try {
+    return invoke("import_bookmarks", { html });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function exportOpml(): Promise<string | null> {
+  try {
+    return invoke("export_opml");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function exportFeedMarkdown(feedId: number): Promise<string | null> {
+  try {
+    return invoke("export_feed_markdown", { feedId });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
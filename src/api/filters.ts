@@ -0,0 +1,57 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+export enum FilterAction {
+  MARK_READ = "MarkRead",
+  HIDE = "Hide",
+}
+
+export interface FilterRule {
+  id: number,
+  pattern: string,
+  action: FilterAction,
+  feed: number | null,
+}
+
+export interface FilterRuleToCreate {
+  pattern: string,
+  action: FilterAction,
+  feed: number | null,
+}
+
+export type FilterRuleToUpdate = FilterRule;
+
+export async function createFilterRule(arg: FilterRuleToCreate): Promise<number | null> {
+  try {
+    return invoke("create_filter_rule", { arg });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function readFilterRules(): Promise<FilterRule[]> {
+  try {
+    return invoke("read_filter_rules");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function updateFilterRule(arg: FilterRuleToUpdate) {
+  try {
+    await invoke("update_filter_rule", { arg });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function deleteFilterRule(id: number) {
+  try {
+    await invoke("delete_filter_rule", { id });
+  } catch (e) {
+    // Do nothing
+  }
+}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/tauri";
+import { Favicon } from "./feeds";
 
 export enum ItemStatus {
   UNREAD = "Unread",
@@ -14,20 +15,31 @@ export interface ItemFeed {
 export interface Item {
   id: number,
   fingerprint: string,
+  guid?: string | null,
   author?: string | null,
   title: string,
   description: string,
+  content_is_html: boolean,
+  summary?: string | null,
   link: string,
   status: ItemStatus,
   is_saved: boolean,
+  is_hidden: boolean,
   published_at: string,
+  created_at: string,
   feed: ItemFeed,
 }
 
+export interface TimelineItem extends Item {
+  feed_favicon: Favicon | null,
+}
+
 export interface ItemToCreate {
   author?: string | null,
   title: string,
   description: string,
+  content_is_html: boolean,
+  summary?: string | null,
   link: string,
   status: ItemStatus,
   pulished_at: string,
@@ -73,6 +85,7 @@ export interface ItemReadOption {
   order_by?: ItemOrder | null,
   limit?: number | null,
   offset?: number | null,
+  include_hidden?: boolean,
 }
 
 export async function readItems(opt: ItemReadOption): Promise<Item[]> {
@@ -85,6 +98,16 @@ export async function readItems(opt: ItemReadOption): Promise<Item[]> {
   return  [];
 }
 
+export async function readItemsByIds(ids: number[]): Promise<Item[]> {
+  try {
+    return invoke("read_items_by_ids", { ids });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
 export async function countItems(opt: ItemReadOption): Promise<number> {
   try {
     return invoke("count_all_items", { opt: { ...opt } });
@@ -95,6 +118,16 @@ export async function countItems(opt: ItemReadOption): Promise<number> {
   return  0
 }
 
+export async function countUnreadItems(feed?: number | null): Promise<number> {
+  try {
+    return invoke("count_unread_items", { feed });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return 0;
+}
+
 export async function save(id: number) {
   try {
     await invoke("update_item", { arg: { id, is_saved: true } });
@@ -111,6 +144,36 @@ export async function unsave(id: number) {
   }
 }
 
+export async function markAllAsRead(): Promise<number> {
+  try {
+    return invoke("mark_all_items_read");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return 0;
+}
+
+export async function toggleStar(id: number): Promise<boolean | null> {
+  try {
+    return invoke("toggle_item_star", { id });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function readStarredItems(): Promise<Item[]> {
+  try {
+    return invoke("read_starred_items");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
 export async function markAs(ids: number[], status: ItemStatus) {
   try {
     if (ids.length === 1) {
@@ -122,3 +185,68 @@ export async function markAs(ids: number[], status: ItemStatus) {
     // Do nothing
   }
 }
+
+export async function readItemsBetween(feed: number | null, since: string, until: string): Promise<Item[]> {
+  try {
+    return invoke("read_items_between", { feed, since, until });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function readTimeline(offset: number, limit: number, unreadOnly: boolean): Promise<TimelineItem[]> {
+  try {
+    return invoke("read_timeline", { offset, limit, unreadOnly });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export interface TimelineCursor {
+  published_at: string,
+  id: number,
+}
+
+export async function readTimelineAfter(cursor: TimelineCursor | null, limit: number, unreadOnly: boolean): Promise<[TimelineItem[], TimelineCursor | null]> {
+  try {
+    return invoke("read_timeline_after", { cursor, limit, unreadOnly });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [[], null];
+}
+
+export async function searchItems(query: string): Promise<Item[]> {
+  try {
+    return invoke("search_items", { query });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function fetchFullContent(id: number): Promise<string | null> {
+  try {
+    return invoke("fetch_item_full_content", { id });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function refreshItem(itemId: number): Promise<boolean> {
+  try {
+    return invoke("refresh_item", { itemId });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return false;
+}
@@ -6,7 +6,10 @@ export enum SettingKey {
   DB_SCHEME_VERSION = "DbSchemeVersion",
   THEME = "Theme",
   ITEMS_ORDER = "ItemsOrder",
-  PROXY="Proxy"
+  PROXY="Proxy",
+  REFRESH_PAUSED = "RefreshPaused",
+  MAX_ITEMS_PER_FETCH = "MaxItemsPerFetch",
+  REQUESTS_PER_HOST_PER_MINUTE = "RequestsPerHostPerMinute",
 }
 
 export interface Setting {
@@ -16,6 +19,21 @@ export interface Setting {
 
 export type SettingToUpdate = Setting;
 
+export interface DbStats {
+  page_count: number,
+  page_size: number,
+  freelist_count: number,
+  size_bytes: number,
+  feed_count: number,
+  item_count: number,
+}
+
+export interface ImportSummary {
+  feeds_created: number,
+  items_created: number,
+  items_updated: number,
+}
+
 export async function readAllSettings(): Promise<Setting[]> {
   try {
     return invoke("read_all_settings");
@@ -43,3 +61,57 @@ export async function updateSetting(arg: SettingToUpdate) {
     // Do nothing
   }
 }
+
+export async function readDatabaseStats(): Promise<DbStats | null> {
+  try {
+    return invoke("read_database_stats");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function compactDatabase() {
+  try {
+    await invoke("compact_database");
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function pauseRefreshing() {
+  try {
+    await invoke("pause_refreshing");
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function resumeRefreshing() {
+  try {
+    await invoke("resume_refreshing");
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function exportState(): Promise<string | null> {
+  try {
+    return invoke("export_state");
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
+
+export async function importState(json: string): Promise<ImportSummary | null> {
+  try {
+    return invoke("import_state", { json });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return null;
+}
@@ -0,0 +1,38 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { Feed, FeedPayload, unwrapFeedPayload } from "./feeds";
+
+export async function addFeedTag(feed: number, name: string) {
+  try {
+    await invoke("add_feed_tag", { feed, name });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function removeFeedTag(feed: number, name: string) {
+  try {
+    await invoke("remove_feed_tag", { feed, name });
+  } catch (e) {
+    // Do nothing
+  }
+}
+
+export async function readFeedTags(feed: number): Promise<string[]> {
+  try {
+    return invoke("read_feed_tags", { feed });
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
+
+export async function readFeedsByTag(name: string): Promise<Feed[]> {
+  try {
+    return unwrapFeedPayload(await invoke<FeedPayload<Feed[]>>("read_feeds_by_tag", { name }));
+  } catch (e) {
+    // Do nothing
+  }
+
+  return [];
+}
@@ -24,13 +24,13 @@ function Feeds() {
 
   const createFeed = async () => {
     await api.createFeed({ title: "auto", link: linkToCreate() });
-    setFeeds(await api.readAllFeeds());
+    setFeeds(await api.readFeedsIncludingUnsubscribed());
     setLinkToCreate("");
   };
 
   const updateFeed = async (id: number) => {
     await api.updateFeed({ id, title: titleToUpdate(), link: linkToUpdate() } );
-    setFeeds(await api.readAllFeeds());
+    setFeeds(await api.readFeedsIncludingUnsubscribed());
     setIdToUpdate(null);
     setTitleToUpdate(null);
     setLinkToUpdate(null);
@@ -45,18 +45,18 @@ function Feeds() {
         await api.updateFeed({ id: feed.id, status: api.FeedStatus.SUBSCRIBED } );
     }
 
-    setFeeds(await api.readAllFeeds());
+    setFeeds(await api.readFeedsIncludingUnsubscribed());
   };
 
   const deleteFeed = async (feed: api.Feed) => {
-    if (await confirm(`A feed "${feed.title}" and their all items will be deleted. Are you sure?`)) {
+    if (await confirm(`A feed "${feed.user_title ?? feed.title}" and their all items will be deleted. Are you sure?`)) {
       await api.deleteFeed(feed.id);
-      setFeeds(await api.readAllFeeds());
+      setFeeds(await api.readFeedsIncludingUnsubscribed());
     }
   };
 
   onMount(async () => {
-    setFeeds(await api.readAllFeeds());
+    setFeeds(await api.readFeedsIncludingUnsubscribed());
   });
 
   return (
@@ -80,10 +80,10 @@ function Feeds() {
             <div class="row">
               <Switch>
                 <Match when={feed.id !== idToUpdate()}>
-                  <strong><A href={feed.link} target="_blank">{feed.title}</A></strong>
+                  <strong><A href={feed.link} target="_blank">{feed.user_title ?? feed.title}</A></strong>
                 </Match>
                 <Match when={feed.id === idToUpdate()}>
-                  <input type="text" value={feed.title}
+                  <input type="text" value={feed.user_title ?? feed.title}
                     onInput={(e) => setTitleToUpdate(e.currentTarget.value)} />
                   <input type="text" value={feed.link}
                     onInput={(e) => setLinkToUpdate(e.currentTarget.value)} />
@@ -1,6 +1,6 @@
 import { listen } from '@tauri-apps/api/event';
 import { A, useParams } from '@solidjs/router';
-import { createSignal, For, Match, onMount, Show, Switch } from "solid-js";
+import { createEffect, createSignal, For, Match, onMount, Show, Switch } from "solid-js";
 import DOMPurify from 'dompurify';
 
 import dayjs from 'dayjs';
@@ -79,6 +79,9 @@ function Items(props: Props) {
   const selectItem = (item: api.Item) => {
     setSelectedItem(item);
     markAs([item], api.ItemStatus.READ)
+    if (props.type === ItemType.FEED) {
+      feedApi.setReadPosition(item.feed.id, item.id);
+    }
 
     const resize = (e: MouseEvent) => {
       e.preventDefault();
@@ -127,6 +130,21 @@ function Items(props: Props) {
     }
   });
 
+  // Comes back to where the feed was left off once its item is rendered, only once per visit.
+  const [restoredPosition, setRestoredPosition] = createSignal(false);
+  createEffect(() => {
+    const target = feed()?.last_read_item_id;
+    if (restoredPosition() || !target || !items().some(x => x.id === target)) return;
+
+    queueMicrotask(() => {
+      const element = document.getElementById(`item-${target}`);
+      if (element) {
+        element.scrollIntoView({ block: "center" });
+        setRestoredPosition(true);
+      }
+    });
+  });
+
   // eslint-disable-next-line solid/reactivity
   listen('feed_updated', async () => loadItems());
 
@@ -137,7 +155,7 @@ function Items(props: Props) {
           <Match when={props.type == ItemType.FEED}>
             <h2>
               <a onClick={() => history.back()}>←</a>
-              <span> {`${feed() ? feed()?.title : 'Feed'} (${count()})`}</span>
+              <span> {`${feed() ? feed()?.user_title ?? feed()?.title : 'Feed'} (${count()})`}</span>
             </h2>
           </Match>
         </Switch>
@@ -158,7 +176,7 @@ function Items(props: Props) {
         </div>
         <ul>
           <For each={items()}>{(item: api.Item) =>
-            <li class={`${item.status == api.ItemStatus.READ ? "lowp" : ""} ${(selectedItem() && selectedItem()?.id == item.id) ? "selected" : ""}`}>
+            <li id={`item-${item.id}`} class={`${item.status == api.ItemStatus.READ ? "lowp" : ""} ${(selectedItem() && selectedItem()?.id == item.id) ? "selected" : ""}`}>
               <strong><a href={item.link} target="_blank"
                 onClick={() => markAs([item], api.ItemStatus.READ)}>
                 {item.title} <small class="hostname">({new URL(item.link).hostname})</small>
@@ -205,9 +223,13 @@ function Items(props: Props) {
             <span>{selectedItem()?.title}</span>
             <button onClick={() => setSelectedItem(null)}>✖</button>
           </h2>
-          {/* eslint-disable-next-line solid/no-innerhtml*/}
-          <div innerHTML={DOMPurify.sanitize(selectedItem()?.description ?? "")
-            .replace(/href="http(s?).*"/g, "target=\"_blank\" $&")} />
+          <Show when={selectedItem()?.content_is_html} fallback={
+            <div class="plain-text">{selectedItem()?.description}</div>
+          }>
+            {/* eslint-disable-next-line solid/no-innerhtml*/}
+            <div innerHTML={DOMPurify.sanitize(selectedItem()?.description ?? "")
+              .replace(/href="http(s?).*"/g, "target=\"_blank\" $&")} />
+          </Show>
         </div>
       </Show>
     </div>
@@ -19,6 +19,9 @@ function Settings() {
     [api.SettingKey.THEME]: "",
     [api.SettingKey.ITEMS_ORDER]: "",
     [api.SettingKey.PROXY]: "",
+    [api.SettingKey.REFRESH_PAUSED]: "",
+    [api.SettingKey.MAX_ITEMS_PER_FETCH]: "",
+    [api.SettingKey.REQUESTS_PER_HOST_PER_MINUTE]: "",
   });
 
   const keyToText = (key: api.SettingKey) => {
@@ -31,6 +34,10 @@ function Settings() {
         return "Theme";
       case api.SettingKey.PROXY:
         return "Proxy"
+      case api.SettingKey.MAX_ITEMS_PER_FETCH:
+        return "Items per fetch";
+      case api.SettingKey.REQUESTS_PER_HOST_PER_MINUTE:
+        return "Requests per host";
       default:
         return "";
     }
@@ -47,6 +54,10 @@ function Settings() {
         break;
       case api.SettingKey.NOTIFICATION:
         if (value !== "1" && value !== "0") return false;
+        break;
+      case api.SettingKey.MAX_ITEMS_PER_FETCH:
+      case api.SettingKey.REQUESTS_PER_HOST_PER_MINUTE:
+        if (!Number.isInteger(Number(value)) || Number(value) < 0) return false;
     }
 
     return true;
@@ -140,6 +151,20 @@ function Settings() {
                        })}/>
                 {SaveButton(setting)}
               </Match>
+              <Match when={setting.key === api.SettingKey.MAX_ITEMS_PER_FETCH}>
+                <span><strong>{keyToText(setting.key)}</strong>: Store at most</span>
+                <input type="number" min="0" value={newSettings()[setting.key]}
+                  onInput={(e) => setNewSettings({ ...newSettings(), [setting.key]: e.currentTarget.value })} /> <span>of the newest items each time a feed is checked.</span>
+                {SaveButton(setting)}
+                <small>0 stores them all. A feed can set its own limit.</small>
+              </Match>
+              <Match when={setting.key === api.SettingKey.REQUESTS_PER_HOST_PER_MINUTE}>
+                <span><strong>{keyToText(setting.key)}</strong>: Send at most</span>
+                <input type="number" min="0" value={newSettings()[setting.key]}
+                  onInput={(e) => setNewSettings({ ...newSettings(), [setting.key]: e.currentTarget.value })} /> <span>requests a minute to the same site.</span>
+                {SaveButton(setting)}
+                <small>0 does not limit them. Different sites are checked in parallel.</small>
+              </Match>
             </Switch>
           </li>
         }</For>